@@ -1,5 +1,6 @@
+use crate::storage::WorkspaceManager;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::Manager;
 
 /*
@@ -59,14 +60,15 @@ fn handle_asset_request<R: tauri::Runtime>(
             .unwrap();
     }
 
-    // Resolve to actual file path
-    let app_data_dir = match app_handle.path().app_data_dir() {
-        Ok(dir) => {
-            eprintln!("App data dir: {:?}", dir);
+    // Resolve to actual file path, assets live inside the open workspace folder
+    let workspace_dir = match app_handle.try_state::<WorkspaceManager>() {
+        Some(workspaces) => {
+            let dir = PathBuf::from(workspaces.current().path);
+            eprintln!("Workspace dir: {:?}", dir);
             dir
         }
-        Err(e) => {
-            eprintln!("Failed to get app data dir: {}", e);
+        None => {
+            eprintln!("Workspace manager not ready yet");
             return tauri::http::Response::builder()
                 .status(500)
                 .body(Vec::new())
@@ -74,7 +76,7 @@ fn handle_asset_request<R: tauri::Runtime>(
         }
     };
 
-    let full_path = app_data_dir.join(&file_path);
+    let full_path = workspace_dir.join(&file_path);
 
     // read file
     let bytes = match fs::read(&full_path) {
@@ -105,7 +107,7 @@ fn handle_asset_request<R: tauri::Runtime>(
         .unwrap()
 }
 
-fn get_type(path: &PathBuf) -> &'static str {
+fn get_type(path: &Path) -> &'static str {
     match path.extension().and_then(|s| s.to_str()) {
         Some("png") => "image/png",
         Some("jpg") | Some("jpeg") => "image/jpeg",
@@ -0,0 +1,186 @@
+use crate::models::{BackupManifest, BackupSettings, BackupSummary, Workspace};
+use crate::storage::backup::{self, ARCHIVE_EXTENSION};
+use crate::storage::{Database, UndoManager, WorkspaceManager};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+// how often the background task looks at whether the current workspace is due a backup
+const SCHEDULE_CHECK: Duration = Duration::from_secs(15 * 60);
+
+// writes the current workspace into one .bismuth file, `destination` can be a folder or a file
+#[tauri::command]
+pub fn export_workspace(
+    destination: String,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+) -> Result<BackupSummary, String> {
+    let _switching = workspaces.hold();
+    let workspace = workspaces.current();
+    let mut path = PathBuf::from(&destination);
+    if path.is_dir() {
+        path = path.join(backup::archive_name(&workspace.name));
+    } else if path.extension().is_none() {
+        path.set_extension(ARCHIVE_EXTENSION);
+    }
+    write_backup(&db, &workspaces, &workspace, &path)
+}
+
+// restores a .bismuth file as a new workspace next to the others, does not switch to it
+#[tauri::command]
+pub fn import_workspace(
+    path: String,
+    name: Option<String>,
+    workspaces: State<WorkspaceManager>,
+) -> Result<Workspace, String> {
+    let archive = Path::new(&path);
+    let manifest = backup::read_manifest(archive)?;
+    let name = match name {
+        Some(name) => name,
+        None => workspaces.available_name(&manifest.workspace),
+    };
+
+    workspaces.create_with(&name, |folder| {
+        backup::restore_archive(archive, folder).map(|_| ())
+    })
+}
+
+// automatic backups of the current workspace, newest first
+#[tauri::command]
+pub fn list_backups(workspaces: State<WorkspaceManager>) -> Vec<BackupSummary> {
+    backup::list_archives(&workspaces.backups_dir(&workspaces.current()))
+}
+
+#[tauri::command]
+pub fn backup_now(
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+) -> Result<BackupSummary, String> {
+    let _switching = workspaces.hold();
+    let summary = backup_current(&db, &workspaces)?;
+    rotate_current(&db, &workspaces)?;
+    Ok(summary)
+}
+
+// puts one of list_backups back over the current workspace, what's there now gets backed up first
+// if that backup fails nothing is restored unless `force` is set
+#[tauri::command]
+pub fn restore_backup(
+    path: String,
+    force: Option<bool>,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+    undo: State<UndoManager>,
+) -> Result<BackupManifest, String> {
+    let _switching = workspaces.hold();
+    let workspace = workspaces.current();
+    let archive = Path::new(&path);
+    if archive.parent() != Some(workspaces.backups_dir(&workspace).as_path()) {
+        return Err(format!("not a backup of this workspace D: {}", path));
+    }
+    backup::read_manifest(archive)?;
+
+    // a db that fails the integrity check cant be backed up, which is likely why we're here,
+    // so the ui can ask and retry with force
+    if let Err(e) = backup_current(&db, &workspaces) {
+        if !force.unwrap_or(false) {
+            return Err(format!("failed to back up before restoring D: {}", e));
+        }
+        eprintln!("Failed to back up before restoring: {}", e);
+    }
+    let manifest = backup::restore_in_place(&db, &workspaces.assets_dir(&workspace), archive)?;
+    undo.clear();
+    Ok(manifest)
+}
+
+#[tauri::command]
+pub fn get_backup_settings(db: State<Database>) -> Result<BackupSettings, String> {
+    db.backup_settings()
+        .map_err(|e| format!("failed to read backup settings D: {}", e))
+}
+
+#[tauri::command]
+pub fn set_backup_settings(settings: BackupSettings, db: State<Database>) -> Result<(), String> {
+    db.set_backup_settings(&settings)
+        .map_err(|e| format!("failed to save backup settings D: {}", e))
+}
+
+// started from run(), backs up whichever workspace is open once its interval has passed
+pub fn start_backup_scheduler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let db = app.state::<Database>();
+        let workspaces = app.state::<WorkspaceManager>();
+        // a switch halfway through would put the new workspace's db in the old one's backups
+        let switching = workspaces.hold();
+        let result = run_scheduled_backup(&db, &workspaces);
+        drop(switching);
+        match result {
+            Ok(Some(summary)) => eprintln!("Backed up workspace to {}", summary.path),
+            Ok(None) => {}
+            Err(e) => eprintln!("Scheduled backup failed: {}", e),
+        }
+        std::thread::sleep(SCHEDULE_CHECK);
+    });
+}
+
+fn run_scheduled_backup(
+    db: &Database,
+    workspaces: &WorkspaceManager,
+) -> Result<Option<BackupSummary>, String> {
+    let settings = db
+        .backup_settings()
+        .map_err(|e| format!("failed to read backup settings D: {}", e))?;
+    let Some(hours) = settings.interval_hours else {
+        return Ok(None);
+    };
+
+    let dir = workspaces.backups_dir(&workspaces.current());
+    let due = backup::list_archives(&dir).first().is_none_or(|newest| {
+        Utc::now() - newest.manifest.created_at >= chrono::Duration::hours(hours as i64)
+    });
+    if !due {
+        return Ok(None);
+    }
+
+    let summary = backup_current(db, workspaces)?;
+    rotate_current(db, workspaces)?;
+    Ok(Some(summary))
+}
+
+fn backup_current(db: &Database, workspaces: &WorkspaceManager) -> Result<BackupSummary, String> {
+    let workspace = workspaces.current();
+    let dir = workspaces.backups_dir(&workspace);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed to create backups folder D: {}", e))?;
+    let path = dir.join(backup::archive_name(&workspace.name));
+    write_backup(db, workspaces, &workspace, &path)
+}
+
+fn rotate_current(db: &Database, workspaces: &WorkspaceManager) -> Result<(), String> {
+    let settings = db
+        .backup_settings()
+        .map_err(|e| format!("failed to read backup settings D: {}", e))?;
+    let dir = workspaces.backups_dir(&workspaces.current());
+    match backup::rotate(&dir, settings.keep_daily, settings.keep_weekly) {
+        0 => {}
+        removed => eprintln!("Removed {} old backups", removed),
+    }
+    Ok(())
+}
+
+fn write_backup(
+    db: &Database,
+    workspaces: &WorkspaceManager,
+    workspace: &Workspace,
+    path: &Path,
+) -> Result<BackupSummary, String> {
+    let manifest =
+        backup::write_archive(db, &workspaces.assets_dir(workspace), &workspace.name, path)?;
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    Ok(BackupSummary {
+        path: path.to_string_lossy().to_string(),
+        size,
+        manifest,
+    })
+}
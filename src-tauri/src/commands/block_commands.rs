@@ -1,35 +1,33 @@
-use crate::models::{Block, BlockType};
-use crate::storage::Database;
-use tauri::State;
+use super::history_commands::snapshot_before_edit;
+use super::page_commands::{page_removal, push_block_deletion};
+use crate::models::{
+    Block, BlockNode, BlockOp, BlockOpError, BlockTreeOptions, BlockType, DeleteReport, LinkRepair,
+    TextSpan,
+};
+use crate::storage::undo::{Change, Step};
+use crate::storage::{Database, UndoManager};
+use chrono::Utc;
+use std::collections::HashSet;
+use tauri::{State, Window};
 use uuid::Uuid;
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn create_block(
     page_id: String,
     block_type: BlockType,
     content: String,
     parent_id: Option<String>,
+    index: Option<usize>,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Block, String> {
     let page_uuid = Uuid::parse_str(&page_id).map_err(|e| format!("invalid page_id D: {}", e))?;
-
-    // get exsting blocks to calculate order
-    let existing_blocks = db
-        .get_page_blocks(&page_id)
-        .map_err(|e| format!("failed to get blocks D: {}", e))?;
-
-    // calc order
-    let order = existing_blocks
-        .iter()
-        .filter(|b| b.parent_id.map(|id| id.to_string()) == parent_id)
-        .map(|b| b.order)
-        .max()
-        .unwrap_or(-1)
-        + 1;
+    snapshot_before_edit(&db, &page_id);
 
     // create the block
     let mut block = Block::new(page_uuid, block_type, content);
-    block.order = order;
 
     if let Some(parent) = parent_id {
         let parent_uuid =
@@ -37,8 +35,13 @@ pub fn create_block(
         block.parent_id = Some(parent_uuid);
     }
 
-    db.insert_block(&block)
+    // at the end of its siblings unless an index says where, the ones after it shift down
+    let changes = db
+        .place_block(&block, index)
         .map_err(|e| format!("failed to correct block D: {}", e))?;
+    let block = placed(&changes, &block)?;
+
+    undo.record(window.label(), Step::new("create block", changes));
 
     Ok(block)
 }
@@ -49,47 +52,416 @@ pub fn get_page_blocks(page_id: String, db: State<Database>) -> Result<Vec<Block
         .map_err(|e| format!("failed to get blocks D: {}", e))
 }
 
+// blocks nested under their parents, see BlockTreeOptions for depth and lazy loading
+#[tauri::command]
+pub fn get_page_block_tree(
+    page_id: String,
+    options: Option<BlockTreeOptions>,
+    db: State<Database>,
+) -> Result<Vec<BlockNode>, String> {
+    let options = options.unwrap_or_default();
+    if let Some(root_id) = &options.root_id {
+        let root = get_existing_block(&db, root_id)?;
+        if root.page_id.to_string() != page_id {
+            return Err(format!("block is not on this page D: {}", root_id));
+        }
+    }
+    db.get_page_block_tree(&page_id, &options)
+        .map_err(|e| format!("failed to get blocks D: {}", e))
+}
+
 #[tauri::command]
 pub fn update_block_content(
     block_id: String,
     content: String,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Block, String> {
+    let before = get_existing_block(&db, &block_id)?;
+    snapshot_before_edit(&db, &before.page_id.to_string());
+
     db.update_block_content(&block_id, &content)
         .map_err(|e| format!("failed to update block D: {}", e))?;
 
-    db.get_block_by_id(&block_id)
-        .map_err(|e| format!("database error D: {}", e))?
-        .ok_or_else(|| format!("block not found D: {}", block_id))
+    let block = get_existing_block(&db, &block_id)?;
+    let step = Step::new(
+        "edit block",
+        vec![Change::Block {
+            before: Some(before),
+            after: Some(block.clone()),
+        }],
+    );
+    undo.record(window.label(), step.merging(block_id));
+
+    Ok(block)
 }
 
+// formatted text for a block, its content becomes the same text without the formatting
 #[tauri::command]
-pub fn delete_block(block_id: String, db: State<Database>) -> Result<(), String> {
-    let block = db
-        .get_block_by_id(&block_id)
-        .map_err(|e| format!("database error D: {}", e))?
-        .ok_or_else(|| format!("block not found D: {}", block_id))?;
+pub fn update_block_rich_text(
+    block_id: String,
+    spans: Vec<TextSpan>,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Block, String> {
+    let before = get_existing_block(&db, &block_id)?;
+    snapshot_before_edit(&db, &before.page_id.to_string());
+
+    db.update_block_spans(&block_id, spans)
+        .map_err(|e| format!("failed to update block D: {}", e))?;
+
+    let block = get_existing_block(&db, &block_id)?;
+    let step = Step::new(
+        "edit block",
+        vec![Change::Block {
+            before: Some(before),
+            after: Some(block.clone()),
+        }],
+    );
+    undo.record(window.label(), step.merging(block_id));
+
+    Ok(block)
+}
 
-    // check if the block is a sub page
-    if let BlockType::SubPage { page_id } = block.block_type {
-        db.delete_page(&page_id.to_string())
-            .map_err(|e| format!("failed to delete associated page D: {}", e))?;
+// deleting a SubPage block deletes its page too (nested ones as well), so it reports (or repairs)
+// links like delete_page
+#[tauri::command]
+pub fn delete_block(
+    block_id: String,
+    repair: Option<LinkRepair>,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<DeleteReport, String> {
+    let block = get_existing_block(&db, &block_id)?;
+    snapshot_before_edit(&db, &block.page_id.to_string());
+
+    // nested blocks get cascaded away with it, keep them so undo can put them back
+    let removed_blocks = db
+        .block_subtree(&block_id)
+        .map_err(|e| format!("database error D: {}", e))?;
+    let removed_ids: HashSet<String> = removed_blocks.iter().map(|b| b.id.to_string()).collect();
+
+    let mut changes = Vec::new();
+    let mut report = DeleteReport::default();
+    for removed in &removed_blocks {
+        let BlockType::SubPage { page_id } = removed.block_type else {
+            continue;
+        };
+        let (removal, removal_report) = page_removal(&db, &page_id.to_string(), repair.clone())?;
+        for change in removal {
+            match change {
+                Change::Block {
+                    before: Some(b),
+                    after: None,
+                } => push_block_deletion(&mut changes, b),
+                // a link that goes away with the block doesnt need repairing
+                Change::Block {
+                    before: Some(b), ..
+                } if removed_ids.contains(&b.id.to_string()) => {}
+                other => changes.push(other),
+            }
+        }
+        report.broken.extend(removal_report.broken);
+        report.repaired.extend(removal_report.repaired);
+    }
+    report.broken.retain(|l| !removed_ids.contains(&l.block_id));
+    report
+        .repaired
+        .retain(|l| !removed_ids.contains(&l.block_id));
+
+    for removed in removed_blocks {
+        push_block_deletion(&mut changes, removed);
     }
 
-    db.delete_block(&block_id)
-        .map_err(|e| format!("failed to delete block D: {}", e))
+    db.apply_changes(&changes, false)
+        .map_err(|e| format!("failed to delete block D: {}", e))?;
+    undo.record(window.label(), Step::new("delete block", changes));
+
+    Ok(report)
 }
 
 #[tauri::command]
 pub fn reorder_block(
     block_id: String,
     new_order: i32,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Block, String> {
-    db.update_block_order(&block_id, new_order)
+    let before = get_existing_block(&db, &block_id)?;
+    snapshot_before_edit(&db, &before.page_id.to_string());
+
+    // new_order is the position among its siblings, everything in between shifts by one
+    let mut moved = before.clone();
+    moved.updated_at = Utc::now();
+    let changes = db
+        .place_block(&moved, Some(new_order.max(0) as usize))
         .map_err(|e| format!("failed to reorder block D: {}", e))?;
+    let block = placed(&changes, &moved)?;
+
+    undo.record(window.label(), Step::new("move block", changes));
+
+    Ok(block)
+}
 
-    db.get_block_by_id(&block_id)
+fn get_existing_block(db: &Database, block_id: &str) -> Result<Block, String> {
+    db.get_block_by_id(block_id)
         .map_err(|e| format!("database error D: {}", e))?
         .ok_or_else(|| format!("block not found D: {}", block_id))
 }
+
+// the placed block as it ended up, with its final order
+fn placed(changes: &[Change], block: &Block) -> Result<Block, String> {
+    changes
+        .iter()
+        .find_map(|change| match change {
+            Change::Block { after: Some(b), .. } if b.id == block.id => Some(b.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| format!("block not found D: {}", block.id))
+}
+
+// many inserts/updates/deletes/moves at once (paste, templates), all or nothing and one undo step.
+// the error says which op failed
+#[tauri::command]
+pub fn apply_block_ops(
+    ops: Vec<BlockOp>,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Vec<Block>, BlockOpError> {
+    let mut pages = HashSet::new();
+    for op in &ops {
+        let page_id = match op {
+            BlockOp::Insert { page_id, .. } => Some(page_id.clone()),
+            BlockOp::Update { block_id, .. }
+            | BlockOp::Delete { block_id }
+            | BlockOp::Move { block_id, .. } => db
+                .get_block_by_id(block_id)
+                .ok()
+                .flatten()
+                .map(|b| b.page_id.to_string()),
+        };
+        pages.extend(page_id);
+    }
+    for page_id in &pages {
+        snapshot_before_edit(&db, page_id);
+    }
+
+    let (blocks, changes) = db.apply_block_ops(&ops)?;
+    if !changes.is_empty() {
+        undo.record(window.label(), Step::new("edit blocks", changes));
+    }
+    Ok(blocks)
+}
+
+// nests the block under the sibling right above it, as that sibling's last child
+#[tauri::command]
+pub fn indent_block(
+    block_id: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Block, String> {
+    let before = get_existing_block(&db, &block_id)?;
+    let siblings = siblings_of(&db, &before.page_id, before.parent_id)?;
+    let position = siblings
+        .iter()
+        .position(|b| b.id == before.id)
+        .unwrap_or_default();
+    let Some(above) = position.checked_sub(1).map(|i| &siblings[i]) else {
+        return Err(format!("nothing to indent under D: {}", block_id));
+    };
+
+    let mut moved = before.clone();
+    moved.parent_id = Some(above.id);
+    move_blocks(&db, &undo, &window, "indent block", vec![moved], None).map(first)
+}
+
+// moves the block out of its parent, right after it
+#[tauri::command]
+pub fn outdent_block(
+    block_id: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Block, String> {
+    let before = get_existing_block(&db, &block_id)?;
+    let Some(parent_id) = before.parent_id else {
+        return Err(format!("block is already at the top level D: {}", block_id));
+    };
+    let parent = get_existing_block(&db, &parent_id.to_string())?;
+    let index = siblings_of(&db, &parent.page_id, parent.parent_id)?
+        .iter()
+        .position(|b| b.id == parent.id)
+        .map(|i| i + 1);
+
+    let mut moved = before.clone();
+    moved.parent_id = parent.parent_id;
+    move_blocks(&db, &undo, &window, "outdent block", vec![moved], index).map(first)
+}
+
+// moves a block (and everything nested in it) under new_parent_id, or to the top level of its
+// page when that is None. it goes right after after_block_id, or first when there is none
+#[tauri::command]
+pub fn move_block(
+    block_id: String,
+    new_parent_id: Option<String>,
+    after_block_id: Option<String>,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Block, String> {
+    let before = get_existing_block(&db, &block_id)?;
+    let mut moved = before.clone();
+
+    if let Some(parent_id) = &new_parent_id {
+        let parent = get_existing_block(&db, parent_id)?;
+        let subtree = db
+            .block_subtree(&block_id)
+            .map_err(|e| format!("database error D: {}", e))?;
+        if subtree.iter().any(|b| b.id == parent.id) {
+            return Err(format!("cannot move a block inside itself D: {}", block_id));
+        }
+        moved.page_id = parent.page_id;
+        moved.parent_id = Some(parent.id);
+    } else {
+        moved.parent_id = None;
+    }
+
+    let index = match &after_block_id {
+        Some(after_id) => {
+            let siblings: Vec<Block> = siblings_of(&db, &moved.page_id, moved.parent_id)?
+                .into_iter()
+                .filter(|b| b.id != moved.id)
+                .collect();
+            let position = siblings
+                .iter()
+                .position(|b| b.id.to_string() == *after_id)
+                .ok_or_else(|| format!("block is not under the new parent D: {}", after_id))?;
+            position + 1
+        }
+        None => 0,
+    };
+
+    move_blocks(&db, &undo, &window, "move block", vec![moved], Some(index)).map(first)
+}
+
+// moves blocks with everything nested in them to the end of another page, in the order given
+#[tauri::command]
+pub fn move_blocks_to_page(
+    block_ids: Vec<String>,
+    target_page_id: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Vec<Block>, String> {
+    let target =
+        Uuid::parse_str(&target_page_id).map_err(|e| format!("invalid target_page_id D: {}", e))?;
+    db.get_page(&target_page_id)
+        .map_err(|e| format!("database error D: {}", e))?
+        .ok_or_else(|| format!("page not found D: {}", target_page_id))?;
+
+    let mut blocks = Vec::new();
+    for block_id in &block_ids {
+        blocks.push(get_existing_block(&db, block_id)?);
+    }
+
+    // a block nested in another one from the list comes along with it anyway
+    let mut nested = HashSet::new();
+    for block in &blocks {
+        let subtree = db
+            .block_subtree(&block.id.to_string())
+            .map_err(|e| format!("database error D: {}", e))?;
+        nested.extend(subtree.into_iter().skip(1).map(|b| b.id));
+    }
+
+    let moved = blocks
+        .into_iter()
+        .filter(|b| !nested.contains(&b.id))
+        .map(|mut b| {
+            b.page_id = target;
+            b.parent_id = None;
+            b
+        })
+        .collect();
+    move_blocks(&db, &undo, &window, "move blocks", moved, None)
+}
+
+// places the moved blocks in one transaction and records it as one undo step
+fn move_blocks(
+    db: &Database,
+    undo: &UndoManager,
+    window: &Window,
+    label: &str,
+    mut blocks: Vec<Block>,
+    index: Option<usize>,
+) -> Result<Vec<Block>, String> {
+    let mut pages = HashSet::new();
+    for block in &mut blocks {
+        let before = get_existing_block(db, &block.id.to_string())?;
+        if before.page_id != block.page_id {
+            reject_page_cycle(db, &before, &block.page_id.to_string())?;
+        }
+        pages.insert(before.page_id.to_string());
+        pages.insert(block.page_id.to_string());
+        block.updated_at = Utc::now();
+    }
+    for page_id in &pages {
+        snapshot_before_edit(db, page_id);
+    }
+
+    let changes = db
+        .place_blocks(&blocks, index)
+        .map_err(|e| format!("failed to move block D: {}", e))?;
+    let moved = blocks
+        .iter()
+        .map(|b| placed(&changes, b))
+        .collect::<Result<Vec<_>, _>>()?;
+    undo.record(window.label(), Step::new(label, changes));
+    Ok(moved)
+}
+
+// a SubPage block takes its page along, so it cant go into that page or anything under it
+fn reject_page_cycle(db: &Database, block: &Block, target_page_id: &str) -> Result<(), String> {
+    let mut ancestors = HashSet::new();
+    let mut next = Some(target_page_id.to_string());
+    while let Some(page_id) = next {
+        if !ancestors.insert(page_id.clone()) {
+            break;
+        }
+        next = db
+            .get_page(&page_id)
+            .map_err(|e| format!("database error D: {}", e))?
+            .and_then(|p| p.parent_id);
+    }
+
+    let subtree = db
+        .block_subtree(&block.id.to_string())
+        .map_err(|e| format!("database error D: {}", e))?;
+    for nested in subtree {
+        if let BlockType::SubPage { page_id } = nested.block_type {
+            if ancestors.contains(&page_id.to_string()) {
+                return Err(format!("cannot move a subpage into itself D: {}", page_id));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn siblings_of(
+    db: &Database,
+    page_id: &Uuid,
+    parent_id: Option<Uuid>,
+) -> Result<Vec<Block>, String> {
+    let parent_id = parent_id.map(|p| p.to_string());
+    db.child_blocks(&page_id.to_string(), parent_id.as_deref())
+        .map_err(|e| format!("failed to get blocks D: {}", e))
+}
+
+fn first(mut blocks: Vec<Block>) -> Block {
+    blocks.remove(0)
+}
@@ -0,0 +1,38 @@
+use crate::export::{html, markdown};
+use crate::models::ExportSummary;
+use crate::storage::{Database, WorkspaceManager};
+use std::path::Path;
+use tauri::State;
+
+// writes the page and its subpages as markdown files into `destination`
+#[tauri::command]
+pub fn export_page_markdown(
+    page_id: String,
+    destination: String,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+) -> Result<ExportSummary, String> {
+    markdown::export_page(
+        &db,
+        &workspaces.current_assets_dir(),
+        &page_id,
+        Path::new(&destination),
+    )
+}
+
+// a static html site of the page and its subpages, or of the whole workspace without a page_id
+#[tauri::command]
+pub fn export_site(
+    page_id: Option<String>,
+    destination: String,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+) -> Result<ExportSummary, String> {
+    html::export_site(
+        &db,
+        &workspaces.current_assets_dir(),
+        page_id.as_deref(),
+        &workspaces.current().name,
+        Path::new(&destination),
+    )
+}
@@ -0,0 +1,56 @@
+use crate::models::{Page, PageVersion, PageVersionInfo, VersionKind};
+use crate::storage::undo::Step;
+use crate::storage::{Database, UndoManager};
+use tauri::{State, Window};
+
+#[tauri::command]
+pub fn list_page_versions(
+    page_id: String,
+    db: State<Database>,
+) -> Result<Vec<PageVersionInfo>, String> {
+    db.list_page_versions(&page_id)
+        .map_err(|e| format!("failed to list page versions D: {}", e))
+}
+
+#[tauri::command]
+pub fn get_page_version(version_id: String, db: State<Database>) -> Result<PageVersion, String> {
+    db.get_page_version(&version_id)
+        .map_err(|e| format!("database error D: {}", e))?
+        .ok_or_else(|| format!("version not found D: {}", version_id))
+}
+
+#[tauri::command]
+pub fn create_save_point(
+    page_id: String,
+    label: Option<String>,
+    db: State<Database>,
+) -> Result<PageVersionInfo, String> {
+    db.snapshot_page(&page_id, VersionKind::SavePoint, label.as_deref())
+        .map_err(|e| format!("failed to create save point D: {}", e))?
+        .ok_or_else(|| format!("page not found D: {}", page_id))
+}
+
+#[tauri::command]
+pub fn restore_page_version(
+    version_id: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Page, String> {
+    let (page_id, changes) = db
+        .restore_page_version(&version_id)
+        .map_err(|e| format!("failed to restore version D: {}", e))?
+        .ok_or_else(|| format!("version not found D: {}", version_id))?;
+    undo.record(window.label(), Step::new("restore version", changes));
+
+    db.get_page(&page_id)
+        .map_err(|e| format!("database error D: {}", e))?
+        .ok_or_else(|| format!("page not found D: {}", page_id))
+}
+
+// editing commands call this before changing a page, history failing should never block an edit
+pub fn snapshot_before_edit(db: &Database, page_id: &str) {
+    if let Err(e) = db.auto_snapshot(page_id) {
+        eprintln!("Failed to snapshot page {}: {}", page_id, e);
+    }
+}
@@ -1,18 +1,19 @@
-use crate::storage::Database;
+use super::history_commands::snapshot_before_edit;
+use crate::storage::{Database, WorkspaceManager};
 use std::fs;
 use std::path::Path;
-use tauri::{AppHandle, Manager, State};
+use tauri::State;
 
 #[tauri::command]
 pub async fn upload_page_asset(
-    app: AppHandle,
+    workspaces: State<'_, WorkspaceManager>,
     db: State<'_, Database>,
     page_id: String,
     source_path: String,
     asset_type: String,
 ) -> Result<String, String> {
-    let mut asset_path = app.path().app_data_dir().map_err(|e| e.to_string())?;
-    asset_path.push("page_assets");
+    snapshot_before_edit(&db, &page_id);
+    let asset_path = workspaces.current_assets_dir();
 
     fs::create_dir_all(&asset_path).map_err(|e| e.to_string())?;
 
@@ -0,0 +1,87 @@
+use super::history_commands::snapshot_before_edit;
+use crate::import::{markdown, notion, vault, ImportPlan};
+use crate::models::ImportSummary;
+use crate::storage::undo::Step;
+use crate::storage::{Database, UndoManager, WorkspaceManager};
+use std::path::Path;
+use tauri::{State, Window};
+
+// a .md file or a folder of them, nested under parent_id when given, undoable as one step
+#[tauri::command]
+pub fn import_markdown(
+    path: String,
+    parent_id: Option<String>,
+    window: Window,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+    undo: State<UndoManager>,
+) -> Result<ImportSummary, String> {
+    let plan = markdown::import_path(
+        &db,
+        &workspaces.current_assets_dir(),
+        Path::new(&path),
+        parent_id.as_deref(),
+    )?;
+    save_import(plan, parent_id, "import markdown", &window, &db, &undo)
+}
+
+// a notion export zip (markdown or html flavour) or the folder it was unzipped into
+#[tauri::command]
+pub fn import_notion(
+    path: String,
+    parent_id: Option<String>,
+    window: Window,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+    undo: State<UndoManager>,
+) -> Result<ImportSummary, String> {
+    let plan = notion::import_export(
+        &db,
+        &workspaces.current_assets_dir(),
+        Path::new(&path),
+        parent_id.as_deref(),
+    )?;
+    save_import(plan, parent_id, "import from notion", &window, &db, &undo)
+}
+
+// an obsidian or logseq vault folder, wikilinks become page links
+#[tauri::command]
+pub fn import_vault(
+    path: String,
+    parent_id: Option<String>,
+    window: Window,
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+    undo: State<UndoManager>,
+) -> Result<ImportSummary, String> {
+    let plan = vault::import_vault(
+        &db,
+        &workspaces.current_assets_dir(),
+        Path::new(&path),
+        parent_id.as_deref(),
+    )?;
+    save_import(plan, parent_id, "import vault", &window, &db, &undo)
+}
+
+fn save_import(
+    plan: ImportPlan,
+    parent_id: Option<String>,
+    label: &str,
+    window: &Window,
+    db: &Database,
+    undo: &UndoManager,
+) -> Result<ImportSummary, String> {
+    let summary = plan
+        .summary()
+        .ok_or_else(|| "nothing to import D:".to_string())?;
+
+    if let Some(parent_id) = &parent_id {
+        snapshot_before_edit(db, parent_id);
+    }
+    let changes = plan.changes();
+    db.apply_changes(&changes, false)
+        .map_err(|e| format!("failed to import D: {}", e))?;
+
+    undo.record(window.label(), Step::new(label, changes));
+    Ok(summary)
+}
@@ -0,0 +1,136 @@
+use super::history_commands::snapshot_before_edit;
+use crate::models::{Backlinks, BlockType, GraphOptions, LinkReference, LinkRepair, PageGraph};
+use crate::storage::undo::{Change, Step};
+use crate::storage::{Database, UndoManager};
+use chrono::Utc;
+use std::collections::HashSet;
+use tauri::{State, Window};
+use uuid::Uuid;
+
+// pages linking to page_id, plus blocks that mention its title without a link
+#[tauri::command]
+pub fn get_backlinks(page_id: String, db: State<Database>) -> Result<Backlinks, String> {
+    db.get_backlinks(&page_id)
+        .map_err(|e| format!("failed to load backlinks D: {}", e))
+}
+
+// the links that would break if page_id (and its subpages) got deleted
+#[tauri::command]
+pub fn get_inbound_links(
+    page_id: String,
+    db: State<Database>,
+) -> Result<Vec<LinkReference>, String> {
+    db.inbound_links(&page_id)
+        .map_err(|e| format!("failed to check links D: {}", e))
+}
+
+#[tauri::command]
+pub fn find_broken_links(db: State<Database>) -> Result<Vec<LinkReference>, String> {
+    db.find_broken_links()
+        .map_err(|e| format!("failed to find broken links D: {}", e))
+}
+
+// unlinks or redirects the given link blocks as one undoable step, returns how many changed
+#[tauri::command]
+pub fn repair_links(
+    block_ids: Vec<String>,
+    repair: LinkRepair,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<usize, String> {
+    let changes = link_repairs(&db, &block_ids, &repair)?;
+    let repaired = changes.len();
+    if repaired == 0 {
+        return Ok(0);
+    }
+    db.apply_changes(&changes, false)
+        .map_err(|e| format!("failed to repair links D: {}", e))?;
+    undo.record(window.label(), Step::new("repair links", changes));
+    Ok(repaired)
+}
+
+// the block changes for a repair, blocks that arent links are left alone.
+// snapshots the pages they're on since this edits them from somewhere else
+pub fn link_repairs(
+    db: &Database,
+    block_ids: &[String],
+    repair: &LinkRepair,
+) -> Result<Vec<Change>, String> {
+    let redirect = match repair {
+        LinkRepair::Unlink => None,
+        LinkRepair::Redirect { page_id } => {
+            let page = db
+                .get_page(page_id)
+                .map_err(|e| format!("database error D: {}", e))?
+                .ok_or_else(|| format!("page not found D: {}", page_id))?;
+            let id = Uuid::parse_str(&page.id).map_err(|e| format!("invalid page id D: {}", e))?;
+            Some((id, page.parent_id))
+        }
+    };
+
+    let mut changes = Vec::new();
+    let mut snapshotted = HashSet::new();
+    for block_id in block_ids {
+        let Some(before) = db
+            .get_block_by_id(block_id)
+            .map_err(|e| format!("database error D: {}", e))?
+        else {
+            continue;
+        };
+        let (BlockType::PageLink { page_id: target } | BlockType::SubPage { page_id: target }) =
+            before.block_type
+        else {
+            continue;
+        };
+
+        let mut after = before.clone();
+        match &redirect {
+            // a subpage block only makes sense on the new page's actual parent
+            Some((id, parent_id)) => {
+                let is_child = parent_id.as_deref() == Some(&before.page_id.to_string());
+                after.block_type = match before.block_type {
+                    BlockType::SubPage { .. } if is_child => BlockType::SubPage { page_id: *id },
+                    _ => BlockType::PageLink { page_id: *id },
+                };
+            }
+            None => {
+                after.block_type = BlockType::Text;
+                if after.content.trim().is_empty() {
+                    after.content = db
+                        .get_page(&target.to_string())
+                        .ok()
+                        .flatten()
+                        .map(|p| p.title)
+                        .unwrap_or_else(|| "Untitled".to_string());
+                }
+            }
+        }
+        after.updated_at = Utc::now();
+
+        if snapshotted.insert(before.page_id) {
+            snapshot_before_edit(db, &before.page_id.to_string());
+        }
+        changes.push(Change::Block {
+            before: Some(before),
+            after: Some(after),
+        });
+    }
+    Ok(changes)
+}
+
+// pages and how they connect, the whole workspace or a local graph around options.center
+#[tauri::command]
+pub fn get_page_graph(
+    options: Option<GraphOptions>,
+    db: State<Database>,
+) -> Result<PageGraph, String> {
+    let options = options.unwrap_or_default();
+    if let Some(center) = &options.center {
+        db.get_page(center)
+            .map_err(|e| format!("database error D: {}", e))?
+            .ok_or_else(|| format!("page not found D: {}", center))?;
+    }
+    db.get_page_graph(&options)
+        .map_err(|e| format!("failed to build page graph D: {}", e))
+}
@@ -1,9 +1,25 @@
+pub mod backup_commands;
 pub mod block_commands;
+pub mod export_commands;
+pub mod history_commands;
 pub mod image_commands;
+pub mod import_commands;
+pub mod link_commands;
 pub mod page_commands;
 pub mod search_commands;
+pub mod trash_commands;
+pub mod undo_commands;
+pub mod workspace_commands;
 
+pub use backup_commands::*;
 pub use block_commands::*;
+pub use export_commands::*;
+pub use history_commands::*;
 pub use image_commands::*;
+pub use import_commands::*;
+pub use link_commands::*;
 pub use page_commands::*;
 pub use search_commands::*;
+pub use trash_commands::*;
+pub use undo_commands::*;
+pub use workspace_commands::*;
@@ -1,14 +1,24 @@
-use crate::models::Page;
-use crate::storage::Database;
-use tauri::State;
+use super::history_commands::snapshot_before_edit;
+use super::link_commands::link_repairs;
+use super::undo_commands::page_deletion;
+use crate::models::{Block, DeleteReport, LinkRepair, Page};
+use crate::storage::undo::{Change, Step};
+use crate::storage::{Database, UndoManager};
+use tauri::{State, Window};
 
 #[tauri::command]
-pub fn create_page(title: String, db: State<Database>) -> Result<Page, String> {
+pub fn create_page(
+    title: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Page, String> {
     let page = Page::new(title);
 
     db.insert_page(&page)
         .map_err(|e| format!("failed to create page D: {}", e))?;
 
+    record_created(&undo, &window, &page);
     Ok(page)
 }
 
@@ -29,48 +39,171 @@ pub fn list_pages(db: State<Database>) -> Result<Vec<Page>, String> {
 pub fn update_page_title(
     page_id: String,
     title: String,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Page, String> {
+    let before = db
+        .get_page(&page_id)
+        .map_err(|e| format!("database error D: {}", e))?
+        .ok_or_else(|| format!("page not found D: {}", page_id))?;
+    snapshot_before_edit(&db, &page_id);
     db.update_page_title(&page_id, &title)
         .map_err(|e| format!("failed to update page D: {}", e))?;
 
-    db.get_page(&page_id)
+    let page = db
+        .get_page(&page_id)
         .map_err(|e| format!("database error D: {}", e))?
-        .ok_or_else(|| format!("page not found D: {}", page_id))
+        .ok_or_else(|| format!("page not found D: {}", page_id))?;
+
+    let step = Step::new(
+        "rename page",
+        vec![Change::Page {
+            before: Some(before),
+            after: Some(page.clone()),
+        }],
+    );
+    undo.record(window.label(), step.merging(format!("title:{}", page_id)));
+
+    Ok(page)
 }
 
+// deletes the page and its subpages for good. links pointing into them from other pages are
+// reported back as broken, or rewritten first when `repair` is given (same undo step)
 #[tauri::command]
-pub fn delete_page(page_id: String, db: State<Database>) -> Result<(), String> {
-    db.delete_page(&page_id)
-        .map_err(|e| format!("failed to delete page D: {}", e))
+pub fn delete_page(
+    page_id: String,
+    repair: Option<LinkRepair>,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<DeleteReport, String> {
+    let (changes, report) = page_removal(&db, &page_id, repair)?;
+
+    // one transaction so the links never get rewritten without the page going too
+    db.apply_changes(&changes, false)
+        .map_err(|e| format!("failed to delete page D: {}", e))?;
+
+    undo.record(window.label(), Step::new("delete page", changes));
+    Ok(report)
+}
+
+// everything deleting a page touches: its subtree, the SubPage blocks showing it in the parent
+// and the link repairs, plus the report. deleting a SubPage block goes through here too
+pub fn page_removal(
+    db: &Database,
+    page_id: &str,
+    repair: Option<LinkRepair>,
+) -> Result<(Vec<Change>, DeleteReport), String> {
+    let deletion = page_deletion(db, page_id)?;
+    let inbound = db
+        .inbound_links(page_id)
+        .map_err(|e| format!("failed to check links D: {}", e))?;
+
+    let (mut changes, report) = match repair {
+        Some(repair) if !inbound.is_empty() => {
+            if let LinkRepair::Redirect { page_id: target } = &repair {
+                let deleted = deletion
+                    .iter()
+                    .any(|c| matches!(c, Change::Page { before: Some(p), .. } if &p.id == target));
+                if deleted {
+                    return Err("cant redirect links to a page that's being deleted D:".to_string());
+                }
+            }
+            let block_ids: Vec<String> = inbound.iter().map(|l| l.block_id.clone()).collect();
+            let repairs = link_repairs(db, &block_ids, &repair)?;
+            let report = DeleteReport {
+                broken: Vec::new(),
+                repaired: inbound,
+            };
+            (repairs, report)
+        }
+        _ => {
+            let report = DeleteReport {
+                broken: inbound,
+                repaired: Vec::new(),
+            };
+            (Vec::new(), report)
+        }
+    };
+    changes.extend(deletion);
+
+    let subpage_blocks = db
+        .subpage_blocks(page_id)
+        .map_err(|e| format!("failed to check links D: {}", e))?;
+    for block_id in subpage_blocks {
+        let subtree = db
+            .block_subtree(&block_id)
+            .map_err(|e| format!("database error D: {}", e))?;
+        for block in subtree {
+            push_block_deletion(&mut changes, block);
+        }
+    }
+
+    Ok((changes, report))
+}
+
+// nested blocks can be reached twice (a SubPage block inside another), only delete each once
+pub fn push_block_deletion(changes: &mut Vec<Change>, block: Block) {
+    let seen = changes
+        .iter()
+        .any(|c| matches!(c, Change::Block { before: Some(b), .. } if b.id == block.id));
+    if !seen {
+        changes.push(Change::Block {
+            before: Some(block),
+            after: None,
+        });
+    }
 }
 
 #[tauri::command]
 pub fn update_page_icon(
     page_id: String,
     icon: String,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Page, String> {
+    let before = db
+        .get_page(&page_id)
+        .map_err(|e| format!("database error: {}", e))?
+        .ok_or_else(|| format!("page not found: {}", page_id))?;
+    snapshot_before_edit(&db, &page_id);
     db.update_page_icon(&page_id, &icon)
         .map_err(|e| format!("failed to update icon: {}", e))?;
 
-    db.get_page(&page_id)
+    let page = db
+        .get_page(&page_id)
         .map_err(|e| format!("database error: {}", e))?
-        .ok_or_else(|| format!("page not found: {}", page_id))
+        .ok_or_else(|| format!("page not found: {}", page_id))?;
+
+    record_page_edit(&undo, &window, "change icon", before, &page);
+    Ok(page)
 }
 
 #[tauri::command]
 pub fn update_page_cover(
     page_id: String,
     cover: String,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Page, String> {
+    let before = db
+        .get_page(&page_id)
+        .map_err(|e| format!("database error D: {}", e))?
+        .ok_or_else(|| format!("page not found D: {}", page_id))?;
+    snapshot_before_edit(&db, &page_id);
     db.update_page_cover(&page_id, &cover)
         .map_err(|e| format!("failed to update cover D: {}", e))?;
 
-    db.get_page(&page_id)
+    let page = db
+        .get_page(&page_id)
         .map_err(|e| format!("database error D: {}", e))?
-        .ok_or_else(|| format!("page not found D: {}", page_id))
+        .ok_or_else(|| format!("page not found D: {}", page_id))?;
+
+    record_page_edit(&undo, &window, "change cover", before, &page);
+    Ok(page)
 }
 
 #[tauri::command]
@@ -89,13 +222,16 @@ pub fn get_root_pages(db: State<Database>) -> Result<Vec<Page>, String> {
 pub fn create_nested_page(
     title: String,
     parent_id: String,
+    window: Window,
     db: State<Database>,
+    undo: State<UndoManager>,
 ) -> Result<Page, String> {
     let page = Page::new(title).with_parent(parent_id);
 
     db.insert_page(&page)
         .map_err(|e| format!("failed to create nested page D: {}", e))?;
 
+    record_created(&undo, &window, &page);
     Ok(page)
 }
 
@@ -105,3 +241,29 @@ pub fn validate_page_link(page_id: String, db: State<Database>) -> Result<bool,
         .map(|page| page.is_some())
         .map_err(|e| format!("failed to validate page link D: {}", e))
 }
+
+fn record_page_edit(undo: &UndoManager, window: &Window, label: &str, before: Page, page: &Page) {
+    undo.record(
+        window.label(),
+        Step::new(
+            label,
+            vec![Change::Page {
+                before: Some(before),
+                after: Some(page.clone()),
+            }],
+        ),
+    );
+}
+
+fn record_created(undo: &UndoManager, window: &Window, page: &Page) {
+    undo.record(
+        window.label(),
+        Step::new(
+            "create page",
+            vec![Change::Page {
+                before: None,
+                after: Some(page.clone()),
+            }],
+        ),
+    );
+}
@@ -1,19 +1,143 @@
-use crate::models::SearchResponse;
-use crate::storage::Database;
+use crate::models::{
+    Block, ReplaceResponse, SearchError, SearchFilters, SearchMode, SearchResponse,
+};
+use crate::storage::matcher::Matcher;
+use crate::storage::search_filters::parse_query;
+use crate::storage::search_queries::ReplaceScope;
+use crate::storage::undo::{Change, Step};
+use crate::storage::{Database, UndoManager};
+use tauri::{State, Window};
+
 #[tauri::command]
 pub fn search_blocks(
     state: tauri::State<'_, Database>,
     query: String,
     case_sensitive: bool,
     whole_word: bool,
-) -> Result<SearchResponse, String> {
-    if query.trim().is_empty() {
+    mode: Option<SearchMode>,
+    filters: Option<SearchFilters>,
+) -> Result<SearchResponse, SearchError> {
+    let mode = mode.unwrap_or_default();
+    // a regex is passed through as written, `(?i)type:\w+` isnt a filter
+    let (text, parsed) = match mode {
+        SearchMode::Regex => (query, SearchFilters::default()),
+        _ => parse_query(&query)?,
+    };
+    let filters = filters.unwrap_or_default().merge(parsed);
+
+    // filters alone are enough, "type:todo checked:false" lists every open todo
+    if text.trim().is_empty() && filters.is_empty() {
         return Ok(SearchResponse {
             groups: vec![],
             total_matches: 0,
         });
     }
-    state
-        .search_blocks(&query, case_sensitive, whole_word)
-        .map_err(|e| e.to_string())
+    state.search_blocks(&text, case_sensitive, whole_word, mode, &filters)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn replace_in_block(
+    state: tauri::State<'_, Database>,
+    block_id: String,
+    search: String,
+    replacement: String,
+    case_sensitive: bool,
+    whole_word: bool,
+    mode: Option<SearchMode>,
+    dry_run: bool,
+    window: Window,
+    undo: State<'_, UndoManager>,
+) -> Result<ReplaceResponse, SearchError> {
+    let matcher = Matcher::new(
+        &search,
+        mode.unwrap_or_default(),
+        case_sensitive,
+        whole_word,
+    )?;
+    let (response, previous) = state.replace_text(
+        ReplaceScope::Block(&block_id),
+        &matcher,
+        &replacement,
+        dry_run,
+    )?;
+    record_replace(&undo, &window, previous, &response);
+    Ok(response)
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn replace_in_page(
+    state: tauri::State<'_, Database>,
+    page_id: String,
+    search: String,
+    replacement: String,
+    case_sensitive: bool,
+    whole_word: bool,
+    mode: Option<SearchMode>,
+    dry_run: bool,
+    window: Window,
+    undo: State<'_, UndoManager>,
+) -> Result<ReplaceResponse, SearchError> {
+    let matcher = Matcher::new(
+        &search,
+        mode.unwrap_or_default(),
+        case_sensitive,
+        whole_word,
+    )?;
+    let (response, previous) = state.replace_text(
+        ReplaceScope::Page(&page_id),
+        &matcher,
+        &replacement,
+        dry_run,
+    )?;
+    record_replace(&undo, &window, previous, &response);
+    Ok(response)
+}
+
+// every non archived page in the workspace
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn replace_all(
+    state: tauri::State<'_, Database>,
+    search: String,
+    replacement: String,
+    case_sensitive: bool,
+    whole_word: bool,
+    mode: Option<SearchMode>,
+    dry_run: bool,
+    window: Window,
+    undo: State<'_, UndoManager>,
+) -> Result<ReplaceResponse, SearchError> {
+    let matcher = Matcher::new(
+        &search,
+        mode.unwrap_or_default(),
+        case_sensitive,
+        whole_word,
+    )?;
+    let (response, previous) =
+        state.replace_text(ReplaceScope::All, &matcher, &replacement, dry_run)?;
+    record_replace(&undo, &window, previous, &response);
+    Ok(response)
+}
+
+// a dry run changes nothing so there's nothing to record
+fn record_replace(
+    undo: &UndoManager,
+    window: &Window,
+    previous: Vec<Block>,
+    response: &ReplaceResponse,
+) {
+    if response.dry_run || previous.is_empty() {
+        return;
+    }
+    let changes = previous
+        .into_iter()
+        .zip(response.blocks.iter().cloned())
+        .map(|(before, after)| Change::Block {
+            before: Some(before),
+            after: Some(after),
+        })
+        .collect();
+    undo.record(window.label(), Step::new("replace", changes));
 }
@@ -0,0 +1,148 @@
+use crate::models::Page;
+use crate::storage::undo::{Change, Step};
+use crate::storage::{Database, UndoManager, WorkspaceManager};
+use chrono::{Duration, Utc};
+use std::fs;
+use std::path::Path;
+use tauri::{State, Window};
+
+#[tauri::command]
+pub fn archive_page(
+    page_id: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<(), String> {
+    let before = subtree_pages(&db, &page_id)?;
+    let archived = db
+        .archive_page(&page_id)
+        .map_err(|e| format!("failed to archive page D: {}", e))?;
+
+    if archived == 0 && db.get_page(&page_id).map_err(|e| e.to_string())?.is_none() {
+        return Err(format!("page not found D: {}", page_id));
+    }
+
+    record_trash_step(&db, &undo, &window, "move to trash", before)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn restore_page(
+    page_id: String,
+    window: Window,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Page, String> {
+    let before = subtree_pages(&db, &page_id)?;
+    db.restore_page(&page_id)
+        .map_err(|e| format!("failed to restore page D: {}", e))?;
+
+    record_trash_step(&db, &undo, &window, "restore page", before)?;
+    db.get_page(&page_id)
+        .map_err(|e| format!("database error D: {}", e))?
+        .ok_or_else(|| format!("page not found D: {}", page_id))
+}
+
+fn subtree_pages(db: &Database, page_id: &str) -> Result<Vec<Page>, String> {
+    db.page_subtree(page_id)
+        .map(|(pages, _)| pages)
+        .map_err(|e| format!("database error D: {}", e))
+}
+
+// one step for every page in the subtree that actually moved in or out of the trash
+fn record_trash_step(
+    db: &Database,
+    undo: &UndoManager,
+    window: &Window,
+    label: &str,
+    before: Vec<Page>,
+) -> Result<(), String> {
+    let mut changes = Vec::new();
+    for page in before {
+        let after = db
+            .get_page(&page.id)
+            .map_err(|e| format!("database error D: {}", e))?;
+        let moved = after
+            .as_ref()
+            .is_none_or(|a| a.is_archived != page.is_archived || a.parent_id != page.parent_id);
+        if moved {
+            changes.push(Change::Page {
+                before: Some(page),
+                after,
+            });
+        }
+    }
+
+    if !changes.is_empty() {
+        undo.record(window.label(), Step::new(label, changes));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_trash(db: State<Database>) -> Result<Vec<Page>, String> {
+    db.list_trash()
+        .map_err(|e| format!("failed to list trash D: {}", e))
+}
+
+// deletes everything in the trash for good, returns how many pages went
+#[tauri::command]
+pub fn empty_trash(
+    db: State<Database>,
+    workspaces: State<WorkspaceManager>,
+) -> Result<usize, String> {
+    purge_trash(&db, &workspaces, None)
+}
+
+#[tauri::command]
+pub fn get_trash_retention(db: State<Database>) -> Result<Option<u32>, String> {
+    db.trash_retention_days()
+        .map_err(|e| format!("failed to read trash retention D: {}", e))
+}
+
+// days before trashed pages are purged on startup, null keeps them forever
+#[tauri::command]
+pub fn set_trash_retention(days: Option<u32>, db: State<Database>) -> Result<(), String> {
+    db.set_trash_retention_days(days)
+        .map_err(|e| format!("failed to save trash retention D: {}", e))
+}
+
+// called on startup, drops whatever has been in the trash longer than the retention period
+pub fn purge_expired_trash(db: &Database, workspaces: &WorkspaceManager) -> Result<usize, String> {
+    let Some(days) = db
+        .trash_retention_days()
+        .map_err(|e| format!("failed to read trash retention D: {}", e))?
+    else {
+        return Ok(0);
+    };
+
+    let cutoff = Utc::now() - Duration::days(days as i64);
+    purge_trash(db, workspaces, Some(cutoff))
+}
+
+fn purge_trash(
+    db: &Database,
+    workspaces: &WorkspaceManager,
+    archived_before: Option<chrono::DateTime<Utc>>,
+) -> Result<usize, String> {
+    let purged = db
+        .purge_trash(archived_before)
+        .map_err(|e| format!("failed to empty trash D: {}", e))?;
+
+    let asset_path = workspaces.current_assets_dir();
+    for filename in purged.unused_assets {
+        // icons can be emojis and the names come from the db, only touch plain files in page_assets
+        if Path::new(&filename).file_name() != Some(filename.as_ref()) {
+            continue;
+        }
+        let path = asset_path.join(&filename);
+        if path.is_file() {
+            match fs::remove_file(&path) {
+                Ok(_) => eprintln!("Deleted unused asset: {:?}", path),
+                Err(e) => eprintln!("Failed to delete unused asset {:?}: {}", path, e),
+            }
+        }
+    }
+
+    Ok(purged.pages)
+}
@@ -0,0 +1,50 @@
+use crate::models::{UndoResult, UndoState};
+use crate::storage::undo::Change;
+use crate::storage::{Database, UndoManager};
+use tauri::{State, Window};
+
+// each window keeps its own stack, Ok(None) means there was nothing to undo
+#[tauri::command]
+pub fn undo(
+    window: Window,
+    undo: State<UndoManager>,
+    db: State<Database>,
+) -> Result<Option<UndoResult>, String> {
+    undo.undo(window.label(), &db)
+        .map_err(|e| format!("failed to undo D: {}", e))
+}
+
+#[tauri::command]
+pub fn redo(
+    window: Window,
+    undo: State<UndoManager>,
+    db: State<Database>,
+) -> Result<Option<UndoResult>, String> {
+    undo.redo(window.label(), &db)
+        .map_err(|e| format!("failed to redo D: {}", e))
+}
+
+#[tauri::command]
+pub fn get_undo_state(window: Window, undo: State<UndoManager>) -> UndoState {
+    undo.state(window.label())
+}
+
+// the changes for deleting a page, its subpages and all their blocks in one go
+pub fn page_deletion(db: &Database, page_id: &str) -> Result<Vec<Change>, String> {
+    let (pages, blocks) = db
+        .page_subtree(page_id)
+        .map_err(|e| format!("database error D: {}", e))?;
+
+    let mut changes: Vec<Change> = pages
+        .into_iter()
+        .map(|p| Change::Page {
+            before: Some(p),
+            after: None,
+        })
+        .collect();
+    changes.extend(blocks.into_iter().map(|b| Change::Block {
+        before: Some(b),
+        after: None,
+    }));
+    Ok(changes)
+}
@@ -0,0 +1,78 @@
+use crate::models::Workspace;
+use crate::storage::{Database, UndoManager, WorkspaceManager};
+use std::path::Path;
+use tauri::State;
+
+#[tauri::command]
+pub fn list_workspaces(workspaces: State<WorkspaceManager>) -> Vec<Workspace> {
+    workspaces.list()
+}
+
+#[tauri::command]
+pub fn get_current_workspace(workspaces: State<WorkspaceManager>) -> Workspace {
+    workspaces.current()
+}
+
+#[tauri::command]
+pub fn create_workspace(
+    name: String,
+    workspaces: State<WorkspaceManager>,
+) -> Result<Workspace, String> {
+    let workspace = workspaces.create(&name)?;
+
+    // run the migrations now so the folder is a valid workspace even before anyone opens it
+    Database::new(workspaces.db_path(&workspace))
+        .map_err(|e| format!("failed to initialize workspace database D: {}", e))?;
+
+    Ok(workspace)
+}
+
+// adds a workspace folder from somewhere else on disk and switches to it
+#[tauri::command]
+pub fn open_workspace(
+    path: String,
+    name: Option<String>,
+    workspaces: State<WorkspaceManager>,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Workspace, String> {
+    let folder = Path::new(&path);
+    let name = name
+        .or_else(|| folder.file_name().map(|f| f.to_string_lossy().to_string()))
+        .ok_or_else(|| format!("invalid workspace path D: {}", path))?;
+
+    let workspace = workspaces.add_existing(&name, folder)?;
+    // a db we cant open (corrupt, too new) shouldnt stay in the list
+    switch_to(&workspace, &workspaces, &db, &undo).inspect_err(|_| {
+        if let Err(e) = workspaces.unregister(&workspace.name) {
+            eprintln!("Failed to unregister workspace {}: {}", workspace.name, e);
+        }
+    })
+}
+
+#[tauri::command]
+pub fn switch_workspace(
+    name: String,
+    workspaces: State<WorkspaceManager>,
+    db: State<Database>,
+    undo: State<UndoManager>,
+) -> Result<Workspace, String> {
+    let workspace = workspaces
+        .get(&name)
+        .ok_or_else(|| format!("workspace not found D: {}", name))?;
+    switch_to(&workspace, &workspaces, &db, &undo)
+}
+
+fn switch_to(
+    workspace: &Workspace,
+    workspaces: &WorkspaceManager,
+    db: &Database,
+    undo: &UndoManager,
+) -> Result<Workspace, String> {
+    let _switching = workspaces.hold();
+    // reopen first so a broken/too new db leaves us on the old workspace
+    db.reopen(workspaces.db_path(workspace))
+        .map_err(|e| format!("failed to open workspace D: {}", e))?;
+    undo.clear();
+    workspaces.set_current(&workspace.name)
+}
@@ -0,0 +1,577 @@
+use super::{block_tree, relative_link, ExportPlan, ExportedPage};
+use crate::models::{Block, BlockType, ExportSummary, Mark, Page, TextSpan};
+use crate::storage::Database;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use uuid::Uuid;
+
+/*
+ * a static site: one .html per page laid out like the markdown export, a shared style.css,
+ * the page tree as a sidebar on every page and an index.html listing everything.
+ * links are all relative so the folder can be dropped on any web server or opened from disk
+ */
+const INDEX_FILE: &str = "index.html";
+const STYLE_FILE: &str = "style.css";
+
+type Tree<'a> = HashMap<Option<Uuid>, Vec<&'a Block>>;
+
+// the page and its subpages, or every root page of the workspace when `page_id` is None
+pub fn export_site(
+    db: &Database,
+    assets_dir: &Path,
+    page_id: Option<&str>,
+    site_title: &str,
+    destination: &Path,
+) -> Result<ExportSummary, String> {
+    let roots: Vec<String> = match page_id {
+        Some(id) => vec![id.to_string()],
+        None => db
+            .get_root_pages()
+            .map_err(|e| format!("failed to load pages D: {}", e))?
+            .into_iter()
+            .map(|p| p.id)
+            .collect(),
+    };
+    if roots.is_empty() {
+        return Err("nothing to export D:".to_string());
+    }
+    let roots: Vec<&str> = roots.iter().map(|id| id.as_str()).collect();
+    let plan = ExportPlan::build_all(db, &roots, assets_dir, "html", &["index", "style"])?;
+
+    let site = Site::new(&plan, site_title);
+    let (pages, assets) = plan.write(destination, assets_dir, |page| site.render_page(page))?;
+
+    let index = destination.join(INDEX_FILE);
+    fs::write(&index, site.render_index())
+        .map_err(|e| format!("failed to write {:?} D: {}", index, e))?;
+    let style = destination.join(STYLE_FILE);
+    fs::write(&style, STYLESHEET).map_err(|e| format!("failed to write {:?} D: {}", style, e))?;
+
+    let root_file = index.to_string_lossy().to_string();
+    let mut files = vec![root_file.clone()];
+    files.extend(pages);
+    files.push(style.to_string_lossy().to_string());
+    Ok(ExportSummary {
+        root_file,
+        files,
+        assets,
+    })
+}
+
+struct Site<'a> {
+    plan: &'a ExportPlan,
+    title: String,
+    top: Vec<&'a Page>,
+    children: HashMap<&'a str, Vec<&'a Page>>,
+}
+
+impl<'a> Site<'a> {
+    // one root page names the site after itself
+    fn new(plan: &'a ExportPlan, fallback_title: &str) -> Self {
+        let exported: HashSet<&str> = plan.pages.iter().map(|p| p.page.id.as_str()).collect();
+        let mut top = Vec::new();
+        let mut children: HashMap<&str, Vec<&Page>> = HashMap::new();
+        // plan.pages is already in tree order, parents first and siblings by creation
+        for page in plan.pages.iter().map(|p| &p.page) {
+            match page.parent_id.as_deref().filter(|id| exported.contains(id)) {
+                Some(parent) => children.entry(parent).or_default().push(page),
+                None => top.push(page),
+            }
+        }
+        let title = match top.as_slice() {
+            [only] => only.title.clone(),
+            _ => fallback_title.to_string(),
+        };
+        Site {
+            plan,
+            title,
+            top,
+            children,
+        }
+    }
+
+    fn render_page(&self, exported: &ExportedPage) -> String {
+        let page = &exported.page;
+        let from = exported.path.as_path();
+        let mut main = String::new();
+
+        if let Some(cover) = page.cover.as_deref().filter(|c| self.plan.is_asset(c)) {
+            main.push_str(&format!(
+                "<img class=\"page-cover\" src=\"{}\" alt=\"\">\n",
+                self.asset_link(from, cover)
+            ));
+        }
+        main.push_str("<header>\n");
+        main.push_str(&self.icon(page, from, "page-icon"));
+        main.push_str(&format!(
+            "<h1 class=\"page-title\">{}</h1>\n</header>\n",
+            escape(&page.title)
+        ));
+
+        let tree = block_tree(&exported.blocks);
+        let resolve = |id: &Uuid| {
+            self.plan
+                .path_of(&id.to_string())
+                .map(|to| relative_link(from, to))
+        };
+        main.push_str("<article>\n");
+        main.push_str(&render_children(&tree, None, &resolve));
+        main.push_str("</article>\n");
+
+        self.layout(&page.title, from, Some(&page.id), &main)
+    }
+
+    fn render_index(&self) -> String {
+        let from = Path::new(INDEX_FILE);
+        let main = format!(
+            "<header>\n<h1 class=\"page-title\">{}</h1>\n</header>\n<div class=\"index\">\n{}</div>\n",
+            escape(&self.title),
+            self.page_list(&self.top, from, None)
+        );
+        self.layout(&self.title, from, None, &main)
+    }
+
+    fn layout(&self, title: &str, from: &Path, current: Option<&str>, main: &str) -> String {
+        let home = relative_link(from, Path::new(INDEX_FILE));
+        let title = if title == self.title {
+            escape(title)
+        } else {
+            format!("{} · {}", escape(title), escape(&self.title))
+        };
+        format!(
+            "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title}</title>
+<link rel=\"stylesheet\" href=\"{style}\">
+</head>
+<body>
+<nav class=\"sidebar\">
+<a class=\"home\" href=\"{home}\">{site}</a>
+{nav}</nav>
+<main>
+{main}</main>
+</body>
+</html>
+",
+            style = relative_link(from, Path::new(STYLE_FILE)),
+            site = escape(&self.title),
+            nav = self.page_list(&self.top, from, current),
+        )
+    }
+
+    // nested <ul> of pages, `current` gets highlighted in the sidebar
+    fn page_list(&self, pages: &[&Page], from: &Path, current: Option<&str>) -> String {
+        if pages.is_empty() {
+            return String::new();
+        }
+        let mut out = String::from("<ul>\n");
+        for page in pages {
+            let Some(path) = self.plan.path_of(&page.id) else {
+                continue;
+            };
+            let class = if current == Some(page.id.as_str()) {
+                " class=\"current\""
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "<li><a{} href=\"{}\">{}{}</a>\n",
+                class,
+                relative_link(from, path),
+                self.icon(page, from, "nav-icon").trim_end(),
+                escape(&page.title)
+            ));
+            let kids = self
+                .children
+                .get(page.id.as_str())
+                .map(|k| k.as_slice())
+                .unwrap_or_default();
+            out.push_str(&self.page_list(kids, from, current));
+            out.push_str("</li>\n");
+        }
+        out.push_str("</ul>\n");
+        out
+    }
+
+    // icons are an emoji or a file in page_assets
+    fn icon(&self, page: &Page, from: &Path, class: &str) -> String {
+        match page.icon.as_deref() {
+            Some(icon) if self.plan.is_asset(icon) => format!(
+                "<img class=\"{}\" src=\"{}\" alt=\"\">\n",
+                class,
+                self.asset_link(from, icon)
+            ),
+            Some(icon) if !icon.trim().is_empty() => {
+                format!("<span class=\"{}\">{}</span>\n", class, escape(icon.trim()))
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn asset_link(&self, from: &Path, name: &str) -> String {
+        relative_link(from, &self.plan.asset_path(name))
+    }
+}
+
+fn list_tag(block_type: &BlockType) -> Option<&'static str> {
+    match block_type {
+        BlockType::BulletList | BlockType::Todo { .. } => Some("ul"),
+        BlockType::NumberedList => Some("ol"),
+        _ => None,
+    }
+}
+
+fn render_children(
+    tree: &Tree,
+    parent: Option<Uuid>,
+    resolve: &dyn Fn(&Uuid) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    let mut open_list: Option<&str> = None;
+
+    for block in tree.get(&parent).map(|v| v.as_slice()).unwrap_or_default() {
+        let tag = list_tag(&block.block_type);
+        if open_list != tag {
+            if let Some(open) = open_list {
+                out.push_str(&format!("</{}>\n", open));
+            }
+            if let Some(tag) = tag {
+                out.push_str(&format!("<{}>\n", tag));
+            }
+            open_list = tag;
+        }
+
+        let children = render_children(tree, Some(block.id), resolve);
+        out.push_str(&render_block(block, &children, resolve));
+    }
+    if let Some(open) = open_list {
+        out.push_str(&format!("</{}>\n", open));
+    }
+    out
+}
+
+fn render_block(
+    block: &Block,
+    children: &str,
+    resolve: &dyn Fn(&Uuid) -> Option<String>,
+) -> String {
+    let content = block.content.as_str();
+    let body = match &block.spans {
+        Some(spans) => rich_text(spans),
+        None => text(content),
+    };
+    // nested blocks sit inside list items and quotes, under everything else
+    let nested = if children.is_empty() {
+        String::new()
+    } else {
+        format!("<div class=\"children\">\n{}</div>\n", children)
+    };
+
+    match &block.block_type {
+        BlockType::Text if content.trim().is_empty() => nested,
+        BlockType::Text => format!("<p>{}</p>\n{}", body, nested),
+        BlockType::Heading1 => format!("<h2>{}</h2>\n{}", body, nested),
+        BlockType::Heading2 => format!("<h3>{}</h3>\n{}", body, nested),
+        BlockType::Heading3 => format!("<h4>{}</h4>\n{}", body, nested),
+        BlockType::BulletList | BlockType::NumberedList => {
+            format!("<li>{}\n{}</li>\n", body, children)
+        }
+        BlockType::Todo { checked } => format!(
+            "<li class=\"todo{}\"><input type=\"checkbox\" disabled{}> {}\n{}</li>\n",
+            if *checked { " checked" } else { "" },
+            if *checked { " checked" } else { "" },
+            body,
+            children
+        ),
+        BlockType::Code { language } => {
+            let class = if language.trim().is_empty() {
+                String::new()
+            } else {
+                format!(" class=\"language-{}\"", escape(language.trim()))
+            };
+            format!(
+                "<pre><code{}>{}</code></pre>\n{}",
+                class,
+                escape(content),
+                nested
+            )
+        }
+        BlockType::Quote => format!("<blockquote>\n<p>{}</p>\n{}</blockquote>\n", body, children),
+        BlockType::Divider => format!("<hr>\n{}", nested),
+        BlockType::SubPage { page_id } | BlockType::PageLink { page_id } => {
+            let class = match block.block_type {
+                BlockType::SubPage { .. } => "subpage",
+                _ => "page-link",
+            };
+            let title = if content.trim().is_empty() {
+                "Untitled".to_string()
+            } else {
+                escape(content.trim())
+            };
+            // pages outside the export have nowhere to point
+            match resolve(page_id) {
+                Some(href) => format!(
+                    "<p class=\"{}\"><a href=\"{}\">{}</a></p>\n{}",
+                    class, href, title, nested
+                ),
+                None => format!("<p class=\"{} missing\">{}</p>\n{}", class, title, nested),
+            }
+        }
+    }
+}
+
+// line breaks typed inside a block stay line breaks
+fn text(content: &str) -> String {
+    escape(content.trim_end()).replace('\n', "<br>\n")
+}
+
+// marks nest in a fixed order so the same formatting always gives the same markup
+fn rich_text(spans: &[TextSpan]) -> String {
+    let mut out = String::new();
+    for span in spans {
+        let mut html = escape(&span.text).replace('\n', "<br>\n");
+        for tag in [Mark::Code, Mark::Italic, Mark::Bold, Mark::Strikethrough] {
+            if span.marks.contains(&tag) {
+                let name = match tag {
+                    Mark::Code => "code",
+                    Mark::Italic => "em",
+                    Mark::Bold => "strong",
+                    _ => "s",
+                };
+                html = format!("<{0}>{1}</{0}>", name, html);
+            }
+        }
+        for mark in &span.marks {
+            html = match mark {
+                Mark::Highlight(c) => format!(
+                    "<mark style=\"background-color: {}\">{}</mark>",
+                    escape(c),
+                    html
+                ),
+                _ => html,
+            };
+        }
+        for mark in &span.marks {
+            html = match mark {
+                Mark::Color(c) => format!("<span style=\"color: {}\">{}</span>", escape(c), html),
+                _ => html,
+            };
+        }
+        if let Some(link) = span.link.as_deref().filter(|l| safe_link(l)) {
+            html = format!("<a href=\"{}\">{}</a>", escape(link), html);
+        }
+        out.push_str(&html);
+    }
+    out.trim_end().to_string()
+}
+
+// only web, mail and relative/anchor links go on the site, anything else (javascript:, data:...)
+// loses its href. browsers drop tabs and newlines inside urls, so they dont hide a scheme here
+fn safe_link(link: &str) -> bool {
+    let link: String = link.chars().filter(|c| !c.is_ascii_control()).collect();
+    let link = link.trim();
+    match link.find([':', '/', '?', '#']) {
+        Some(end) if link[end..].starts_with(':') => {
+            let scheme = link[..end].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLESHEET: &str = ":root {
+  --text: #37352f;
+  --muted: #787774;
+  --border: #e9e9e7;
+  --sidebar: #f7f7f5;
+  --accent: #2383e2;
+}
+
+* { box-sizing: border-box; }
+
+body {
+  margin: 0;
+  display: flex;
+  min-height: 100vh;
+  color: var(--text);
+  font: 16px/1.6 -apple-system, BlinkMacSystemFont, \"Segoe UI\", Helvetica, Arial, sans-serif;
+}
+
+a { color: var(--accent); text-decoration: none; }
+a:hover { text-decoration: underline; }
+
+.sidebar {
+  flex: 0 0 260px;
+  padding: 24px 12px;
+  background: var(--sidebar);
+  border-right: 1px solid var(--border);
+  font-size: 14px;
+  overflow-y: auto;
+}
+.sidebar .home { display: block; font-weight: 600; color: var(--text); margin: 0 8px 12px; }
+.sidebar ul, .index ul { list-style: none; margin: 0; padding-left: 14px; }
+.sidebar > ul, .index > ul { padding-left: 0; }
+.sidebar li a { display: block; padding: 2px 8px; border-radius: 4px; color: var(--text); }
+.sidebar li a.current { background: var(--border); font-weight: 600; }
+
+main { flex: 1; max-width: 860px; margin: 0 auto; padding: 0 48px 96px; }
+
+.page-cover { display: block; width: calc(100% + 96px); max-height: 280px; margin: 0 -48px; object-fit: cover; }
+header { padding-top: 48px; }
+.page-icon { display: block; font-size: 64px; line-height: 1; }
+img.page-icon { width: 72px; height: 72px; object-fit: cover; }
+.nav-icon { margin-right: 6px; }
+img.nav-icon { width: 16px; height: 16px; vertical-align: -3px; object-fit: cover; }
+.page-title { font-size: 40px; margin: 12px 0 24px; }
+
+.children { padding-left: 24px; }
+li.todo { list-style: none; margin-left: -20px; }
+li.todo.checked { color: var(--muted); text-decoration: line-through; }
+pre { background: var(--sidebar); padding: 16px; border-radius: 4px; overflow-x: auto; font-size: 14px; }
+blockquote { margin: 0; padding-left: 16px; border-left: 3px solid var(--text); }
+hr { border: none; border-top: 1px solid var(--border); margin: 16px 0; }
+.subpage a { color: var(--text); text-decoration: underline; text-decoration-color: var(--border); }
+.missing { color: var(--muted); }
+
+@media (max-width: 720px) {
+  body { display: block; }
+  .sidebar { border-right: none; border-bottom: 1px solid var(--border); }
+  main { padding: 0 20px 64px; }
+  .page-cover { width: calc(100% + 40px); margin: 0 -20px; }
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_a_linked_site() {
+        let db = Database::new(":memory:").unwrap();
+        let dir = std::env::temp_dir().join(format!("bismuth-site-{}", Uuid::new_v4()));
+        let assets = dir.join("page_assets");
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(assets.join("cover.png"), "png").unwrap();
+
+        let mut handbook = Page::new("Handbook".to_string());
+        handbook.icon = Some("📘".to_string());
+        handbook.cover = Some("cover.png".to_string());
+        let onboarding = Page::new("On <boarding>".to_string()).with_parent(handbook.id.clone());
+        let other = Page::new("Elsewhere".to_string());
+        for page in [&handbook, &onboarding, &other] {
+            db.insert_page(page).unwrap();
+        }
+        let handbook_id = Uuid::parse_str(&handbook.id).unwrap();
+        let onboarding_id = Uuid::parse_str(&onboarding.id).unwrap();
+        let item = Block::new(handbook_id, BlockType::BulletList, "one".to_string()).with_order(1);
+        let blocks = [
+            Block::new(handbook_id, BlockType::Text, "a & b\nc".to_string()).with_order(0),
+            item.clone(),
+            Block::new(
+                handbook_id,
+                BlockType::Todo { checked: true },
+                "nested".to_string(),
+            )
+            .with_parent(item.id),
+            Block::new(
+                handbook_id,
+                BlockType::SubPage {
+                    page_id: onboarding_id,
+                },
+                "On <boarding>".to_string(),
+            )
+            .with_order(2),
+            Block::new(
+                handbook_id,
+                BlockType::PageLink {
+                    page_id: Uuid::parse_str(&other.id).unwrap(),
+                },
+                "Elsewhere".to_string(),
+            )
+            .with_order(3),
+            Block::new(
+                onboarding_id,
+                BlockType::PageLink {
+                    page_id: handbook_id,
+                },
+                "back".to_string(),
+            ),
+        ];
+        for block in &blocks {
+            db.insert_block(block).unwrap();
+        }
+
+        let out = dir.join("site");
+        let summary = export_site(&db, &assets, Some(&handbook.id), "Workspace", &out).unwrap();
+        assert_eq!(summary.root_file, out.join("index.html").to_string_lossy());
+        assert_eq!(summary.assets, vec!["cover.png".to_string()]);
+        assert!(out.join("style.css").is_file());
+        assert!(out.join("assets").join("cover.png").is_file());
+
+        let root = fs::read_to_string(out.join("Handbook.html")).unwrap();
+        assert!(root.contains("<title>Handbook</title>"));
+        assert!(root.contains("<img class=\"page-cover\" src=\"assets/cover.png\""));
+        assert!(root.contains("<span class=\"page-icon\">📘</span>"));
+        assert!(root.contains("<p>a &amp; b<br>\nc</p>"));
+        assert!(root.contains(
+            "<ul>\n<li>one\n<ul>\n<li class=\"todo checked\"><input type=\"checkbox\" disabled checked> nested\n</li>\n</ul>\n</li>\n</ul>"
+        ));
+        assert!(root.contains(
+            "<p class=\"subpage\"><a href=\"Handbook/On%20boarding.html\">On &lt;boarding&gt;</a></p>"
+        ));
+        assert!(root.contains("<p class=\"page-link missing\">Elsewhere</p>"));
+        assert!(root.contains("<a class=\"current\" href=\"Handbook.html\">"));
+
+        let child = fs::read_to_string(out.join("Handbook").join("On boarding.html")).unwrap();
+        assert!(child.contains("<link rel=\"stylesheet\" href=\"../style.css\">"));
+        assert!(child.contains("<a class=\"home\" href=\"../index.html\">Handbook</a>"));
+        assert!(child.contains("<p class=\"page-link\"><a href=\"../Handbook.html\">back</a></p>"));
+
+        let index = fs::read_to_string(out.join("index.html")).unwrap();
+        assert!(index.contains("href=\"Handbook/On%20boarding.html\">On &lt;boarding&gt;</a>"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_safe_links_keep_their_href() {
+        for link in [
+            "https://example.com",
+            "HTTP://example.com",
+            "mailto:someone@example.com",
+            "#section",
+            "../Other.html",
+            "notes/a:b",
+        ] {
+            assert!(safe_link(link), "{}", link);
+        }
+        for link in [
+            "javascript:alert(1)",
+            " JavaScript:alert(1)",
+            "java\tscript:alert(1)",
+            "java\nscript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "vbscript:msgbox(1)",
+        ] {
+            assert!(!safe_link(link), "{}", link);
+        }
+    }
+}
@@ -0,0 +1,605 @@
+use super::{block_tree, relative_link, ExportPlan, ExportedPage};
+use crate::models::{Block, BlockType, ExportSummary, Mark, TextSpan};
+use crate::storage::Database;
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+type Tree<'a> = HashMap<Option<Uuid>, Vec<&'a Block>>;
+
+// writes the page and its subpages as GFM into `destination`
+pub fn export_page(
+    db: &Database,
+    assets_dir: &Path,
+    page_id: &str,
+    destination: &Path,
+) -> Result<ExportSummary, String> {
+    let plan = ExportPlan::build(db, page_id, assets_dir, "md")?;
+    let (files, assets) = plan.write(destination, assets_dir, |page| render_page(page, &plan))?;
+
+    Ok(ExportSummary {
+        root_file: files.first().cloned().unwrap_or_default(),
+        files,
+        assets,
+    })
+}
+
+pub fn render_page(exported: &ExportedPage, plan: &ExportPlan) -> String {
+    let page = &exported.page;
+    let asset_link = |name: &str| relative_link(&exported.path, &plan.asset_path(name));
+    let mut out = String::new();
+
+    if let Some(cover) = page.cover.as_deref().filter(|c| plan.is_asset(c)) {
+        out.push_str(&format!("![cover]({})\n\n", asset_link(cover)));
+    }
+    let title = match page.icon.as_deref() {
+        Some(icon) if plan.is_asset(icon) => {
+            out.push_str(&format!("![icon]({})\n\n", asset_link(icon)));
+            page.title.clone()
+        }
+        Some(icon) if !icon.trim().is_empty() => format!("{} {}", icon, page.title),
+        _ => page.title.clone(),
+    };
+    out.push_str(&format!("# {}\n", one_line(&title)));
+
+    let tree = block_tree(&exported.blocks);
+    let resolve = |id: &Uuid| {
+        plan.path_of(&id.to_string())
+            .map(|to| relative_link(&exported.path, to))
+    };
+    let body = render_children(&tree, None, &resolve);
+    if !body.is_empty() {
+        out.push('\n');
+        out.push_str(&body.join("\n"));
+        out.push('\n');
+    }
+    out
+}
+
+fn render_children(
+    tree: &Tree,
+    parent: Option<Uuid>,
+    resolve: &dyn Fn(&Uuid) -> Option<String>,
+) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut previous: Option<&BlockType> = None;
+    let mut number = 0;
+
+    for block in tree.get(&parent).map(|v| v.as_slice()).unwrap_or_default() {
+        number = match (&block.block_type, previous) {
+            (BlockType::NumberedList, Some(BlockType::NumberedList)) => number + 1,
+            (BlockType::NumberedList, _) => 1,
+            _ => 0,
+        };
+
+        let own = render_block(block, number, resolve);
+        let children = render_children(tree, Some(block.id), resolve);
+        if own.is_empty() && children.is_empty() {
+            continue;
+        }
+
+        // items of the same list stay together, everything else gets a blank line in between
+        if let Some(previous) = previous {
+            if !same_list(previous, &block.block_type) {
+                lines.push(String::new());
+            }
+        }
+
+        let child_indent = match &block.block_type {
+            BlockType::BulletList | BlockType::Todo { .. } => "  ".to_string(),
+            BlockType::NumberedList => " ".repeat(format!("{}. ", number).len()),
+            BlockType::Quote => "> ".to_string(),
+            _ => String::new(),
+        };
+        let nested_list = children.first().is_some_and(|l| is_list_line(l));
+
+        lines.extend(own);
+        if !children.is_empty() {
+            // a paragraph right under a list item would just continue the item text
+            if !nested_list || child_indent.is_empty() {
+                lines.push(child_indent.trim_end().to_string());
+            }
+            lines.extend(
+                children
+                    .into_iter()
+                    .map(|line| indent(&child_indent, &line)),
+            );
+        }
+        previous = Some(&block.block_type);
+    }
+
+    lines
+}
+
+fn render_block(
+    block: &Block,
+    number: usize,
+    resolve: &dyn Fn(&Uuid) -> Option<String>,
+) -> Vec<String> {
+    let content = block.content.as_str();
+    // plain text goes through the same escaping, "2*3*4" would come back italic otherwise
+    let text = match &block.spans {
+        Some(spans) => inline(spans),
+        None => inline(&[TextSpan::plain(content)]),
+    };
+    let text = text.as_str();
+
+    match &block.block_type {
+        BlockType::Text => paragraph(text),
+        BlockType::Heading1 => vec![format!("# {}", one_line(text))],
+        BlockType::Heading2 => vec![format!("## {}", one_line(text))],
+        BlockType::Heading3 => vec![format!("### {}", one_line(text))],
+        BlockType::BulletList => item("- ", text),
+        BlockType::NumberedList => item(&format!("{}. ", number), text),
+        BlockType::Todo { checked } => item(if *checked { "- [x] " } else { "- [ ] " }, text),
+        BlockType::Code { language } => code(content, language),
+        BlockType::Quote => paragraph(text)
+            .into_iter()
+            .map(|line| indent("> ", &line))
+            .collect(),
+        BlockType::Divider => vec!["---".to_string()],
+        BlockType::SubPage { page_id } | BlockType::PageLink { page_id } => {
+            let text = escape_link_text(&one_line(content));
+            let text = if text.is_empty() {
+                "Untitled".to_string()
+            } else {
+                text
+            };
+            match resolve(page_id) {
+                Some(link) => vec![format!("[{}]({})", text, link)],
+                None => vec![escape_line_start(&text)],
+            }
+        }
+    }
+}
+
+// hard breaks (trailing backslash) keep the line breaks typed inside a block
+fn paragraph(content: &str) -> Vec<String> {
+    if content.trim().is_empty() {
+        return Vec::new();
+    }
+    let lines: Vec<&str> = content.trim_end().lines().collect();
+    lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let line = escape_formatted_line(line);
+            let next_has_text = lines.get(idx + 1).is_some_and(|l| !l.trim().is_empty());
+            if !line.is_empty() && next_has_text {
+                format!("{}\\", line)
+            } else {
+                line
+            }
+        })
+        .collect()
+}
+
+fn item(marker: &str, content: &str) -> Vec<String> {
+    let mut lines = paragraph(content).into_iter();
+    let first = match lines.next() {
+        Some(line) => format!("{}{}", marker, line),
+        None => marker.trim_end().to_string(),
+    };
+    let continuation = " ".repeat(marker.len());
+    std::iter::once(first)
+        .chain(lines.map(|line| indent(&continuation, &line)))
+        .collect()
+}
+
+fn code(content: &str, language: &str) -> Vec<String> {
+    // the fence has to be longer than any run of backticks inside the code
+    let longest_run = content
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+
+    let mut lines = vec![format!("{}{}", fence, language.trim())];
+    lines.extend(content.lines().map(|line| line.to_string()));
+    lines.push(fence);
+    lines
+}
+
+fn indent(prefix: &str, line: &str) -> String {
+    if line.is_empty() {
+        prefix.trim_end().to_string()
+    } else {
+        format!("{}{}", prefix, line)
+    }
+}
+
+fn one_line(content: &str) -> String {
+    content.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn same_list(a: &BlockType, b: &BlockType) -> bool {
+    let bullet = |t: &BlockType| matches!(t, BlockType::BulletList | BlockType::Todo { .. });
+    (bullet(a) && bullet(b)) || matches!((a, b), (BlockType::NumberedList, BlockType::NumberedList))
+}
+
+fn is_list_line(line: &str) -> bool {
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    line.starts_with("- ") || line == "-" || (digits > 0 && line[digits..].starts_with(". "))
+}
+
+// text that happens to start like markdown ("# not a heading", "1. not a list") stays text
+fn escape_line_start(line: &str) -> String {
+    let line = line.trim_start();
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let looks_like_markdown = line.starts_with(['#', '>', '-', '+', '*', '=', '|'])
+        || line.starts_with("```")
+        || line.starts_with("~~~")
+        || (digits > 0 && line[digits..].starts_with(['.', ')']));
+
+    if !looks_like_markdown {
+        line.to_string()
+    } else if digits > 0 {
+        format!("{}\\{}", &line[..digits], &line[digits..])
+    } else {
+        format!("\\{}", line)
+    }
+}
+
+// inline() escapes literal * ~ and `, so a line starting with one of them starts a marker
+fn escape_formatted_line(line: &str) -> String {
+    if line.trim_start().starts_with(['*', '~', '`']) {
+        line.trim_start().to_string()
+    } else {
+        escape_line_start(line)
+    }
+}
+
+/*
+ * rich text as inline markdown. bold, italic, strikethrough, code and links are plain gfm,
+ * colours have no markdown so they go out as <span>/<mark> the importer reads back.
+ * marks stay open across spans that share them and whitespace is kept outside the markers,
+ * otherwise "**bold **text" wouldnt parse as bold
+ */
+fn inline(spans: &[TextSpan]) -> String {
+    // every line is closed on its own so a hard break never ends up inside a marker
+    let mut lines: Vec<Vec<TextSpan>> = vec![Vec::new()];
+    for span in spans {
+        for (idx, part) in span.text.split('\n').enumerate() {
+            if idx > 0 {
+                lines.push(Vec::new());
+            }
+            lines.last_mut().unwrap().push(TextSpan {
+                text: part.to_string(),
+                ..span.clone()
+            });
+        }
+    }
+    lines
+        .iter()
+        .map(|line| inline_line(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Clone, PartialEq)]
+enum Wrap {
+    Link(String),
+    Color(String),
+    Highlight(String),
+    Strikethrough,
+    Bold,
+    Italic,
+}
+
+impl Wrap {
+    // outermost first, code is always innermost and handled on its own
+    fn of(span: &TextSpan) -> Vec<Wrap> {
+        let mut wraps: Vec<Wrap> = span.link.iter().cloned().map(Wrap::Link).collect();
+        let mut marks: Vec<Wrap> = span
+            .marks
+            .iter()
+            .filter_map(|mark| match mark {
+                // the colour goes into a style attribute as is, anything odd loses the colour
+                Mark::Color(c) => css_color(c).then(|| Wrap::Color(c.clone())),
+                Mark::Highlight(c) => css_color(c).then(|| Wrap::Highlight(c.clone())),
+                Mark::Strikethrough => Some(Wrap::Strikethrough),
+                Mark::Bold => Some(Wrap::Bold),
+                Mark::Italic => Some(Wrap::Italic),
+                Mark::Code => None,
+            })
+            .collect();
+        marks.sort_by_key(|w| match w {
+            Wrap::Link(_) => 0,
+            Wrap::Color(_) => 1,
+            Wrap::Highlight(_) => 2,
+            Wrap::Strikethrough => 3,
+            Wrap::Bold => 4,
+            Wrap::Italic => 5,
+        });
+        wraps.extend(marks);
+        wraps
+    }
+
+    fn open(&self) -> String {
+        match self {
+            Wrap::Link(_) => "[".to_string(),
+            Wrap::Color(c) => format!("<span style=\"color: {}\">", c),
+            Wrap::Highlight(c) => format!("<mark style=\"background-color: {}\">", c),
+            Wrap::Strikethrough => "~~".to_string(),
+            Wrap::Bold => "**".to_string(),
+            Wrap::Italic => "*".to_string(),
+        }
+    }
+
+    fn close(&self) -> String {
+        match self {
+            Wrap::Link(url) if url.contains([' ', '(', ')', '<', '>']) => {
+                format!("](<{}>)", url.replace('<', "%3C").replace('>', "%3E"))
+            }
+            Wrap::Link(url) => format!("]({})", url),
+            Wrap::Color(_) => "</span>".to_string(),
+            Wrap::Highlight(_) => "</mark>".to_string(),
+            other => other.open(),
+        }
+    }
+}
+
+// #rgb/#rrggbb(aa), a plain name like "red", or rgb()/hsl() with only numbers inside
+fn css_color(color: &str) -> bool {
+    if let Some(hex) = color.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if let Some((name, args)) = color.split_once('(') {
+        let Some(args) = args.strip_suffix(')') else {
+            return false;
+        };
+        return matches!(name, "rgb" | "rgba" | "hsl" | "hsla")
+            && args
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, ' ' | ',' | '.' | '%' | '/'));
+    }
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn inline_line(spans: &[TextSpan]) -> String {
+    let mut out = String::new();
+    let mut open: Vec<Wrap> = Vec::new();
+    let mut pending = String::new(); // trailing whitespace, goes after any marker that closes
+
+    for span in spans {
+        let core = span.text.trim();
+        if core.is_empty() {
+            pending.push_str(&span.text);
+            continue;
+        }
+        let lead = &span.text[..span.text.len() - span.text.trim_start().len()];
+        let trail = &span.text[span.text.trim_end().len()..];
+
+        let wanted = Wrap::of(span);
+        let keep = open.iter().zip(&wanted).take_while(|(a, b)| a == b).count();
+        while open.len() > keep {
+            out.push_str(&open.pop().unwrap().close());
+        }
+        out.push_str(&pending);
+        out.push_str(lead);
+        pending.clear();
+        for wrap in &wanted[keep..] {
+            out.push_str(&wrap.open());
+            open.push(wrap.clone());
+        }
+
+        if span.marks.contains(&Mark::Code) {
+            out.push_str(&code_span(core));
+        } else {
+            out.push_str(&escape_inline(core));
+        }
+        pending.push_str(trail);
+    }
+    while let Some(wrap) = open.pop() {
+        out.push_str(&wrap.close());
+    }
+    out.push_str(&pending);
+    out
+}
+
+// backslash escapes for everything that could start inline formatting
+fn escape_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let entity = c == '&'
+            && chars
+                .peek()
+                .is_some_and(|n| n.is_alphanumeric() || *n == '#');
+        if matches!(c, '\\' | '*' | '_' | '`' | '~' | '[' | ']' | '<') || entity {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// the fence has to be longer than any run of backticks inside, like code blocks
+fn code_span(code: &str) -> String {
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    if code.starts_with('`') || code.ends_with('`') {
+        format!("{} {} {}", fence, code, fence)
+    } else {
+        format!("{}{}{}", fence, code, fence)
+    }
+}
+
+fn escape_link_text(text: &str) -> String {
+    text.replace('[', "\\[").replace(']', "\\]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Page;
+    use std::collections::HashSet;
+    use std::path::PathBuf;
+
+    fn block(page: &Page, block_type: BlockType, content: &str, order: i32) -> Block {
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        Block::new(page_id, block_type, content.to_string()).with_order(order)
+    }
+
+    #[test]
+    fn renders_every_block_type() {
+        let page = Page::new("Plans".to_string());
+        let child = Page::new("Trip".to_string()).with_parent(page.id.clone());
+        let child_id = Uuid::parse_str(&child.id).unwrap();
+
+        let bullet = block(&page, BlockType::BulletList, "pack", 1);
+        let blocks = vec![
+            block(&page, BlockType::Heading2, "Todo", 0),
+            bullet.clone(),
+            block(&page, BlockType::BulletList, "socks", 0).with_parent(bullet.id),
+            block(&page, BlockType::Todo { checked: true }, "book", 2),
+            block(&page, BlockType::NumberedList, "one", 3),
+            block(&page, BlockType::NumberedList, "two", 4),
+            block(&page, BlockType::Text, "# not a heading\nsecond line", 5),
+            block(
+                &page,
+                BlockType::Code {
+                    language: "rust".to_string(),
+                },
+                "let a = \"```\";",
+                6,
+            ),
+            block(&page, BlockType::Quote, "quoted", 7),
+            block(&page, BlockType::Divider, "", 8),
+            block(&page, BlockType::SubPage { page_id: child_id }, "Trip", 9),
+            block(
+                &page,
+                BlockType::PageLink {
+                    page_id: Uuid::new_v4(),
+                },
+                "Elsewhere",
+                10,
+            ),
+        ];
+
+        let plan = ExportPlan {
+            pages: Vec::new(),
+            paths: HashMap::from([
+                (page.id.clone(), PathBuf::from("Plans.md")),
+                (child.id.clone(), PathBuf::from("Plans/Trip.md")),
+            ]),
+            assets: HashSet::new(),
+        };
+        let exported = ExportedPage {
+            page,
+            blocks,
+            path: PathBuf::from("Plans.md"),
+        };
+
+        let expected = "# Plans
+
+## Todo
+
+- pack
+  - socks
+- [x] book
+
+1. one
+2. two
+
+\\# not a heading\\
+second line
+
+````rust
+let a = \"```\";
+````
+
+> quoted
+
+---
+
+[Trip](Plans/Trip.md)
+
+Elsewhere
+";
+        assert_eq!(render_page(&exported, &plan), expected);
+    }
+
+    #[test]
+    fn formatting_survives_a_round_trip() {
+        let spans = vec![
+            TextSpan {
+                marks: vec![Mark::Bold],
+                ..TextSpan::plain("bold ")
+            },
+            TextSpan {
+                marks: vec![Mark::Bold, Mark::Italic],
+                ..TextSpan::plain("both")
+            },
+            TextSpan::plain(" 2*3 [x]\nnext "),
+            TextSpan {
+                marks: vec![Mark::Code],
+                ..TextSpan::plain("a `b`")
+            },
+            TextSpan::plain(" "),
+            TextSpan {
+                marks: vec![Mark::Color("red".to_string()), Mark::Strikethrough],
+                link: Some("https://example.com/a b".to_string()),
+                ..TextSpan::plain("gone")
+            },
+            TextSpan::plain(" "),
+            TextSpan {
+                marks: vec![Mark::Highlight("#ff0".to_string())],
+                ..TextSpan::plain("lit")
+            },
+        ];
+        let block =
+            Block::new(Uuid::new_v4(), BlockType::BulletList, String::new()).with_spans(spans);
+        let markdown = render_block(&block, 0, &|_| None).join("\n");
+
+        let parsed = crate::import::markdown::parse(&markdown).blocks;
+        assert_eq!(parsed.len(), 1, "{}", markdown);
+        assert_eq!(parsed[0].content, block.content);
+        assert_eq!(parsed[0].spans, block.spans, "{}", markdown);
+    }
+
+    #[test]
+    fn odd_colours_are_dropped() {
+        let spans = vec![
+            TextSpan {
+                marks: vec![Mark::Color("red\" onmouseover=\"alert(1)".to_string())],
+                ..TextSpan::plain("a")
+            },
+            TextSpan {
+                marks: vec![Mark::Highlight("rgb(255, 0, 0)".to_string())],
+                ..TextSpan::plain("b")
+            },
+        ];
+        let block = Block::new(Uuid::new_v4(), BlockType::Text, String::new()).with_spans(spans);
+        assert_eq!(
+            render_block(&block, 0, &|_| None).join("\n"),
+            "a<mark style=\"background-color: rgb(255, 0, 0)\">b</mark>"
+        );
+    }
+
+    #[test]
+    fn plain_text_survives_a_round_trip() {
+        for content in [
+            "2*3*4 = 24",
+            "snake_case_name and __init__",
+            "see [x](y) and ![img](a.png)",
+            "~~not struck~~ ~tilde~",
+            "use `code` or ``two``",
+            "a <b>tag</b> &amp; \\ backslash",
+            "# hash\n- dash\n1. one\n> quote",
+        ] {
+            let block = Block::new(Uuid::new_v4(), BlockType::Text, content.to_string());
+            let markdown = render_block(&block, 0, &|_| None).join("\n");
+
+            let parsed = crate::import::markdown::parse(&markdown).blocks;
+            assert_eq!(parsed.len(), 1, "{}", markdown);
+            assert_eq!(parsed[0].content, content, "{}", markdown);
+            assert_eq!(parsed[0].spans, None, "{}", markdown);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+pub mod html;
+pub mod markdown;
+
+use crate::models::{Block, Page};
+use crate::storage::Database;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+/*
+ * shared bits for exporters: which pages go where, nesting blocks, copying assets
+ * a page "Notes" with subpages turns into Notes.md plus a Notes/ folder holding the children,
+ * icons and covers are copied into assets/ at the root of the export
+ */
+pub const ASSETS_DIRNAME: &str = "assets";
+
+pub struct ExportedPage {
+    pub page: Page,
+    pub blocks: Vec<Block>,
+    pub path: PathBuf, // relative to the export root
+}
+
+pub struct ExportPlan {
+    pub pages: Vec<ExportedPage>,
+    paths: HashMap<String, PathBuf>,
+    assets: HashSet<String>,
+}
+
+impl ExportPlan {
+    // the page and every subpage that isnt in the trash, with the file each one goes to
+    pub fn build(
+        db: &Database,
+        page_id: &str,
+        assets_dir: &Path,
+        extension: &str,
+    ) -> Result<Self, String> {
+        Self::build_all(db, &[page_id], assets_dir, extension, &[])
+    }
+
+    // several top level pages side by side, `reserved` stems are kept free for other files
+    pub fn build_all(
+        db: &Database,
+        page_ids: &[&str],
+        assets_dir: &Path,
+        extension: &str,
+        reserved: &[&str],
+    ) -> Result<Self, String> {
+        let mut plan = ExportPlan {
+            pages: Vec::new(),
+            paths: HashMap::new(),
+            assets: HashSet::new(),
+        };
+        let mut used: HashSet<String> = std::iter::once(ASSETS_DIRNAME)
+            .chain(reserved.iter().copied())
+            .map(|s| s.to_lowercase())
+            .collect();
+
+        for page_id in page_ids {
+            let (pages, blocks) = db
+                .page_subtree(page_id)
+                .map_err(|e| format!("failed to load pages D: {}", e))?;
+            let root = pages
+                .first()
+                .cloned()
+                .ok_or_else(|| format!("page not found D: {}", page_id))?;
+
+            let mut children: HashMap<String, Vec<Page>> = HashMap::new();
+            for page in pages.into_iter().skip(1).filter(|p| !p.is_archived) {
+                if let Some(parent_id) = page.parent_id.clone() {
+                    children.entry(parent_id).or_default().push(page);
+                }
+            }
+            let mut blocks_by_page: HashMap<String, Vec<Block>> = HashMap::new();
+            for block in blocks {
+                blocks_by_page
+                    .entry(block.page_id.to_string())
+                    .or_default()
+                    .push(block);
+            }
+
+            let path = PathBuf::from(format!(
+                "{}.{}",
+                unique_stem(&root.title, &mut used),
+                extension
+            ));
+            plan.add(
+                root,
+                path,
+                &mut children,
+                &mut blocks_by_page,
+                assets_dir,
+                extension,
+            );
+        }
+        Ok(plan)
+    }
+
+    fn add(
+        &mut self,
+        page: Page,
+        path: PathBuf,
+        children: &mut HashMap<String, Vec<Page>>,
+        blocks_by_page: &mut HashMap<String, Vec<Block>>,
+        assets_dir: &Path,
+        extension: &str,
+    ) {
+        for asset in [&page.icon, &page.cover].into_iter().flatten() {
+            if is_asset_file(assets_dir, asset) {
+                self.assets.insert(asset.clone());
+            }
+        }
+
+        let mut kids = children.remove(&page.id).unwrap_or_default();
+        kids.sort_by_key(|p| p.created_at);
+        let folder = path.with_extension("");
+        let blocks = blocks_by_page.remove(&page.id).unwrap_or_default();
+        self.paths.insert(page.id.clone(), path.clone());
+        self.pages.push(ExportedPage { page, blocks, path });
+
+        let mut used = HashSet::new();
+        for child in kids {
+            let child_path = folder.join(format!(
+                "{}.{}",
+                unique_stem(&child.title, &mut used),
+                extension
+            ));
+            self.add(
+                child,
+                child_path,
+                children,
+                blocks_by_page,
+                assets_dir,
+                extension,
+            );
+        }
+    }
+
+    pub fn path_of(&self, page_id: &str) -> Option<&Path> {
+        self.paths.get(page_id).map(|p| p.as_path())
+    }
+
+    // icons can be emojis, only the ones that are actual files in page_assets count
+    pub fn is_asset(&self, value: &str) -> bool {
+        self.assets.contains(value)
+    }
+
+    pub fn asset_path(&self, filename: &str) -> PathBuf {
+        Path::new(ASSETS_DIRNAME).join(filename)
+    }
+
+    // writes every page with `render` and copies the assets, returns the files written
+    pub fn write(
+        &self,
+        destination: &Path,
+        assets_dir: &Path,
+        render: impl Fn(&ExportedPage) -> String,
+    ) -> Result<(Vec<String>, Vec<String>), String> {
+        let mut files = Vec::new();
+        for exported in &self.pages {
+            let target = destination.join(&exported.path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("failed to create folder D: {}", e))?;
+            }
+            fs::write(&target, render(exported))
+                .map_err(|e| format!("failed to write {:?} D: {}", target, e))?;
+            files.push(target.to_string_lossy().to_string());
+        }
+
+        let mut assets: Vec<String> = Vec::new();
+        if !self.assets.is_empty() {
+            let assets_target = destination.join(ASSETS_DIRNAME);
+            fs::create_dir_all(&assets_target)
+                .map_err(|e| format!("failed to create assets folder D: {}", e))?;
+            for asset in &self.assets {
+                fs::copy(assets_dir.join(asset), assets_target.join(asset))
+                    .map_err(|e| format!("failed to copy asset {} D: {}", asset, e))?;
+                assets.push(asset.clone());
+            }
+            assets.sort();
+        }
+
+        Ok((files, assets))
+    }
+}
+
+// children of each block (None for the top level) in their `order`
+pub fn block_tree(blocks: &[Block]) -> HashMap<Option<Uuid>, Vec<&Block>> {
+    let mut tree: HashMap<Option<Uuid>, Vec<&Block>> = HashMap::new();
+    for block in blocks {
+        tree.entry(block.parent_id).or_default().push(block);
+    }
+    for children in tree.values_mut() {
+        children.sort_by_key(|b| b.order);
+    }
+    tree
+}
+
+// a link from one exported file to another, "../assets/cover%20art.png"
+pub fn relative_link(from_file: &Path, to: &Path) -> String {
+    let from_dir: Vec<Component> = from_file
+        .parent()
+        .map(|p| p.components().collect())
+        .unwrap_or_default();
+    let to: Vec<Component> = to.components().collect();
+    let common = from_dir.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from_dir.len() - common];
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|c| urlencoding::encode(&c.as_os_str().to_string_lossy()).into_owned()),
+    );
+    parts.join("/")
+}
+
+// "What? / Why: Notes" -> "What Why Notes", safe as a file name everywhere
+fn file_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .filter(|c| {
+            !matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') && !c.is_control()
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned = cleaned.trim_matches('.').trim();
+    if cleaned.is_empty() {
+        "Untitled".to_string()
+    } else {
+        cleaned.chars().take(100).collect()
+    }
+}
+
+// siblings with the same title get " (2)", " (3)"... compared case insensitively for mac/windows
+fn unique_stem(title: &str, used: &mut HashSet<String>) -> String {
+    let stem = file_stem(title);
+    let mut candidate = stem.clone();
+    let mut n = 2;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{} ({})", stem, n);
+        n += 1;
+    }
+    candidate
+}
+
+fn is_asset_file(assets_dir: &Path, value: &str) -> bool {
+    Path::new(value).file_name() == Some(value.as_ref()) && assets_dir.join(value).is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stems_and_links() {
+        let mut used = HashSet::from([ASSETS_DIRNAME.to_string()]);
+        assert_eq!(
+            unique_stem("What? / Why: Notes", &mut used),
+            "What Why Notes"
+        );
+        assert_eq!(
+            unique_stem("what why notes", &mut used),
+            "what why notes (2)"
+        );
+        assert_eq!(unique_stem("Assets", &mut used), "Assets (2)");
+        assert_eq!(unique_stem("  ", &mut used), "Untitled");
+
+        assert_eq!(
+            relative_link(Path::new("A/B.md"), Path::new("assets/cover art.png")),
+            "../assets/cover%20art.png"
+        );
+        assert_eq!(
+            relative_link(Path::new("A.md"), Path::new("A/B.md")),
+            "A/B.md"
+        );
+        assert_eq!(
+            relative_link(Path::new("A/B/C.md"), Path::new("A/D.md")),
+            "../D.md"
+        );
+    }
+}
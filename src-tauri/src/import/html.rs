@@ -0,0 +1,480 @@
+use super::{is_web_link, ParsedBlock, ParsedPage};
+use crate::models::BlockType;
+use scraper::{ElementRef, Html, Node, Selector};
+
+/*
+ * html pages into blocks, written against what notion's html export looks like (page-title,
+ * page-body, to-do-list, callout figures...) but plain html mostly works too. anything without
+ * a block type (tables, images) is flattened to text and reported in the warnings
+ */
+pub fn parse(html: &str, is_page_link: fn(&str) -> bool) -> ParsedPage {
+    let document = Html::parse_document(html);
+    let mut reader = Reader {
+        is_page_link,
+        doc: ParsedPage::default(),
+    };
+
+    let first = |selector: &str| {
+        let selector = Selector::parse(selector).ok()?;
+        document.select(&selector).next()
+    };
+    reader.doc.title = first("h1.page-title")
+        .or_else(|| first("title"))
+        .map(|el| collapse(&el.text().collect::<String>()))
+        .filter(|t| !t.is_empty());
+    reader.doc.cover = first("img.page-cover-image")
+        .and_then(|el| el.attr("src"))
+        .map(|src| src.to_string());
+    reader.doc.icon = first(".page-header-icon img")
+        .and_then(|el| el.attr("src"))
+        .map(|src| src.to_string())
+        .or_else(|| {
+            first(".page-header-icon")
+                .map(|el| collapse(&el.text().collect::<String>()))
+                .filter(|t| !t.is_empty())
+        });
+
+    // database rows keep their properties in a table above the body
+    let mut blocks = Vec::new();
+    if let Ok(rows) = Selector::parse("table.properties tr") {
+        for row in document.select(&rows) {
+            let cells: Vec<String> = row
+                .child_elements()
+                .map(|cell| collapse(&cell.text().collect::<String>()))
+                .collect();
+            if let [name, value] = cells.as_slice() {
+                blocks.push(ParsedBlock::new(
+                    BlockType::Text,
+                    format!("{}: {}", name, value),
+                ));
+            }
+        }
+    }
+
+    let body = first(".page-body").or_else(|| first("body"));
+    if let Some(body) = body {
+        blocks.extend(reader.blocks(body));
+    }
+    reader.doc.blocks = blocks;
+    reader.doc
+}
+
+struct Reader {
+    is_page_link: fn(&str) -> bool,
+    doc: ParsedPage,
+}
+
+// text of a paragraph-ish element plus the page links inside it
+#[derive(Default)]
+struct Inline {
+    text: String,
+    outside_links: String,
+    links: Vec<(String, String)>,
+}
+
+impl Inline {
+    fn push(&mut self, text: &str, in_link: bool) {
+        self.text.push_str(text);
+        if !in_link {
+            self.outside_links.push_str(text);
+        }
+    }
+
+    // same rules as markdown: a lone link is a link block, links inside text follow the text
+    fn finish(self, block_type: BlockType) -> Vec<ParsedBlock> {
+        let text = self
+            .text
+            .lines()
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join("\n")
+            .trim()
+            .to_string();
+        if self.links.len() == 1 && self.outside_links.trim().is_empty() {
+            let (link_text, url) = self.links.into_iter().next().unwrap();
+            return vec![ParsedBlock::link(collapse(&link_text), url)];
+        }
+        let mut blocks = Vec::new();
+        if !text.is_empty() {
+            blocks.push(ParsedBlock::new(block_type, text));
+        }
+        blocks.extend(
+            self.links
+                .into_iter()
+                .map(|(link_text, url)| ParsedBlock::link(collapse(&link_text), url)),
+        );
+        blocks
+    }
+}
+
+impl Reader {
+    // the block level children of `parent`, loose text around them becomes paragraphs
+    fn blocks(&mut self, parent: ElementRef) -> Vec<ParsedBlock> {
+        let mut out = Vec::new();
+        let mut loose = Inline::default();
+        for child in parent.children() {
+            match ElementRef::wrap(child) {
+                Some(el) if is_block(el) => {
+                    out.extend(std::mem::take(&mut loose).finish(BlockType::Text));
+                    self.block(el, &mut out);
+                }
+                Some(el) => self.inline(el, &mut loose, false),
+                None => push_text(child.value(), &mut loose, false),
+            }
+        }
+        out.extend(loose.finish(BlockType::Text));
+        out
+    }
+
+    fn block(&mut self, el: ElementRef, out: &mut Vec<ParsedBlock>) {
+        match el.value().name() {
+            "p" => out.extend(self.paragraph(el, BlockType::Text)),
+            "h1" => out.extend(self.paragraph(el, BlockType::Heading1)),
+            "h2" => out.extend(self.paragraph(el, BlockType::Heading2)),
+            "h3" | "h4" | "h5" | "h6" => out.extend(self.paragraph(el, BlockType::Heading3)),
+            "ul" | "ol" => {
+                for item in el.child_elements().filter(|c| c.value().name() == "li") {
+                    out.push(self.item(item, list_type(el, item)));
+                }
+            }
+            "li" => out.push(self.item(el, BlockType::BulletList)),
+            "pre" => {
+                let language = el
+                    .descendent_elements()
+                    .flat_map(|c| c.value().classes())
+                    .find_map(|class| class.strip_prefix("language-"))
+                    .unwrap_or_default()
+                    .to_lowercase();
+                let code: String = el.text().collect();
+                out.push(ParsedBlock::new(
+                    BlockType::Code { language },
+                    code.trim_end_matches('\n').to_string(),
+                ));
+            }
+            "blockquote" => out.push(self.container(el, BlockType::Quote)),
+            "details" => out.push(self.container(el, BlockType::BulletList)),
+            "hr" => out.push(ParsedBlock::new(BlockType::Divider, String::new())),
+            "figure" if has_class(el, "callout") => {
+                // an icon div and a text div, the icon goes in front like page titles do
+                let icon = el
+                    .child_elements()
+                    .next()
+                    .map(|c| collapse(&c.text().collect::<String>()))
+                    .unwrap_or_default();
+                let mut quote = match el.child_elements().last() {
+                    Some(body) => self.container(body, BlockType::Quote),
+                    None => ParsedBlock::new(BlockType::Quote, String::new()),
+                };
+                if !icon.is_empty() && quote.content != icon {
+                    quote.content = format!("{} {}", icon, quote.content).trim().to_string();
+                }
+                out.push(quote);
+            }
+            "figure" => {
+                let mut inline = Inline::default();
+                self.inline_children(el, &mut inline, false);
+                out.extend(inline.finish(BlockType::Text));
+            }
+            "table" => {
+                self.doc
+                    .warnings
+                    .push("a table was flattened to text".to_string());
+                out.extend(self.table(el));
+            }
+            "div" if has_class(el, "checkbox") => {}
+            "div" if has_class(el, "indented") => {
+                let children = self.blocks(el);
+                match out.last_mut() {
+                    Some(last) => last.children.extend(children),
+                    None => out.extend(children),
+                }
+            }
+            "script" | "style" | "head" => {}
+            _ => out.extend(self.blocks(el)),
+        }
+    }
+
+    fn paragraph(&mut self, el: ElementRef, block_type: BlockType) -> Vec<ParsedBlock> {
+        let mut inline = Inline::default();
+        self.inline_children(el, &mut inline, false);
+        inline.finish(block_type)
+    }
+
+    fn item(&mut self, li: ElementRef, block_type: BlockType) -> ParsedBlock {
+        // toggles are a details element inside the list item
+        let only_child = li.child_elements().collect::<Vec<_>>();
+        if let [details] = only_child.as_slice() {
+            if details.value().name() == "details" {
+                return self.container(*details, block_type);
+            }
+        }
+        self.container(li, block_type)
+    }
+
+    // list items, quotes and toggles: their own text first, nested blocks become children
+    fn container(&mut self, el: ElementRef, block_type: BlockType) -> ParsedBlock {
+        let mut inline = Inline::default();
+        let mut children = Vec::new();
+        for child in el.children() {
+            match ElementRef::wrap(child) {
+                Some(c) if c.value().name() == "summary" => {
+                    self.inline_children(c, &mut inline, false)
+                }
+                Some(c) if has_class(c, "indented") => children.extend(self.blocks(c)),
+                Some(c) if is_block(c) => self.block(c, &mut children),
+                Some(c) => self.inline(c, &mut inline, false),
+                None => push_text(child.value(), &mut inline, false),
+            }
+        }
+
+        let mut parts = inline.finish(BlockType::Text).into_iter();
+        let mut block = ParsedBlock::new(block_type, String::new());
+        let mut leading = Vec::new();
+        if let Some(first) = parts.next() {
+            if first.link.is_none() {
+                block.content = first.content;
+            } else {
+                block.content = first.content.clone();
+                leading.push(first);
+            }
+        } else if children
+            .first()
+            .is_some_and(|c| c.block_type == BlockType::Text && c.link.is_none())
+        {
+            // <blockquote><p>text</p></blockquote>
+            block.content = children.remove(0).content;
+        }
+        leading.extend(parts);
+        leading.extend(children);
+        block.children = leading;
+        block
+    }
+
+    fn table(&mut self, el: ElementRef) -> Vec<ParsedBlock> {
+        let mut inline = Inline::default();
+        for row in el
+            .descendent_elements()
+            .filter(|c| c.value().name() == "tr")
+        {
+            for (idx, cell) in row.child_elements().enumerate() {
+                if idx > 0 {
+                    inline.push(" | ", false);
+                }
+                let mut text = Inline::default();
+                self.inline_children(cell, &mut text, false);
+                inline.push(&collapse(&text.text), false);
+                inline.links.extend(text.links);
+            }
+            inline.push("\n", false);
+        }
+        inline.finish(BlockType::Text)
+    }
+
+    fn inline_children(&mut self, el: ElementRef, inline: &mut Inline, in_link: bool) {
+        for child in el.children() {
+            match ElementRef::wrap(child) {
+                Some(c) => self.inline(c, inline, in_link),
+                None => push_text(child.value(), inline, in_link),
+            }
+        }
+    }
+
+    fn inline(&mut self, el: ElementRef, inline: &mut Inline, in_link: bool) {
+        match el.value().name() {
+            "br" => inline.push("\n", in_link),
+            "code" if !in_link => {
+                let code: String = el.text().collect();
+                inline.push(&format!("`{}`", code), in_link);
+            }
+            "img" => self.image(el, inline),
+            "a" if !in_link => self.link(el, inline),
+            "script" | "style" => {}
+            "div" if has_class(el, "checkbox") => {}
+            // notion puts the page icon in front of links to pages
+            "span" if in_link && has_class(el, "icon") => {}
+            _ => self.inline_children(el, inline, in_link),
+        }
+    }
+
+    fn link(&mut self, el: ElementRef, inline: &mut Inline) {
+        let href = el.attr("href").unwrap_or_default().to_string();
+        let mut text = Inline::default();
+        self.inline_children(el, &mut text, true);
+        // a linked image is just the image
+        if el.descendent_elements().any(|c| c.value().name() == "img") {
+            inline.push(&text.text, false);
+            return;
+        }
+        let link_text = collapse(&text.text);
+
+        if !href.is_empty() && (self.is_page_link)(&href) {
+            inline.push(&text.text, true);
+            inline.links.push((link_text, href));
+        } else {
+            inline.push(&text.text, false);
+            if !href.is_empty() && link_text != href {
+                // keep where it went, the editor has no inline links yet
+                inline.push(&format!(" ({})", href), false);
+            }
+        }
+    }
+
+    fn image(&mut self, el: ElementRef, inline: &mut Inline) {
+        let Some(src) = el.attr("src") else {
+            return;
+        };
+        let alt = el.attr("alt").unwrap_or_default();
+        inline.push(&format!("![{}]({})", alt, src), false);
+        if !is_web_link(src) {
+            self.doc.images.push(src.to_string());
+        }
+    }
+}
+
+fn push_text(node: &Node, inline: &mut Inline, in_link: bool) {
+    if let Node::Text(text) = node {
+        inline.push(&squash_spaces(text), in_link);
+    }
+}
+
+fn list_type(list: ElementRef, item: ElementRef) -> BlockType {
+    if has_class(list, "to-do-list") {
+        let checked = item
+            .descendent_elements()
+            .any(|c| has_class(c, "checkbox-on"));
+        BlockType::Todo { checked }
+    } else if list.value().name() == "ol" {
+        BlockType::NumberedList
+    } else {
+        BlockType::BulletList
+    }
+}
+
+fn is_block(el: ElementRef) -> bool {
+    matches!(
+        el.value().name(),
+        "p" | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "ul"
+            | "ol"
+            | "li"
+            | "pre"
+            | "blockquote"
+            | "details"
+            | "hr"
+            | "figure"
+            | "table"
+            | "div"
+            | "section"
+            | "article"
+            | "header"
+            | "main"
+            | "aside"
+            | "nav"
+            | "script"
+            | "style"
+    )
+}
+
+fn has_class(el: ElementRef, class: &str) -> bool {
+    el.value().classes().any(|c| c == class)
+}
+
+// html whitespace, runs of spaces and newlines are one space
+fn squash_spaces(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !space {
+                out.push(' ');
+            }
+            space = true;
+        } else {
+            out.push(c);
+            space = false;
+        }
+    }
+    out
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_notion_page() {
+        let doc = parse(
+            r#"<html><head><title>Plans</title></head><body><article class="page sans">
+<header><img class="page-cover-image" src="Plans%20abc/beach.png"/>
+<div class="page-header-icon undefined"><span class="icon">🌴</span></div>
+<h1 class="page-title">Plans</h1></header>
+<div class="page-body">
+<h2 id="1">Todo</h2>
+<ul id="2" class="bulleted-list"><li style="list-style-type:disc">pack<ul class="bulleted-list"><li>socks</li></ul></li></ul>
+<ul id="3" class="to-do-list"><li><div class="checkbox checkbox-on"></div> <span class="to-do-children-checked">book</span></li></ul>
+<ol type="1" class="numbered-list" start="1"><li>one</li></ol>
+<p id="4">see <a href="https://example.com">a site</a><br/>and <a href="Plans%20abc/Budget%20def.html">Budget</a></p>
+<pre id="5" class="code"><code class="language-Rust">let a = 1;
+</code></pre>
+<figure class="callout"><div style="font-size:1.5em"><span class="icon">💡</span></div><div style="width:100%">careful</div></figure>
+<hr id="6"/>
+<figure id="7" class="link-to-page"><a href="Plans%20abc/Trip%20fed.html"><span class="icon">📄</span>Trip</a></figure>
+<figure id="8" class="image"><a href="Plans%20abc/map.png"><img style="width:100px" src="Plans%20abc/map.png"/></a></figure>
+<table class="simple-table"><tbody><tr><td>a</td><td>b</td></tr></tbody></table>
+</div></article></body></html>"#,
+            |url| !is_web_link(url),
+        );
+
+        assert_eq!(doc.title.as_deref(), Some("Plans"));
+        assert_eq!(doc.cover.as_deref(), Some("Plans%20abc/beach.png"));
+        assert_eq!(doc.icon.as_deref(), Some("🌴"));
+        assert_eq!(doc.images, vec!["Plans%20abc/map.png".to_string()]);
+        assert_eq!(doc.warnings.len(), 1);
+
+        let text = |block_type: BlockType, content: &str| {
+            ParsedBlock::new(block_type, content.to_string())
+        };
+        let mut pack = text(BlockType::BulletList, "pack");
+        pack.children = vec![text(BlockType::BulletList, "socks")];
+        assert_eq!(
+            doc.blocks,
+            vec![
+                text(BlockType::Heading2, "Todo"),
+                pack,
+                text(BlockType::Todo { checked: true }, "book"),
+                text(BlockType::NumberedList, "one"),
+                text(
+                    BlockType::Text,
+                    "see a site (https://example.com)\nand Budget"
+                ),
+                ParsedBlock::link(
+                    "Budget".to_string(),
+                    "Plans%20abc/Budget%20def.html".to_string()
+                ),
+                text(
+                    BlockType::Code {
+                        language: "rust".to_string()
+                    },
+                    "let a = 1;"
+                ),
+                text(BlockType::Quote, "💡 careful"),
+                text(BlockType::Divider, ""),
+                ParsedBlock::link(
+                    "Trip".to_string(),
+                    "Plans%20abc/Trip%20fed.html".to_string()
+                ),
+                text(BlockType::Text, "![](Plans%20abc/map.png)"),
+                text(BlockType::Text, "a | b"),
+            ]
+        );
+    }
+}
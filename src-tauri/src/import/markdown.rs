@@ -0,0 +1,571 @@
+use super::{import_tree, is_web_link, ImportPlan, Layout, ParsedBlock, ParsedPage, Source};
+use crate::models::rich_text::{self, Mark, TextSpan};
+use crate::models::BlockType;
+use crate::storage::Database;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd};
+use std::fs;
+use std::path::Path;
+
+/*
+ * markdown files (or a folder of them) into pages, the layout is the one export writes:
+ * Notes.md with a Notes/ folder next to it holding the subpages. a folder without a matching
+ * file still becomes a page so nothing ends up loose in the sidebar
+ */
+pub fn import_path(
+    db: &Database,
+    assets_dir: &Path,
+    source: &Path,
+    parent_id: Option<&str>,
+) -> Result<ImportPlan, String> {
+    let root = Source::from_path(source, &MarkdownFiles)
+        .ok_or_else(|| format!("no markdown files in {:?}", source))?;
+    import_tree(db, assets_dir, root, parent_id, &MarkdownFiles)
+}
+
+struct MarkdownFiles;
+
+impl Layout for MarkdownFiles {
+    fn is_page(&self, path: &Path) -> bool {
+        is_markdown(path)
+    }
+
+    fn parse(&self, file: &Path) -> Result<ParsedPage, String> {
+        let text =
+            fs::read_to_string(file).map_err(|e| format!("failed to read {:?} D: {}", file, e))?;
+        Ok(parse(&text))
+    }
+}
+
+fn is_image(target: &str) -> bool {
+    let ext = target.rsplit('.').next().unwrap_or_default().to_lowercase();
+    matches!(
+        ext.as_str(),
+        "png" | "jpg" | "jpeg" | "gif" | "webp" | "svg" | "bmp" | "avif"
+    )
+}
+
+enum ColourTag {
+    Open(Mark),
+    CloseSpan,
+    CloseMark,
+}
+
+// <span style="color: red">, <mark style="background-color: yellow"> and their closing tags
+fn colour_tag(html: &str) -> Option<ColourTag> {
+    let html = html.trim();
+    match html {
+        "</span>" => return Some(ColourTag::CloseSpan),
+        "</mark>" => return Some(ColourTag::CloseMark),
+        _ => {}
+    }
+    let style = |prefix: &str, property: &str| {
+        let value = html
+            .strip_prefix(prefix)?
+            .strip_suffix("\">")?
+            .strip_prefix(property)?
+            .trim()
+            .trim_end_matches(';')
+            .trim();
+        (!value.is_empty() && !value.contains(['"', '<', '>'])).then(|| value.to_string())
+    };
+    style("<span style=\"", "color:")
+        .map(Mark::Color)
+        .or_else(|| style("<mark style=\"", "background-color:").map(Mark::Highlight))
+        .map(ColourTag::Open)
+}
+
+pub fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+pub fn parse(markdown: &str) -> ParsedPage {
+    parse_with(markdown, |url| !is_web_link(url), false)
+}
+
+// `is_page_link` picks the links that might point at another imported page,
+// `wikilinks` turns on [[Page]] / [[Page|text]] / ![[image.png]]
+pub fn parse_with(markdown: &str, is_page_link: fn(&str) -> bool, wikilinks: bool) -> ParsedPage {
+    let mut options = Options::ENABLE_TASKLISTS
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+    if wikilinks {
+        options |= Options::ENABLE_WIKILINKS;
+    }
+    let mut builder = Builder {
+        frames: Vec::new(),
+        leaf: None,
+        marks: Vec::new(),
+        in_metadata: false,
+        is_page_link,
+        doc: ParsedPage::default(),
+    };
+    for event in Parser::new_ext(markdown, options) {
+        builder.event(event);
+    }
+    builder.close_leaf();
+    builder.doc
+}
+
+// a paragraph, heading or code block that is still collecting text
+struct Leaf {
+    block_type: BlockType,
+    text: String,
+    spans: Vec<TextSpan>,  // the same text with its formatting
+    outside_links: String, // to spot paragraphs that are nothing but a link
+    links: Vec<ParsedBlock>,
+    open_link: Option<(String, String, bool)>, // url, text so far, is it a [[wikilink]]
+    open_image: Option<(String, String)>,
+    header_images: Vec<(String, String)>,
+}
+
+enum Frame {
+    List { numbered: bool },
+    // list items and quotes, the first paragraph is their text and the rest nests under them
+    Container { block: ParsedBlock, started: bool },
+}
+
+struct Builder {
+    frames: Vec<Frame>,
+    leaf: Option<Leaf>,
+    marks: Vec<Mark>, // bold/italic/... that are open right now
+    in_metadata: bool,
+    is_page_link: fn(&str) -> bool,
+    doc: ParsedPage,
+}
+
+impl Builder {
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Paragraph) | Event::Start(Tag::HtmlBlock) => {
+                self.open(BlockType::Text)
+            }
+            Event::Start(Tag::Heading { level, .. }) => self.open(match level {
+                HeadingLevel::H1 => BlockType::Heading1,
+                HeadingLevel::H2 => BlockType::Heading2,
+                _ => BlockType::Heading3,
+            }),
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.open(BlockType::Code { language })
+            }
+            Event::End(TagEnd::Paragraph)
+            | Event::End(TagEnd::HtmlBlock)
+            | Event::End(TagEnd::Heading(_))
+            | Event::End(TagEnd::CodeBlock) => self.close_leaf(),
+
+            Event::Start(Tag::List(start)) => {
+                self.close_leaf();
+                self.frames.push(Frame::List {
+                    numbered: start.is_some(),
+                });
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.close_leaf();
+                self.frames.pop();
+            }
+            Event::Start(Tag::Item) => {
+                self.close_leaf();
+                let numbered = matches!(self.frames.last(), Some(Frame::List { numbered: true }));
+                let block_type = if numbered {
+                    BlockType::NumberedList
+                } else {
+                    BlockType::BulletList
+                };
+                self.push_container(block_type);
+            }
+            Event::Start(Tag::BlockQuote(_)) => {
+                self.close_leaf();
+                self.push_container(BlockType::Quote);
+            }
+            Event::End(TagEnd::Item) | Event::End(TagEnd::BlockQuote(_)) => {
+                self.close_leaf();
+                if let Some(Frame::Container { block, .. }) = self.frames.pop() {
+                    self.deliver(block);
+                }
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(Frame::Container { block, .. }) = self.frames.last_mut() {
+                    block.block_type = BlockType::Todo { checked };
+                }
+            }
+            Event::Rule => {
+                self.close_leaf();
+                self.deliver(ParsedBlock::new(BlockType::Divider, String::new()));
+            }
+
+            Event::Start(Tag::MetadataBlock(_)) => self.in_metadata = true,
+            Event::End(TagEnd::MetadataBlock(_)) => self.in_metadata = false,
+            Event::Start(Tag::Emphasis) => self.marks.push(Mark::Italic),
+            Event::Start(Tag::Strong) => self.marks.push(Mark::Bold),
+            Event::Start(Tag::Strikethrough) => self.marks.push(Mark::Strikethrough),
+            Event::End(TagEnd::Emphasis) => self.close_mark(|m| *m == Mark::Italic),
+            Event::End(TagEnd::Strong) => self.close_mark(|m| *m == Mark::Bold),
+            Event::End(TagEnd::Strikethrough) => self.close_mark(|m| *m == Mark::Strikethrough),
+            // colours are written as inline html by the exporter
+            Event::InlineHtml(html) => match colour_tag(&html) {
+                Some(ColourTag::Open(mark)) => self.marks.push(mark),
+                Some(ColourTag::CloseSpan) => self.close_mark(|m| matches!(m, Mark::Color(_))),
+                Some(ColourTag::CloseMark) => self.close_mark(|m| matches!(m, Mark::Highlight(_))),
+                None => self.push_text(&html),
+            },
+            Event::Text(text) | Event::Html(text) => self.push_text(&text),
+            Event::Code(code) => {
+                self.marks.push(Mark::Code);
+                self.push_text(&code);
+                self.marks.pop();
+            }
+            Event::SoftBreak | Event::HardBreak => self.push_text("\n"),
+
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            }) => {
+                let wiki = matches!(link_type, LinkType::WikiLink { .. });
+                self.leaf().open_link = Some((dest_url.to_string(), String::new(), wiki));
+            }
+            Event::End(TagEnd::Link) => self.close_link(),
+            // ![[Other note]] embeds a note, treat it like a link to it
+            Event::Start(Tag::Image {
+                link_type: LinkType::WikiLink { .. },
+                dest_url,
+                ..
+            }) if !is_image(&dest_url) => {
+                self.leaf().open_link = Some((dest_url.to_string(), String::new(), true));
+            }
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                self.leaf().open_image = Some((dest_url.to_string(), String::new()));
+            }
+            Event::End(TagEnd::Image) => {
+                if self.leaf.as_ref().is_some_and(|l| l.open_image.is_some()) {
+                    self.close_image()
+                } else {
+                    self.close_link()
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn open(&mut self, block_type: BlockType) {
+        self.close_leaf();
+        self.leaf = Some(Leaf {
+            block_type,
+            text: String::new(),
+            spans: Vec::new(),
+            outside_links: String::new(),
+            links: Vec::new(),
+            open_link: None,
+            open_image: None,
+            header_images: Vec::new(),
+        });
+    }
+
+    // text right inside a tight list item comes without a paragraph around it
+    fn leaf(&mut self) -> &mut Leaf {
+        if self.leaf.is_none() {
+            self.open(BlockType::Text);
+        }
+        self.leaf.as_mut().unwrap()
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if self.in_metadata {
+            return;
+        }
+        let is_page_link = self.is_page_link;
+        let marks = self.marks.clone();
+        let leaf = self.leaf();
+        if let Some((_, alt)) = &mut leaf.open_image {
+            alt.push_str(text);
+            return;
+        }
+        leaf.text.push_str(text);
+        // links to other imported files become their own block, the rest stay inline
+        let link = match &leaf.open_link {
+            Some((url, _, false)) if !is_page_link(url) => Some(url.clone()),
+            _ => None,
+        };
+        leaf.spans.push(TextSpan {
+            text: text.to_string(),
+            marks,
+            link,
+        });
+        match &mut leaf.open_link {
+            Some((_, link_text, _)) => link_text.push_str(text),
+            None => leaf.outside_links.push_str(text),
+        }
+    }
+
+    fn close_link(&mut self) {
+        let is_page_link = self.is_page_link;
+        let leaf = self.leaf();
+        let Some((url, text, wiki)) = leaf.open_link.take() else {
+            return;
+        };
+        if wiki && !url.starts_with('#') {
+            leaf.links
+                .push(ParsedBlock::wikilink(text.trim().to_string(), url));
+        } else if !is_page_link(&url) {
+            // stays inline, its spans already carry the url
+            leaf.outside_links.push_str(&text);
+        } else {
+            leaf.links
+                .push(ParsedBlock::link(text.trim().to_string(), url));
+        }
+    }
+
+    fn close_image(&mut self) {
+        let at_top =
+            self.frames.is_empty() && self.doc.blocks.is_empty() && self.doc.title.is_none();
+        let leaf = self.leaf();
+        let Some((url, alt)) = leaf.open_image.take() else {
+            return;
+        };
+        if at_top && (alt == "cover" || alt == "icon") {
+            leaf.header_images.push((alt, url));
+            return;
+        }
+        let image = format!("![{}]({})", alt, url);
+        leaf.text.push_str(&image);
+        leaf.spans.push(TextSpan::plain(&image));
+        leaf.outside_links.push_str(&image);
+        if !is_web_link(&url) {
+            self.doc.images.push(url);
+        }
+    }
+
+    fn close_leaf(&mut self) {
+        let Some(leaf) = self.leaf.take() else {
+            return;
+        };
+        for (alt, url) in leaf.header_images {
+            match alt.as_str() {
+                "cover" => self.doc.cover = Some(url),
+                _ => self.doc.icon = Some(url),
+            }
+        }
+
+        if matches!(leaf.block_type, BlockType::Code { .. }) {
+            let code = leaf.text.strip_suffix('\n').unwrap_or(&leaf.text);
+            self.deliver(ParsedBlock::new(leaf.block_type, code.to_string()));
+            return;
+        }
+
+        let text = leaf.text.trim().to_string();
+        if leaf.links.len() == 1 && leaf.outside_links.trim().is_empty() {
+            self.deliver(leaf.links.into_iter().next().unwrap());
+            return;
+        }
+        if !text.is_empty() {
+            let spans = rich_text::trim(leaf.spans);
+            let spans = (rich_text::plain_text(&spans) == text)
+                .then(|| rich_text::normalize(spans))
+                .flatten();
+            self.deliver(ParsedBlock {
+                spans,
+                ..ParsedBlock::new(leaf.block_type, text)
+            });
+        }
+        // links in the middle of text also show up as their own block right after it
+        for link in leaf.links {
+            self.deliver(link);
+        }
+    }
+
+    // closes the innermost open mark of that kind, stray closing tags are ignored
+    fn close_mark(&mut self, is_kind: impl Fn(&Mark) -> bool) {
+        if let Some(idx) = self.marks.iter().rposition(is_kind) {
+            self.marks.remove(idx);
+        }
+    }
+
+    fn push_container(&mut self, block_type: BlockType) {
+        self.frames.push(Frame::Container {
+            block: ParsedBlock::new(block_type, String::new()),
+            started: false,
+        });
+    }
+
+    // hands a finished block to the innermost item/quote, or the page itself
+    fn deliver(&mut self, block: ParsedBlock) {
+        let container = self.frames.iter_mut().rev().find_map(|frame| match frame {
+            Frame::Container { block, started } => Some((block, started)),
+            Frame::List { .. } => None,
+        });
+
+        match container {
+            Some((parent, started)) => {
+                if !*started && block.block_type == BlockType::Text {
+                    parent.content = block.content.clone();
+                    parent.spans = block.spans.clone();
+                    if block.link.is_some() {
+                        parent.children.push(block);
+                    }
+                } else {
+                    parent.children.push(block);
+                }
+                *started = true;
+            }
+            None => {
+                let is_title = self.doc.title.is_none()
+                    && self.doc.blocks.is_empty()
+                    && block.block_type == BlockType::Heading1;
+                if is_title {
+                    self.doc.title = Some(block.content);
+                } else {
+                    self.doc.blocks.push(block);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{markdown::render_page, ExportPlan, ExportedPage};
+    use crate::models::{Block, Page};
+    use uuid::Uuid;
+
+    fn text(block_type: BlockType, content: &str) -> ParsedBlock {
+        ParsedBlock::new(block_type, content.to_string())
+    }
+
+    #[test]
+    fn parses_every_block_type() {
+        let doc = parse(
+            "![cover](assets/beach.png)
+
+# 🌴 Plans
+
+## Todo
+
+- pack
+  - socks
+- [x] book
+- [ ] pay
+
+1. one
+2. two
+
+\\# not a heading\\
+second line with [a site](https://example.com)
+
+```rust
+let a = 1;
+```
+
+> quoted
+>
+> - nested
+
+---
+
+[Trip](Plans/Trip.md)
+
+see [Budget](Budget.md) first
+",
+        );
+
+        assert_eq!(doc.title.as_deref(), Some("🌴 Plans"));
+        assert_eq!(doc.cover.as_deref(), Some("assets/beach.png"));
+        assert_eq!(doc.icon, None);
+
+        let mut pack = text(BlockType::BulletList, "pack");
+        pack.children = vec![text(BlockType::BulletList, "socks")];
+        let mut quote = text(BlockType::Quote, "quoted");
+        quote.children = vec![text(BlockType::BulletList, "nested")];
+        assert_eq!(
+            doc.blocks,
+            vec![
+                text(BlockType::Heading2, "Todo"),
+                pack,
+                text(BlockType::Todo { checked: true }, "book"),
+                text(BlockType::Todo { checked: false }, "pay"),
+                text(BlockType::NumberedList, "one"),
+                text(BlockType::NumberedList, "two"),
+                ParsedBlock {
+                    spans: Some(vec![
+                        TextSpan::plain("# not a heading\nsecond line with "),
+                        TextSpan {
+                            link: Some("https://example.com".to_string()),
+                            ..TextSpan::plain("a site")
+                        },
+                    ]),
+                    ..text(BlockType::Text, "# not a heading\nsecond line with a site")
+                },
+                text(
+                    BlockType::Code {
+                        language: "rust".to_string()
+                    },
+                    "let a = 1;"
+                ),
+                quote,
+                text(BlockType::Divider, ""),
+                ParsedBlock::link("Trip".to_string(), "Plans/Trip.md".to_string()),
+                text(BlockType::Text, "see Budget first"),
+                ParsedBlock::link("Budget".to_string(), "Budget.md".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn imports_what_export_wrote() {
+        let db = Database::new(":memory:").unwrap();
+        let dir = std::env::temp_dir().join(format!("bismuth-import-{}", Uuid::new_v4()));
+
+        let page = Page::new("Plans".to_string());
+        let child = Page::new("Trip".to_string()).with_parent(page.id.clone());
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let child_id = Uuid::parse_str(&child.id).unwrap();
+        let blocks = vec![
+            Block::new(page_id, BlockType::Heading2, "Soon".to_string()),
+            Block::new(
+                page_id,
+                BlockType::SubPage { page_id: child_id },
+                "Trip".to_string(),
+            )
+            .with_order(1),
+        ];
+        db.insert_page(&page).unwrap();
+        db.insert_page(&child).unwrap();
+        for block in &blocks {
+            db.insert_block(block).unwrap();
+        }
+        let plan = ExportPlan::build(&db, &page.id, &dir, "md").unwrap();
+        plan.write(&dir, &dir, |p: &ExportedPage| render_page(p, &plan))
+            .unwrap();
+
+        let imported = import_path(&db, &dir.join("assets"), &dir.join("Plans.md"), None).unwrap();
+        let summary = imported.summary().unwrap();
+        assert_eq!(summary.root_page.title, "Plans");
+        assert_eq!(summary.page_count, 2);
+
+        db.apply_changes(&imported.changes(), false).unwrap();
+        let trip = db.get_child_pages(&summary.root_page.id).unwrap();
+        assert_eq!(trip.len(), 1);
+        assert_eq!(trip[0].title, "Trip");
+
+        let root_blocks = db.get_page_blocks(&summary.root_page.id).unwrap();
+        let types: Vec<&BlockType> = root_blocks.iter().map(|b| &b.block_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                &BlockType::Heading2,
+                &BlockType::SubPage {
+                    page_id: Uuid::parse_str(&trip[0].id).unwrap()
+                }
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
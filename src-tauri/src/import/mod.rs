@@ -0,0 +1,703 @@
+pub mod html;
+pub mod markdown;
+pub mod notion;
+pub mod vault;
+
+use crate::models::rich_text;
+use crate::models::{Block, BlockType, ImportSummary, Page, TextSpan};
+use crate::storage::undo::Change;
+use crate::storage::Database;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+/*
+ * shared bits for importers: parsers turn a file into ParsedBlocks, the plan gives every source
+ * file a page first so links between files can point at page ids, then turns the parsed blocks
+ * into rows. nothing touches the database here, the command writes plan.changes() in one go
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedBlock {
+    pub block_type: BlockType,
+    pub content: String,
+    pub spans: Option<Vec<TextSpan>>, // content with its formatting, None when plain
+    pub link: Option<String>, // becomes a PageLink/SubPage if this points at an imported file
+    pub wikilink: bool,       // [[Page]] links go by name and get a stub page when missing
+    pub children: Vec<ParsedBlock>,
+}
+
+impl ParsedBlock {
+    pub fn new(block_type: BlockType, content: String) -> Self {
+        ParsedBlock {
+            block_type,
+            content,
+            spans: None,
+            link: None,
+            wikilink: false,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn link(content: String, target: String) -> Self {
+        ParsedBlock {
+            link: Some(target),
+            ..ParsedBlock::new(BlockType::Text, content)
+        }
+    }
+
+    pub fn wikilink(content: String, target: String) -> Self {
+        ParsedBlock {
+            wikilink: true,
+            ..ParsedBlock::link(content, target)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedPage {
+    pub title: Option<String>, // a leading # heading
+    pub icon: Option<String>,  // an image link or an emoji, as written
+    pub cover: Option<String>,
+    pub images: Vec<String>, // relative links of images in the body
+    pub blocks: Vec<ParsedBlock>,
+    pub warnings: Vec<String>, // things the parser had to drop or flatten
+}
+
+// how an importer tells pages apart on disk and reads them
+pub trait Layout {
+    fn is_page(&self, path: &Path) -> bool;
+
+    fn parse(&self, file: &Path) -> Result<ParsedPage, String>;
+
+    // used until the file itself says otherwise
+    fn title(&self, path: &Path) -> String {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    // where the subpages of a page file live
+    fn folder(&self, file: &Path) -> PathBuf {
+        file.with_extension("")
+    }
+
+    // other names links can use for the page of this file, besides its path
+    fn aliases(&self, _file: &Path) -> Vec<String> {
+        Vec::new()
+    }
+
+    // the file an image link in `from_file` points at
+    fn find_file(&self, from_file: &Path, url: &str) -> Option<PathBuf> {
+        link_target(from_file, url).filter(|p| p.is_file())
+    }
+}
+
+pub struct Source {
+    pub title: String,
+    pub file: Option<PathBuf>,
+    pub children: Vec<Source>,
+}
+
+impl Source {
+    // a page file (plus its folder) or a folder, None when theres nothing to import
+    pub fn from_path(path: &Path, layout: &dyn Layout) -> Option<Self> {
+        if path.is_dir() {
+            let children = Source::collect(path, layout);
+            (!children.is_empty()).then(|| Source {
+                title: layout.title(path),
+                file: None,
+                children,
+            })
+        } else if layout.is_page(path) {
+            Some(Source {
+                title: layout.title(path),
+                file: Some(path.to_path_buf()),
+                children: Source::collect(&layout.folder(path), layout),
+            })
+        } else {
+            None
+        }
+    }
+
+    // page files in `dir` with their folders, plus folders that have pages somewhere inside
+    pub fn collect(dir: &Path, layout: &dyn Layout) -> Vec<Source> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| {
+                // .obsidian, .git and friends
+                !p.file_name()
+                    .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+            })
+            .collect();
+        paths.sort();
+
+        let mut sources = Vec::new();
+        let mut claimed = Vec::new();
+        for path in paths.iter().filter(|p| p.is_file() && layout.is_page(p)) {
+            let folder = layout.folder(path);
+            sources.push(Source {
+                title: layout.title(path),
+                file: Some(path.clone()),
+                children: Source::collect(&folder, layout),
+            });
+            claimed.push(folder);
+        }
+        for path in paths.iter().filter(|p| p.is_dir() && !claimed.contains(p)) {
+            if let Some(source) = Source::from_path(path, layout) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+}
+
+// gives every source a page, reads them all, then turns them into blocks
+pub fn import_tree(
+    db: &Database,
+    assets_dir: &Path,
+    root: Source,
+    parent_id: Option<&str>,
+    layout: &dyn Layout,
+) -> Result<ImportPlan, String> {
+    let mut plan = ImportPlan::default();
+    let mut files = Vec::new();
+    register(&mut plan, root, None, layout, &mut files);
+
+    // titles come from the files, so read everything before any links get resolved
+    let mut parsed = Vec::new();
+    for (page_id, file) in files {
+        match layout.parse(&file) {
+            Ok(mut doc) => {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                for warning in doc.warnings.drain(..) {
+                    plan.warn(format!("{}: {}", name, warning));
+                }
+                apply_header(&mut plan, layout, &page_id, &file, &doc, assets_dir);
+                copy_images(&mut plan, layout, &file, &mut doc, assets_dir);
+                parsed.push((page_id, file, doc.blocks));
+            }
+            Err(e) => plan.warn(e),
+        }
+    }
+    for (page_id, file, blocks) in parsed {
+        plan.add_blocks(&page_id, &file, blocks);
+    }
+
+    if let Some(parent_id) = parent_id {
+        if db
+            .get_page(parent_id)
+            .map_err(|e| format!("failed to load parent page D: {}", e))?
+            .is_none()
+        {
+            return Err(format!("page not found D: {}", parent_id));
+        }
+        let siblings = db
+            .get_page_blocks(parent_id)
+            .map_err(|e| format!("failed to load parent page D: {}", e))?;
+        let order = siblings
+            .iter()
+            .filter(|b| b.parent_id.is_none())
+            .map(|b| b.order + 1)
+            .max()
+            .unwrap_or(0);
+        plan.attach_to(parent_id, order);
+    }
+    plan.link_subpages();
+    Ok(plan)
+}
+
+fn register(
+    plan: &mut ImportPlan,
+    source: Source,
+    parent_id: Option<String>,
+    layout: &dyn Layout,
+    files: &mut Vec<(String, PathBuf)>,
+) {
+    let mut page = Page::new(source.title);
+    if let Some(parent_id) = parent_id {
+        page = page.with_parent(parent_id);
+    }
+    let page_id = plan.add_page(page, source.file.as_deref());
+    if let Some(file) = source.file {
+        for alias in layout.aliases(&file) {
+            plan.aliases
+                .entry(alias_key(&alias))
+                .or_insert(page_id.clone());
+        }
+        files.push((page_id.clone(), file));
+    }
+    for child in source.children {
+        register(plan, child, Some(page_id.clone()), layout, files);
+    }
+}
+
+// the title, icon and cover a file starts with
+fn apply_header(
+    plan: &mut ImportPlan,
+    layout: &dyn Layout,
+    page_id: &str,
+    file: &Path,
+    doc: &ParsedPage,
+    assets_dir: &Path,
+) {
+    let mut image = |url: &str| {
+        let path = layout.find_file(file, url)?;
+        plan.copy_asset(&path, assets_dir)
+    };
+    let cover = doc.cover.as_deref().and_then(&mut image);
+    let icon = doc
+        .icon
+        .as_deref()
+        .and_then(|icon| image(icon).or_else(|| is_emoji(icon).then(|| icon.to_string())));
+
+    let Some(page) = plan.page_mut(page_id) else {
+        return;
+    };
+    page.cover = cover;
+    page.icon = icon;
+    if let Some(title) = &doc.title {
+        // export puts emoji icons in front of the title
+        match title.split_once(' ') {
+            Some((icon, rest))
+                if page.icon.is_none() && is_emoji(icon) && !rest.trim().is_empty() =>
+            {
+                page.icon = Some(icon.to_string());
+                page.title = rest.trim().to_string();
+            }
+            _ => page.title = title.clone(),
+        }
+    }
+}
+
+// there is no image block yet, so body images go to page_assets and the text points at them
+fn copy_images(
+    plan: &mut ImportPlan,
+    layout: &dyn Layout,
+    file: &Path,
+    doc: &mut ParsedPage,
+    assets_dir: &Path,
+) {
+    let mut renamed = Vec::new();
+    for url in &doc.images {
+        let Some(path) = layout.find_file(file, url) else {
+            plan.warn(format!("image not found D: {} in {:?}", url, file));
+            continue;
+        };
+        if let Some(name) = plan.copy_asset(&path, assets_dir) {
+            renamed.push((format!("]({})", url), format!("]({})", name)));
+        }
+    }
+    if !renamed.is_empty() {
+        rename_links(&mut doc.blocks, &renamed);
+        plan.warn(format!(
+            "{}: {} images were copied to page_assets but show up as text, there is no image block",
+            file.file_name().unwrap_or_default().to_string_lossy(),
+            renamed.len()
+        ));
+    }
+}
+
+fn rename_links(blocks: &mut [ParsedBlock], renamed: &[(String, String)]) {
+    for block in blocks {
+        for (from, to) in renamed {
+            if block.content.contains(from.as_str()) {
+                block.content = block.content.replace(from.as_str(), to);
+                // the image text is one span, so the formatting still lines up afterwards
+                for span in block.spans.iter_mut().flatten() {
+                    span.text = span.text.replace(from.as_str(), to);
+                }
+            }
+        }
+        rename_links(&mut block.children, renamed);
+    }
+}
+
+fn is_emoji(text: &str) -> bool {
+    text.graphemes(true).count() == 1
+        && text
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_ascii() && !c.is_alphanumeric())
+}
+
+#[derive(Default)]
+pub struct ImportPlan {
+    pages: Vec<Page>,
+    blocks: Vec<Block>,
+    files: HashMap<PathBuf, String>,  // source file -> page id
+    aliases: HashMap<String, String>, // alias_key of a Layout::aliases name -> page id
+    stubs: usize,
+    linked: HashSet<String>, // pages that already got a SubPage block
+    assets: Vec<String>,
+    warnings: Vec<String>,
+}
+
+impl ImportPlan {
+    // pages have to be added parents first, the first one is the root of the import
+    pub fn add_page(&mut self, page: Page, file: Option<&Path>) -> String {
+        if let Some(file) = file {
+            self.files.insert(normalize(file), page.id.clone());
+        }
+        let id = page.id.clone();
+        self.pages.push(page);
+        id
+    }
+
+    pub fn page_mut(&mut self, page_id: &str) -> Option<&mut Page> {
+        self.pages.iter_mut().find(|p| p.id == page_id)
+    }
+
+    pub fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    // turns the parsed blocks of `file` into rows of `page_id`, links are resolved against `file`
+    pub fn add_blocks(&mut self, page_id: &str, file: &Path, parsed: Vec<ParsedBlock>) {
+        let page_uuid = match Uuid::parse_str(page_id) {
+            Ok(id) => id,
+            Err(_) => return,
+        };
+        self.add_tree(page_id, page_uuid, file, parsed, None);
+    }
+
+    fn add_tree(
+        &mut self,
+        page_id: &str,
+        page_uuid: Uuid,
+        file: &Path,
+        parsed: Vec<ParsedBlock>,
+        parent: Option<Uuid>,
+    ) {
+        for (order, node) in parsed.into_iter().enumerate() {
+            let resolved = node.link.as_deref().and_then(|url| {
+                self.resolve(file, url)
+                    .or_else(|| node.wikilink.then(|| self.stub(url)).flatten())
+            });
+            let target = resolved
+                .and_then(|id| self.pages.iter().find(|p| p.id == id))
+                .map(|p| (p.id.clone(), p.title.clone(), p.parent_id.clone()));
+
+            let (block_type, content) = match target {
+                Some((id, title, target_parent)) => {
+                    let target_uuid = Uuid::parse_str(&id).unwrap_or_default();
+                    // the first link to a child page is where that child lives in the parent
+                    let block_type =
+                        if target_parent.as_deref() == Some(page_id) && self.linked.insert(id) {
+                            BlockType::SubPage {
+                                page_id: target_uuid,
+                            }
+                        } else {
+                            BlockType::PageLink {
+                                page_id: target_uuid,
+                            }
+                        };
+                    let content = if node.content.trim().is_empty() {
+                        title
+                    } else {
+                        node.content
+                    };
+                    (block_type, content)
+                }
+                None => (node.block_type, node.content),
+            };
+
+            let mut block = Block::new(page_uuid, block_type, content).with_order(order as i32);
+            // formatting only survives if the parser didnt rewrite the text afterwards
+            block.spans = node
+                .spans
+                .filter(|s| rich_text::plain_text(s) == block.content);
+            if let Some(parent) = parent {
+                block = block.with_parent(parent);
+            }
+            let id = block.id;
+            self.blocks.push(block);
+            self.add_tree(page_id, page_uuid, file, node.children, Some(id));
+        }
+    }
+
+    // the page of the imported file a relative link points at, if there is one
+    fn resolve(&self, from_file: &Path, url: &str) -> Option<String> {
+        let by_path = link_target(from_file, url).and_then(|target| {
+            let with_md = PathBuf::from(format!("{}.md", target.to_string_lossy()));
+            self.files.get(&target).or_else(|| self.files.get(&with_md))
+        });
+        by_path
+            .or_else(|| self.aliases.get(&alias_key(url)))
+            .cloned()
+    }
+
+    // an empty page for a [[link]] to a note that isnt there, under the root so it can be found
+    fn stub(&mut self, target: &str) -> Option<String> {
+        let name = alias_key(target);
+        if name.is_empty() {
+            return None;
+        }
+        let title = target
+            .split('#')
+            .next()
+            .unwrap_or(target)
+            .trim()
+            .to_string();
+        let mut page = Page::new(title);
+        page.parent_id = self.pages.first().map(|root| root.id.clone());
+        let id = self.add_page(page, None);
+        self.aliases.insert(name, id.clone());
+        self.stubs += 1;
+        Some(id)
+    }
+
+    // hangs the root of the import under an existing page, `order` is after its last block
+    pub fn attach_to(&mut self, parent_id: &str, order: i32) {
+        let Some(root) = self.pages.first_mut() else {
+            return;
+        };
+        root.parent_id = Some(parent_id.to_string());
+        let (Ok(page_uuid), Ok(parent_uuid)) =
+            (Uuid::parse_str(&root.id), Uuid::parse_str(parent_id))
+        else {
+            return;
+        };
+        let block = Block::new(
+            parent_uuid,
+            BlockType::SubPage { page_id: page_uuid },
+            root.title.clone(),
+        )
+        .with_order(order);
+        self.linked.insert(root.id.clone());
+        self.blocks.push(block);
+    }
+
+    // child pages nobody linked to get a SubPage block at the end of their parent
+    pub fn link_subpages(&mut self) {
+        let unlinked: Vec<(String, String, String)> = self
+            .pages
+            .iter()
+            .skip(1)
+            .filter(|p| !self.linked.contains(&p.id))
+            .filter_map(|p| Some((p.id.clone(), p.title.clone(), p.parent_id.clone()?)))
+            .collect();
+
+        for (id, title, parent_id) in unlinked {
+            let (Ok(page_uuid), Ok(parent_uuid)) =
+                (Uuid::parse_str(&id), Uuid::parse_str(&parent_id))
+            else {
+                continue;
+            };
+            let order = self
+                .blocks
+                .iter()
+                .filter(|b| b.page_id == parent_uuid && b.parent_id.is_none())
+                .count();
+            self.blocks.push(
+                Block::new(
+                    parent_uuid,
+                    BlockType::SubPage { page_id: page_uuid },
+                    title,
+                )
+                .with_order(order as i32),
+            );
+            self.linked.insert(id);
+        }
+    }
+
+    // copies an image next to the workspace assets, returns the name to store on the page
+    pub fn copy_asset(&mut self, source: &Path, assets_dir: &Path) -> Option<String> {
+        let filename = source.file_name()?.to_string_lossy().to_string();
+        let result = fs::create_dir_all(assets_dir).and_then(|_| {
+            let mut name = filename.clone();
+            let existing = assets_dir.join(&name);
+            if existing.exists() && fs::read(&existing)? != fs::read(source)? {
+                // same name but a different picture, dont clobber the one already in use
+                name = format!("{}-{}", &Uuid::new_v4().simple().to_string()[..8], filename);
+            }
+            if !assets_dir.join(&name).exists() {
+                fs::copy(source, assets_dir.join(&name))?;
+            }
+            Ok(name)
+        });
+
+        match result {
+            Ok(name) => {
+                self.assets.push(name.clone());
+                Some(name)
+            }
+            Err(e) => {
+                self.warn(format!("failed to copy {:?} D: {}", source, e));
+                None
+            }
+        }
+    }
+
+    // every page and block as undoable creations, parents first
+    pub fn changes(&self) -> Vec<Change> {
+        let pages = self.pages.iter().map(|p| Change::Page {
+            before: None,
+            after: Some(p.clone()),
+        });
+        let blocks = self.blocks.iter().map(|b| Change::Block {
+            before: None,
+            after: Some(b.clone()),
+        });
+        pages.chain(blocks).collect()
+    }
+
+    pub fn summary(&self) -> Option<ImportSummary> {
+        let mut warnings = self.warnings.clone();
+        if self.stubs > 0 {
+            warnings.push(format!(
+                "{} links pointed at notes that werent imported, they got empty pages",
+                self.stubs
+            ));
+        }
+        Some(ImportSummary {
+            root_page: self.pages.first()?.clone(),
+            page_count: self.pages.len(),
+            block_count: self.blocks.len(),
+            assets: self.assets.clone(),
+            warnings,
+        })
+    }
+}
+
+// where a relative link in `from_file` points on disk, None for web links and anchors
+pub fn link_target(from_file: &Path, url: &str) -> Option<PathBuf> {
+    let url = url.trim();
+    if is_web_link(url) || url.starts_with('/') {
+        return None;
+    }
+
+    let path = url.split(['#', '?']).next().unwrap_or_default();
+    let path = urlencoding::decode(path).ok()?;
+    if path.is_empty() {
+        return None;
+    }
+    Some(normalize(&from_file.parent()?.join(path.as_ref())))
+}
+
+// "Trip Ideas.md#packing" and "trip ideas" both become "trip ideas"
+pub fn alias_key(name: &str) -> String {
+    let name = name.split('#').next().unwrap_or_default().trim();
+    let name = urlencoding::decode(name)
+        .map(|n| n.into_owned())
+        .unwrap_or_else(|_| name.to_string());
+    let name = name.strip_suffix(".md").unwrap_or(&name);
+    name.trim().to_lowercase()
+}
+
+// https:, mailto: and #anchors, anything that isnt a file next to the one being imported
+pub fn is_web_link(url: &str) -> bool {
+    let has_scheme = url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphabetic())
+    });
+    has_scheme || url.starts_with('#')
+}
+
+// "a/b/../c.md" -> "a/c.md" without touching the disk, so links match the paths we walked
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Mark;
+
+    #[test]
+    fn resolves_relative_links() {
+        let from = Path::new("/notes/Plans.md");
+        assert_eq!(
+            link_target(from, "Plans/Trip%20Ideas.md#packing"),
+            Some(PathBuf::from("/notes/Plans/Trip Ideas.md"))
+        );
+        assert_eq!(
+            link_target(from, "../other/./Todo.md"),
+            Some(PathBuf::from("/other/Todo.md"))
+        );
+        assert_eq!(link_target(from, "https://example.com/a.md"), None);
+        assert_eq!(link_target(from, "mailto:me@example.com"), None);
+        assert_eq!(link_target(from, "#heading"), None);
+    }
+
+    #[test]
+    fn unlinked_children_get_subpage_blocks() {
+        let mut plan = ImportPlan::default();
+        let root = plan.add_page(
+            Page::new("Notes".to_string()),
+            Some(Path::new("/n/Notes.md")),
+        );
+        let linked = plan.add_page(
+            Page::new("Linked".to_string()).with_parent(root.clone()),
+            Some(Path::new("/n/Notes/Linked.md")),
+        );
+        let loose = plan.add_page(
+            Page::new("Loose".to_string()).with_parent(root.clone()),
+            None,
+        );
+
+        plan.add_blocks(
+            &root,
+            Path::new("/n/Notes.md"),
+            vec![
+                ParsedBlock::new(BlockType::Text, "hello".to_string()),
+                ParsedBlock::link(String::new(), "Notes/Linked.md".to_string()),
+                ParsedBlock::link("again".to_string(), "Notes/Linked".to_string()),
+            ],
+        );
+        plan.link_subpages();
+
+        let types: Vec<(&BlockType, &str, i32)> = plan
+            .blocks
+            .iter()
+            .map(|b| (&b.block_type, b.content.as_str(), b.order))
+            .collect();
+        let linked_id = Uuid::parse_str(&linked).unwrap();
+        let loose_id = Uuid::parse_str(&loose).unwrap();
+        assert_eq!(
+            types,
+            vec![
+                (&BlockType::Text, "hello", 0),
+                (&BlockType::SubPage { page_id: linked_id }, "Linked", 1),
+                (&BlockType::PageLink { page_id: linked_id }, "again", 2),
+                (&BlockType::SubPage { page_id: loose_id }, "Loose", 3),
+            ]
+        );
+        assert_eq!(plan.changes().len(), 3 + 4);
+    }
+
+    #[test]
+    fn renamed_images_keep_the_formatting() {
+        let bold = TextSpan {
+            marks: vec![Mark::Bold],
+            ..TextSpan::plain("look ")
+        };
+        let mut blocks = vec![ParsedBlock {
+            spans: Some(vec![bold.clone(), TextSpan::plain("beach.png")]),
+            ..ParsedBlock::new(BlockType::Text, "look beach.png".to_string())
+        }];
+        rename_links(
+            &mut blocks,
+            &[("beach.png".to_string(), "beach-2.png".to_string())],
+        );
+        assert_eq!(blocks[0].content, "look beach-2.png");
+        assert_eq!(
+            blocks[0].spans,
+            Some(vec![bold, TextSpan::plain("beach-2.png")])
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use super::{
+    html, import_tree, is_web_link, markdown, ImportPlan, Layout, ParsedBlock, ParsedPage, Source,
+};
+use crate::storage::Database;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+/*
+ * notion's workspace export, either flavour: "Markdown & CSV" or "HTML". every page is
+ * "Title <32 hex id>.md" (or .html) with a folder of the same name for its subpages and images,
+ * databases are a .csv with their rows as pages in the folder. the zip sometimes holds more zips
+ */
+const NESTED_ZIP_DEPTH: usize = 3;
+
+// `source` is the zip, or the folder it was already unpacked into
+pub fn import_export(
+    db: &Database,
+    assets_dir: &Path,
+    source: &Path,
+    parent_id: Option<&str>,
+) -> Result<ImportPlan, String> {
+    if source.is_dir() {
+        return import_folder(db, assets_dir, source, parent_id);
+    }
+
+    let scratch = std::env::temp_dir().join(format!("bismuth-notion-{}", Uuid::new_v4()));
+    let result =
+        unzip(source, &scratch).and_then(|_| import_folder(db, assets_dir, &scratch, parent_id));
+    if let Err(e) = fs::remove_dir_all(&scratch) {
+        eprintln!("Failed to clean up {:?}: {}", scratch, e);
+    }
+    result
+}
+
+fn import_folder(
+    db: &Database,
+    assets_dir: &Path,
+    folder: &Path,
+    parent_id: Option<&str>,
+) -> Result<ImportPlan, String> {
+    // "Export-<uuid>/" around everything
+    let mut folder = folder.to_path_buf();
+    while let Some(inner) = only_subfolder(&folder) {
+        folder = inner;
+    }
+
+    let mut pages = Source::collect(&folder, &NotionExport);
+    let root = match pages.len() {
+        0 => return Err(format!("no notion pages found in {:?}", folder)),
+        1 => pages.remove(0),
+        _ => Source {
+            title: "Notion import".to_string(),
+            file: None,
+            children: pages,
+        },
+    };
+    import_tree(db, assets_dir, root, parent_id, &NotionExport)
+}
+
+fn unzip(zip_path: &Path, into: &Path) -> Result<(), String> {
+    extract(zip_path, into)?;
+    for _ in 0..NESTED_ZIP_DEPTH {
+        let nested = find_zips(into);
+        if nested.is_empty() {
+            break;
+        }
+        for zip in nested {
+            let parent = zip.parent().unwrap_or(into).to_path_buf();
+            extract(&zip, &parent)?;
+            fs::remove_file(&zip).map_err(|e| format!("failed to remove {:?} D: {}", zip, e))?;
+        }
+    }
+    Ok(())
+}
+
+fn extract(zip_path: &Path, into: &Path) -> Result<(), String> {
+    let file =
+        fs::File::open(zip_path).map_err(|e| format!("failed to open {:?} D: {}", zip_path, e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("not a zip file {:?} D: {}", zip_path, e))?;
+    // extract() skips entries that would land outside `into`
+    archive
+        .extract(into)
+        .map_err(|e| format!("failed to unzip {:?} D: {}", zip_path, e))
+}
+
+fn find_zips(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut zips = Vec::new();
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            zips.extend(find_zips(&path));
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        {
+            zips.push(path);
+        }
+    }
+    zips
+}
+
+fn only_subfolder(dir: &Path) -> Option<PathBuf> {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            !p.file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        })
+        .collect();
+    match entries.as_slice() {
+        [only] if only.is_dir() => Some(only.clone()),
+        _ => None,
+    }
+}
+
+struct NotionExport;
+
+impl Layout for NotionExport {
+    fn is_page(&self, path: &Path) -> bool {
+        let Some(ext) = path.extension().map(|e| e.to_string_lossy().to_lowercase()) else {
+            return false;
+        };
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        match ext.as_str() {
+            "md" => true,
+            // the root index.html only lists the top level pages
+            "html" => stem != "index",
+            // newer exports write "Db <id>_all.csv" next to "Db <id>.csv"
+            "csv" => match stem.strip_suffix("_all") {
+                Some(base) => !path.with_file_name(format!("{}.csv", base)).exists(),
+                None => true,
+            },
+            _ => false,
+        }
+    }
+
+    fn parse(&self, file: &Path) -> Result<ParsedPage, String> {
+        let ext = file
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if ext == "csv" {
+            return Ok(ParsedPage {
+                warnings: vec![
+                    "database columns and views were not imported, its rows came in as subpages"
+                        .to_string(),
+                ],
+                ..ParsedPage::default()
+            });
+        }
+
+        let text =
+            fs::read_to_string(file).map_err(|e| format!("failed to read {:?} D: {}", file, e))?;
+        let mut doc = if ext == "html" {
+            html::parse(&text, is_page_link)
+        } else {
+            markdown::parse_with(&text, is_page_link, false)
+        };
+        notion_links_to_ids(&mut doc.blocks);
+        Ok(doc)
+    }
+
+    fn title(&self, path: &Path) -> String {
+        let name = if self.is_page(path) {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        let name = name.unwrap_or_default().to_string_lossy();
+        let name = name.strip_suffix("_all").unwrap_or(&name);
+        let title = id_suffix().replace(name, "").trim().to_string();
+        if title.is_empty() {
+            "Untitled".to_string()
+        } else {
+            title
+        }
+    }
+
+    fn folder(&self, file: &Path) -> PathBuf {
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        file.with_file_name(stem.strip_suffix("_all").unwrap_or(&stem))
+    }
+
+    // links to notion.so/Page-<id> can point at any page of the export
+    fn aliases(&self, file: &Path) -> Vec<String> {
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        let stem = stem.strip_suffix("_all").unwrap_or(&stem);
+        id_suffix()
+            .find(stem)
+            .map(|m| m.as_str().trim().to_string())
+            .into_iter()
+            .collect()
+    }
+}
+
+fn id_suffix() -> &'static Regex {
+    static ID: OnceLock<Regex> = OnceLock::new();
+    ID.get_or_init(|| Regex::new(r"\s[0-9a-f]{32}$").unwrap())
+}
+
+fn is_page_link(url: &str) -> bool {
+    !is_web_link(url) || notion_id(url).is_some()
+}
+
+// "https://www.notion.so/Trip-0123...cdef?pvs=21" -> "0123...cdef"
+fn notion_id(url: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let (host, path) = rest.split_once('/')?;
+    if !(host.ends_with("notion.so") || host.ends_with("notion.site")) {
+        return None;
+    }
+    let last = path.split(['?', '#']).next()?.rsplit('/').next()?;
+    let hex: String = last.chars().filter(|c| *c != '-').collect();
+    let id = hex.get(hex.len().checked_sub(32)?..)?;
+    id.chars()
+        .all(|c| c.is_ascii_hexdigit())
+        .then(|| id.to_lowercase())
+}
+
+fn notion_links_to_ids(blocks: &mut [ParsedBlock]) {
+    for block in blocks {
+        if let Some(id) = block.link.as_deref().and_then(notion_id) {
+            block.link = Some(id);
+        }
+        notion_links_to_ids(&mut block.children);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BlockType;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const ID_A: &str = "0123456789abcdef0123456789abcdef";
+    const ID_B: &str = "fedcba9876543210fedcba9876543210";
+
+    #[test]
+    fn titles_and_links() {
+        let layout = NotionExport;
+        let file = PathBuf::from(format!("/x/Trip Ideas {}.md", ID_A));
+        assert_eq!(layout.title(&file), "Trip Ideas");
+        assert_eq!(layout.aliases(&file), vec![ID_A.to_string()]);
+        assert_eq!(
+            notion_id(&format!(
+                "https://www.notion.so/team/Trip-Ideas-{}?pvs=21",
+                ID_A
+            ))
+            .as_deref(),
+            Some(ID_A)
+        );
+        assert_eq!(
+            notion_id("https://www.notion.so/0123e456-7890-abcd-ef01-23456789abcd").as_deref(),
+            Some("0123e4567890abcdef0123456789abcd")
+        );
+        assert_eq!(
+            notion_id("https://example.com/0123456789abcdef0123456789abcdef"),
+            None
+        );
+    }
+
+    #[test]
+    fn imports_a_markdown_export_zip() {
+        let db = Database::new(":memory:").unwrap();
+        let dir = std::env::temp_dir().join(format!("bismuth-notion-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let zip_path = dir.join("export.zip");
+        let assets = dir.join("assets");
+
+        let mut zip = zip::ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        let files = [
+            (
+                format!("Plans {}.md", ID_A),
+                format!(
+                    "# Plans\n\n- [x] book\n\n[Trip](Plans%20{}/Trip%20{}.md)\n\nsee [budget](https://www.notion.so/Budget-{})\n\n![map](Plans%20{}/map.png)\n",
+                    ID_A, ID_B, ID_B, ID_A
+                ),
+            ),
+            (format!("Plans {}/Trip {}.md", ID_A, ID_B), "# Trip\n\nbeach\n".to_string()),
+            (format!("Plans {}/map.png", ID_A), "png".to_string()),
+        ];
+        for (name, content) in files {
+            zip.start_file(name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let plan = import_export(&db, &assets, &zip_path, None).unwrap();
+        let summary = plan.summary().unwrap();
+        assert_eq!(summary.root_page.title, "Plans");
+        assert_eq!(summary.page_count, 2);
+        assert_eq!(summary.assets, vec!["map.png".to_string()]);
+        assert!(assets.join("map.png").is_file());
+
+        db.apply_changes(&plan.changes(), false).unwrap();
+        let trip = db.get_child_pages(&summary.root_page.id).unwrap();
+        let trip_id = Uuid::parse_str(&trip[0].id).unwrap();
+        let blocks = db.get_page_blocks(&summary.root_page.id).unwrap();
+        let types: Vec<(&BlockType, &str)> = blocks
+            .iter()
+            .map(|b| (&b.block_type, b.content.as_str()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (&BlockType::Todo { checked: true }, "book"),
+                (&BlockType::SubPage { page_id: trip_id }, "Trip"),
+                (&BlockType::Text, "see budget"),
+                (&BlockType::PageLink { page_id: trip_id }, "budget"),
+                (&BlockType::Text, "![map](map.png)"),
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
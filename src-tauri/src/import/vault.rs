@@ -0,0 +1,362 @@
+use super::markdown::{is_markdown, parse_with};
+use super::{
+    import_tree, is_web_link, link_target, ImportPlan, Layout, ParsedBlock, ParsedPage, Source,
+};
+use crate::models::BlockType;
+use crate::storage::Database;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/*
+ * obsidian and logseq vaults. obsidian is folders of notes named after their file, links go
+ * by note name ([[Note]], [[Folder/Note|text]]) and images sit anywhere in the vault.
+ * logseq keeps pages/ and journals/ flat and every line is an outline bullet, so bullets turn
+ * into plain nested blocks instead of a list
+ */
+pub fn import_vault(
+    db: &Database,
+    assets_dir: &Path,
+    vault: &Path,
+    parent_id: Option<&str>,
+) -> Result<ImportPlan, String> {
+    if !vault.is_dir() {
+        return Err(format!("not a folder D: {:?}", vault));
+    }
+    let layout = Vault::open(vault);
+
+    let root = if layout.logseq {
+        let mut children = Source::collect(&vault.join("pages"), &layout);
+        let journals = Source::collect(&vault.join("journals"), &layout);
+        if !journals.is_empty() {
+            children.push(Source {
+                title: "Journals".to_string(),
+                file: None,
+                children: journals,
+            });
+        }
+        Source {
+            title: layout.title(vault),
+            file: None,
+            children,
+        }
+    } else {
+        Source::from_path(vault, &layout).unwrap_or(Source {
+            title: layout.title(vault),
+            file: None,
+            children: Vec::new(),
+        })
+    };
+    if root.children.is_empty() {
+        return Err(format!("no notes found in {:?}", vault));
+    }
+
+    import_tree(db, assets_dir, root, parent_id, &layout)
+}
+
+struct Vault {
+    root: PathBuf,
+    logseq: bool,
+    files: HashMap<String, PathBuf>, // lowercase file name -> path, for ![[image.png]]
+}
+
+impl Vault {
+    fn open(root: &Path) -> Self {
+        let mut files = HashMap::new();
+        index_files(root, &mut files);
+        Vault {
+            root: root.to_path_buf(),
+            logseq: root.join("logseq").join("config.edn").is_file(),
+            files,
+        }
+    }
+}
+
+fn index_files(dir: &Path, files: &mut HashMap<String, PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            index_files(&path, files);
+        } else if !is_markdown(&path) {
+            files.entry(name.to_lowercase()).or_insert(path);
+        }
+    }
+}
+
+impl Layout for Vault {
+    fn is_page(&self, path: &Path) -> bool {
+        is_markdown(path)
+    }
+
+    fn parse(&self, file: &Path) -> Result<ParsedPage, String> {
+        let text =
+            fs::read_to_string(file).map_err(|e| format!("failed to read {:?} D: {}", file, e))?;
+        let mut doc = parse_with(&text, |url| !is_web_link(url), true);
+
+        if self.logseq {
+            page_properties(&mut doc);
+            outline(&mut doc.blocks);
+        } else if let Some(heading) = doc.title.take() {
+            // obsidian notes are named by their file, a leading # heading is just content
+            let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+            if !heading.eq_ignore_ascii_case(&stem) {
+                doc.blocks
+                    .insert(0, ParsedBlock::new(BlockType::Heading1, heading));
+            }
+        }
+        Ok(doc)
+    }
+
+    fn title(&self, path: &Path) -> String {
+        let name = if self.is_page(path) {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        let name = name.unwrap_or_default().to_string_lossy().to_string();
+        if self.logseq && self.is_page(path) {
+            logseq_title(&name)
+        } else {
+            name
+        }
+    }
+
+    // [[Note]] and [[Folder/Note]] in obsidian, [[namespace/page]] in logseq
+    fn aliases(&self, file: &Path) -> Vec<String> {
+        let mut aliases = vec![self.title(file)];
+        if let Ok(relative) = file.with_extension("").strip_prefix(&self.root) {
+            let relative: Vec<String> = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            aliases.push(relative.join("/"));
+        }
+        aliases
+    }
+
+    // next to the note, from the vault root, or anywhere in the vault by name
+    fn find_file(&self, from_file: &Path, url: &str) -> Option<PathBuf> {
+        link_target(from_file, url)
+            .filter(|p| p.is_file())
+            .or_else(|| {
+                let decoded = urlencoding::decode(url).ok()?;
+                Some(self.root.join(decoded.as_ref())).filter(|p| p.is_file())
+            })
+            .or_else(|| {
+                let name = url.rsplit('/').next()?;
+                let name = urlencoding::decode(name).ok()?;
+                self.files.get(&name.to_lowercase()).cloned()
+            })
+    }
+}
+
+// "2024_01_15" -> "2024-01-15", "projects___bismuth" / "projects%2Fbismuth" -> "projects/bismuth"
+fn logseq_title(stem: &str) -> String {
+    static JOURNAL: OnceLock<Regex> = OnceLock::new();
+    let journal = JOURNAL.get_or_init(|| Regex::new(r"^\d{4}_\d{2}_\d{2}$").unwrap());
+    if journal.is_match(stem) {
+        return stem.replace('_', "-");
+    }
+    let name = stem.replace("___", "/");
+    urlencoding::decode(&name)
+        .map(|n| n.into_owned())
+        .unwrap_or(name)
+}
+
+fn property_line(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.trim().split_once(":: ")?;
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    is_key.then_some((key, value.trim()))
+}
+
+// "title:: Real name" lines at the very top of a logseq page
+fn page_properties(doc: &mut ParsedPage) {
+    let Some(first) = doc.blocks.first() else {
+        return;
+    };
+    let all_properties = first.block_type == BlockType::Text
+        && first.content.lines().all(|l| property_line(l).is_some());
+    if !all_properties {
+        return;
+    }
+    let first = doc.blocks.remove(0);
+    for (key, value) in first.content.lines().filter_map(property_line) {
+        if key == "title" && !value.is_empty() {
+            doc.title = Some(value.to_string());
+        }
+    }
+}
+
+// logseq bullets are the blocks themselves, not a list
+fn outline(blocks: &mut [ParsedBlock]) {
+    for block in blocks {
+        if block.block_type == BlockType::BulletList {
+            block.block_type = BlockType::Text;
+            // "- ## Heading" and "- ```code" nest the real block under an empty bullet
+            let nested_first = block.content.is_empty()
+                && block
+                    .children
+                    .first()
+                    .is_some_and(|c| c.block_type != BlockType::BulletList);
+            if nested_first {
+                let first = block.children.remove(0);
+                let rest = std::mem::take(&mut block.children);
+                block.block_type = first.block_type;
+                block.content = first.content;
+                block.link = first.link;
+                block.wikilink = first.wikilink;
+                block.children = first.children.into_iter().chain(rest).collect();
+            }
+        }
+
+        // id:: and collapsed:: belong to logseq, not the text
+        block.content = block
+            .content
+            .lines()
+            .filter(|l| property_line(l).is_none())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if block.block_type == BlockType::Text {
+            task_marker(block);
+        }
+        outline(&mut block.children);
+    }
+}
+
+fn task_marker(block: &mut ParsedBlock) {
+    let Some((marker, rest)) = block.content.split_once(' ') else {
+        return;
+    };
+    let checked = match marker {
+        "TODO" | "LATER" | "NOW" | "DOING" | "WAITING" | "IN-PROGRESS" => false,
+        // cancelled tasks are closed too
+        "DONE" | "CANCELED" | "CANCELLED" => true,
+        _ => return,
+    };
+    block.block_type = BlockType::Todo { checked };
+    block.content = rest.trim_start().to_string();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn text(block_type: BlockType, content: &str) -> ParsedBlock {
+        ParsedBlock::new(block_type, content.to_string())
+    }
+
+    #[test]
+    fn logseq_outline_becomes_nested_blocks() {
+        let mut doc = parse_with(
+            "title:: Real Name\nalias:: other\n\n- first\n  id:: 6500-aa\n\t- child [[Other Page]]\n- ## Section\n\t- DONE shipped\n- TODO write docs\n",
+            |url| !is_web_link(url),
+            true,
+        );
+        page_properties(&mut doc);
+        outline(&mut doc.blocks);
+
+        assert_eq!(doc.title.as_deref(), Some("Real Name"));
+        let mut child = text(BlockType::Text, "child Other Page");
+        child.children = vec![ParsedBlock::wikilink(
+            "Other Page".to_string(),
+            "Other Page".to_string(),
+        )];
+        let mut first = text(BlockType::Text, "first");
+        first.children = vec![child];
+        let mut section = text(BlockType::Heading2, "Section");
+        section.children = vec![text(BlockType::Todo { checked: true }, "shipped")];
+        assert_eq!(
+            doc.blocks,
+            vec![
+                first,
+                section,
+                text(BlockType::Todo { checked: false }, "write docs")
+            ]
+        );
+
+        assert_eq!(logseq_title("2024_01_15"), "2024-01-15");
+        assert_eq!(logseq_title("projects___bismuth"), "projects/bismuth");
+        assert_eq!(logseq_title("a%2Fb"), "a/b");
+    }
+
+    #[test]
+    fn obsidian_vault_with_wikilinks() {
+        let db = Database::new(":memory:").unwrap();
+        let dir = std::env::temp_dir().join(format!("bismuth-vault-{}", Uuid::new_v4()));
+        let vault = dir.join("Vault");
+        fs::create_dir_all(vault.join("Projects")).unwrap();
+        fs::create_dir_all(vault.join("attachments")).unwrap();
+        fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        fs::write(
+            vault.join("Index.md"),
+            "# Index\n\nsee [[Projects/Plan|the plan]]\n\n[[Missing note]]\n\n![[pic.png]]\n",
+        )
+        .unwrap();
+        fs::write(
+            vault.join("Projects").join("Plan.md"),
+            "back to [[index]]\n",
+        )
+        .unwrap();
+        fs::write(vault.join("attachments").join("pic.png"), "png").unwrap();
+        fs::write(vault.join(".obsidian").join("app.md"), "not a note").unwrap();
+
+        let assets = dir.join("assets");
+        let plan = import_vault(&db, &assets, &vault, None).unwrap();
+        let summary = plan.summary().unwrap();
+        // vault, index, projects, plan and a stub for the missing note
+        assert_eq!(summary.root_page.title, "Vault");
+        assert_eq!(summary.page_count, 5);
+        assert_eq!(summary.assets, vec!["pic.png".to_string()]);
+
+        db.apply_changes(&plan.changes(), false).unwrap();
+        let pages = db.list_pages().unwrap();
+        let id_of = |title: &str| {
+            let page = pages.iter().find(|p| p.title == title).unwrap();
+            Uuid::parse_str(&page.id).unwrap()
+        };
+        let blocks = db.get_page_blocks(&id_of("Index").to_string()).unwrap();
+        let types: Vec<(&BlockType, &str)> = blocks
+            .iter()
+            .map(|b| (&b.block_type, b.content.as_str()))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                (&BlockType::Text, "see the plan"),
+                (
+                    &BlockType::PageLink {
+                        page_id: id_of("Plan")
+                    },
+                    "the plan"
+                ),
+                (
+                    &BlockType::PageLink {
+                        page_id: id_of("Missing note")
+                    },
+                    "Missing note"
+                ),
+                (&BlockType::Text, "![pic.png](pic.png)"),
+            ]
+        );
+        let plan_blocks = db.get_page_blocks(&id_of("Plan").to_string()).unwrap();
+        assert_eq!(
+            plan_blocks[1].block_type,
+            BlockType::PageLink {
+                page_id: id_of("Index")
+            }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,10 +1,13 @@
 mod asset_protocol;
 mod commands;
+mod export;
+mod import;
 mod models;
 mod storage;
 
 use commands::*;
-use storage::Database;
+use storage::{Database, UndoManager, WorkspaceManager};
+use tauri::Manager;
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 #[tauri::command]
@@ -14,9 +17,6 @@ fn greet(name: &str) -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    // initialize database
-    let db = Database::new("bismuth.db").expect("failed to initialize database D:");
-
     let builder = tauri::Builder::default();
 
     // register the asset protocol on the builder (die yeah die)
@@ -24,6 +24,19 @@ pub fn run() {
 
     builder
         .setup(|app| {
+            // initialize database inside the current workspace in app data
+            let workspaces = WorkspaceManager::load(app.path().app_data_dir()?)?;
+            let db = Database::new(workspaces.current_db_path())?;
+            match purge_expired_trash(&db, &workspaces) {
+                Ok(0) => {}
+                Ok(purged) => eprintln!("Purged {} pages from the trash", purged),
+                Err(e) => eprintln!("Failed to purge trash: {}", e),
+            }
+            app.manage(workspaces);
+            app.manage(db);
+            app.manage(UndoManager::default());
+            start_backup_scheduler(app.handle().clone());
+
             tauri::WebviewWindowBuilder::new(
                 app,
                 "main",
@@ -35,7 +48,6 @@ pub fn run() {
             .build()?;
             Ok(())
         })
-        .manage(db)
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .invoke_handler(tauri::generate_handler![
@@ -50,14 +62,59 @@ pub fn run() {
             get_root_pages,
             create_nested_page,
             validate_page_link,
+            get_backlinks,
+            get_inbound_links,
+            find_broken_links,
+            repair_links,
+            get_page_graph,
             delete_page,
+            archive_page,
+            restore_page,
+            list_trash,
+            empty_trash,
+            get_trash_retention,
+            set_trash_retention,
             create_block,
             get_page_blocks,
+            get_page_block_tree,
             update_block_content,
+            update_block_rich_text,
             delete_block,
             reorder_block,
+            indent_block,
+            outdent_block,
+            move_block,
+            move_blocks_to_page,
+            apply_block_ops,
+            list_page_versions,
+            get_page_version,
+            create_save_point,
+            restore_page_version,
+            undo,
+            redo,
+            get_undo_state,
             upload_page_asset,
+            export_page_markdown,
+            export_site,
+            import_markdown,
+            import_notion,
+            import_vault,
             search_blocks,
+            replace_in_block,
+            replace_in_page,
+            replace_all,
+            list_workspaces,
+            get_current_workspace,
+            create_workspace,
+            open_workspace,
+            switch_workspace,
+            export_workspace,
+            import_workspace,
+            list_backups,
+            backup_now,
+            restore_backup,
+            get_backup_settings,
+            set_backup_settings,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application D:");
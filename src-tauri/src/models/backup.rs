@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// manifest.json at the root of a .bismuth archive, checked before anything gets restored
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub schema_version: u32,
+    pub app_version: String,
+    pub workspace: String,
+    #[ts(type = "string")]
+    pub created_at: DateTime<Utc>,
+    pub page_count: usize,
+    pub asset_count: usize,
+}
+
+// where export_workspace wrote the archive
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BackupSummary {
+    pub path: String,
+    #[ts(type = "number")]
+    pub size: u64,
+    pub manifest: BackupManifest,
+}
+
+// scheduled backups of the current workspace, stored per workspace in settings
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BackupSettings {
+    pub interval_hours: Option<u32>, // null turns scheduled backups off
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+}
@@ -1,3 +1,4 @@
+use super::rich_text::{self, TextSpan};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
@@ -42,6 +43,9 @@ pub struct Block {
     pub page_id: Uuid,
     pub block_type: BlockType,
     pub content: String,
+    // formatted version of content, None for plain text
+    #[serde(default)]
+    pub spans: Option<Vec<TextSpan>>,
     #[ts(type = "string | null")]
     pub parent_id: Option<Uuid>,
     pub order: i32,
@@ -60,6 +64,7 @@ impl Block {
             page_id,
             block_type,
             content,
+            spans: None,
             parent_id: None,
             order: 0,
             created_at: now,
@@ -77,6 +82,42 @@ impl Block {
         self.order = order;
         self
     }
+
+    // sets the plain text, formatting on the parts that didnt change is kept
+    pub fn with_content(mut self, content: String) -> Self {
+        self.spans = self
+            .spans
+            .take()
+            .and_then(|spans| rich_text::retext(spans, &content));
+        self.content = content;
+        self
+    }
+
+    // sets the formatted text, content follows as its plain projection
+    pub fn with_spans(mut self, spans: Vec<TextSpan>) -> Self {
+        self.content = rich_text::plain_text(&spans);
+        self.spans = rich_text::normalize(spans);
+        self
+    }
+}
+
+// a block with its children already in order, children stays empty past the depth limit or
+// for collapsed blocks, child_count says if there is more to load
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BlockNode {
+    pub block: Block,
+    pub children: Vec<BlockNode>,
+    pub child_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(default)]
+pub struct BlockTreeOptions {
+    pub root_id: Option<String>, // only the children of this block, for loading a collapsed one
+    pub depth: Option<u32>,      // levels to return, everything when not given
+    pub collapsed: Vec<String>,  // blocks whose children are left out
 }
 
 #[cfg(test)]
@@ -87,5 +128,8 @@ mod tests {
     fn export_bindings() {
         Block::export().unwrap();
         BlockType::export().unwrap();
+        BlockNode::export().unwrap();
+        BlockTreeOptions::export().unwrap();
+        TextSpan::export().unwrap();
     }
 }
@@ -0,0 +1,53 @@
+use super::{BlockType, TextSpan};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// one step of apply_block_ops. ids are strings like everywhere else in the api, an insert can pick
+// its own id so later ops in the same batch can nest blocks under it
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(tag = "type", content = "data")]
+pub enum BlockOp {
+    Insert {
+        id: Option<String>,
+        page_id: String,
+        parent_id: Option<String>,
+        index: Option<usize>, // position among its siblings, the end when not given
+        block_type: BlockType,
+        content: String,
+        #[serde(default)]
+        spans: Option<Vec<TextSpan>>, // formatted text, replaces content when given
+    },
+    Update {
+        block_id: String,
+        block_type: Option<BlockType>,
+        content: Option<String>, // plain text, formatting on the unchanged parts is kept
+        #[serde(default)]
+        spans: Option<Vec<TextSpan>>,
+    },
+    Delete {
+        block_id: String,
+    },
+    // within the same page, move_blocks_to_page is for going somewhere else
+    Move {
+        block_id: String,
+        parent_id: Option<String>,
+        index: Option<usize>,
+    },
+}
+
+// which op broke the batch, nothing from the batch is saved when this comes back
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BlockOpError {
+    pub index: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for BlockOpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "op {} failed D: {}", self.index, self.message)
+    }
+}
+
+impl std::error::Error for BlockOpError {}
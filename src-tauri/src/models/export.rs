@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// what an export wrote, paths are absolute so the frontend can reveal them
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct ExportSummary {
+    pub root_file: String,
+    pub files: Vec<String>,
+    pub assets: Vec<String>,
+}
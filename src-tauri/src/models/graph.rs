@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// center + depth gives a local graph around one page, without center it's the whole workspace
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(default)]
+pub struct GraphOptions {
+    pub center: Option<String>,
+    pub depth: Option<u32>, // hops from center, 1 when not given
+    pub include_archived: bool,
+}
+
+// link counts only count PageLink/SubPage blocks, not the parent-child hierarchy
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct GraphNode {
+    pub id: String,
+    pub title: String,
+    pub icon: Option<String>,
+    pub parent_id: Option<String>,
+    pub is_archived: bool,
+    pub inbound_links: usize,
+    pub outbound_links: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub enum EdgeKind {
+    Parent, // source is the parent page, target the child
+    PageLink,
+    SubPage,
+}
+
+// weight is how many blocks make up a link edge, always 1 for Parent
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    pub weight: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct PageGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
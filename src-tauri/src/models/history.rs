@@ -0,0 +1,54 @@
+use super::Block;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub enum VersionKind {
+    Auto,          // taken while editing, pruned over time
+    SavePoint,     // the user asked for it, never pruned
+    BeforeRestore, // what the page looked like right before an old version was restored
+}
+
+impl VersionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionKind::Auto => "Auto",
+            VersionKind::SavePoint => "SavePoint",
+            VersionKind::BeforeRestore => "BeforeRestore",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "SavePoint" => VersionKind::SavePoint,
+            "BeforeRestore" => VersionKind::BeforeRestore,
+            _ => VersionKind::Auto,
+        }
+    }
+}
+
+// what the history list shows, without the blocks
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct PageVersionInfo {
+    pub id: String,
+    pub page_id: String,
+    pub kind: VersionKind,
+    pub label: Option<String>,
+    pub title: String,
+    pub block_count: u32,
+    #[ts(type = "string")]
+    pub created_at: DateTime<Utc>,
+}
+
+// a full snapshot, read only on the frontend until it gets restored
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct PageVersion {
+    pub info: PageVersionInfo,
+    pub icon: Option<String>,
+    pub cover: Option<String>,
+    pub blocks: Vec<Block>,
+}
@@ -0,0 +1,14 @@
+use super::Page;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// what an import created, root_page is the page (or folder page) that was picked
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct ImportSummary {
+    pub root_page: Page,
+    pub page_count: usize,
+    pub block_count: usize,
+    pub assets: Vec<String>,
+    pub warnings: Vec<String>, // files that couldnt be read, images that couldnt be copied
+}
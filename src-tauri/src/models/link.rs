@@ -0,0 +1,67 @@
+use super::Page;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// how a block points at a page, Mention is plain text with the page title in it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub enum LinkKind {
+    PageLink,
+    SubPage,
+    Mention,
+}
+
+// snippet is the block's text (cut down around the title for mentions),
+// context is the block a link sits under or right after
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct LinkMention {
+    pub block_id: String,
+    pub kind: LinkKind,
+    pub snippet: String,
+    pub context: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BacklinkGroup {
+    pub page: Page,
+    pub mentions: Vec<LinkMention>,
+}
+
+// "what links here", unlinked are blocks that name the page without linking it
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct Backlinks {
+    pub linked: Vec<BacklinkGroup>,
+    pub unlinked: Vec<BacklinkGroup>,
+}
+
+// a PageLink/SubPage block and where it lives, used for links into a page being deleted
+// and for ones already pointing at a page that no longer exists
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct LinkReference {
+    pub block_id: String,
+    pub page: Page,
+    pub target_page_id: String,
+    pub kind: LinkKind,
+    pub content: String,
+}
+
+// what to do with links into a page that's going away
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(tag = "type", content = "data")]
+pub enum LinkRepair {
+    Unlink, // turns the block into plain text
+    Redirect { page_id: String },
+}
+
+// broken is what still points at the deleted pages, repaired is what got rewritten
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct DeleteReport {
+    pub broken: Vec<LinkReference>,
+    pub repaired: Vec<LinkReference>,
+}
@@ -4,10 +4,35 @@
  * page content just conist of blocks so we can just say what page id the block
  * belongs to
 */
+pub mod backup;
 pub mod block;
+pub mod block_op;
+pub mod export;
+pub mod graph;
+pub mod history;
+pub mod import;
+pub mod link;
 pub mod page;
+pub mod rich_text;
 pub mod search;
+pub mod undo;
+pub mod workspace;
 
-pub use block::{Block, BlockType};
+pub use backup::{BackupManifest, BackupSettings, BackupSummary};
+pub use block::{Block, BlockNode, BlockTreeOptions, BlockType};
+pub use block_op::{BlockOp, BlockOpError};
+pub use export::ExportSummary;
+pub use graph::{EdgeKind, GraphEdge, GraphNode, GraphOptions, PageGraph};
+pub use history::{PageVersion, PageVersionInfo, VersionKind};
+pub use import::ImportSummary;
+pub use link::{
+    BacklinkGroup, Backlinks, DeleteReport, LinkKind, LinkMention, LinkReference, LinkRepair,
+};
 pub use page::Page;
-pub use search::{SearchFileGroup, SearchMatch, SearchResponse};
+pub use rich_text::{Mark, TextSpan};
+pub use search::{
+    ArchivedFilter, BlockTypeFilter, ReplaceChange, ReplaceResponse, SearchError, SearchFileGroup,
+    SearchFilters, SearchMatch, SearchMode, SearchResponse,
+};
+pub use undo::{UndoResult, UndoState};
+pub use workspace::Workspace;
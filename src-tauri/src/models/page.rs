@@ -15,6 +15,8 @@ pub struct Page {
     pub created_at: DateTime<Utc>,
     #[ts(type = "string")]
     pub updated_at: DateTime<Utc>,
+    #[ts(type = "string | null")]
+    pub archived_at: Option<DateTime<Utc>>, // when it went to the trash
 }
 
 impl Page {
@@ -29,6 +31,7 @@ impl Page {
             is_archived: false,
             created_at: now,
             updated_at: now,
+            archived_at: None,
         }
     }
 
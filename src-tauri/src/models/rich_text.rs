@@ -0,0 +1,195 @@
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// formatting on a run of text. colours are whatever css accepts ("red", "#e03e3e")
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(tag = "type", content = "data")]
+pub enum Mark {
+    Bold,
+    Italic,
+    Strikethrough,
+    Code,
+    Color(String),
+    Highlight(String),
+}
+
+// a run of text with the same formatting, a block's rich text is a list of these.
+// block.content always holds the same text without formatting so search and links keep working
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct TextSpan {
+    pub text: String,
+    #[serde(default)]
+    pub marks: Vec<Mark>,
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+impl TextSpan {
+    pub fn plain(text: &str) -> Self {
+        TextSpan {
+            text: text.to_string(),
+            marks: Vec::new(),
+            link: None,
+        }
+    }
+
+    fn same_format(&self, other: &TextSpan) -> bool {
+        self.marks == other.marks && self.link == other.link
+    }
+}
+
+// the text without formatting, what goes into block.content
+pub fn plain_text(spans: &[TextSpan]) -> String {
+    spans.iter().map(|s| s.text.as_str()).collect()
+}
+
+// same as str::trim on the plain text, so spans and a trimmed content stay in step
+pub fn trim(mut spans: Vec<TextSpan>) -> Vec<TextSpan> {
+    if let Some(first) = spans.iter_mut().find(|s| !s.text.trim().is_empty()) {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = spans.iter_mut().rev().find(|s| !s.text.trim().is_empty()) {
+        last.text = last.text.trim_end().to_string();
+    }
+    let first = spans.iter().position(|s| !s.text.trim().is_empty());
+    let last = spans.iter().rposition(|s| !s.text.trim().is_empty());
+    match (first, last) {
+        (Some(first), Some(last)) => spans.drain(first..=last).collect(),
+        _ => Vec::new(),
+    }
+}
+
+// new plain text for formatted spans, for saves that only send content. the unchanged start
+// and end keep their marks, typed text takes the formatting of the text right before it
+pub fn retext(spans: Vec<TextSpan>, text: &str) -> Option<Vec<TextSpan>> {
+    let old = plain_text(&spans);
+    let prefix: usize = old
+        .chars()
+        .zip(text.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let room = old.len().min(text.len()) - prefix;
+    let mut suffix = 0;
+    for (a, b) in old.chars().rev().zip(text.chars().rev()) {
+        if a != b || suffix + a.len_utf8() > room {
+            break;
+        }
+        suffix += a.len_utf8();
+    }
+
+    let mut out = slice(&spans, 0, prefix);
+    let typed = &text[prefix..text.len() - suffix];
+    if !typed.is_empty() {
+        let mut offset = 0;
+        let before = spans
+            .iter()
+            .find(|s| {
+                offset += s.text.len();
+                !s.text.is_empty() && offset >= prefix
+            })
+            .cloned()
+            .unwrap_or_else(|| TextSpan::plain(""));
+        out.push(TextSpan {
+            text: typed.to_string(),
+            ..before
+        });
+    }
+    out.extend(slice(&spans, old.len() - suffix, old.len()));
+    normalize(out)
+}
+
+// the spans covering start..end (byte offsets into the plain text), cut at the edges
+fn slice(spans: &[TextSpan], start: usize, end: usize) -> Vec<TextSpan> {
+    let mut out = Vec::new();
+    let mut offset = 0;
+    for span in spans {
+        let (from, to) = (offset, offset + span.text.len());
+        offset = to;
+        let (a, b) = (start.max(from), end.min(to));
+        if a < b {
+            out.push(TextSpan {
+                text: span.text[a - from..b - from].to_string(),
+                ..span.clone()
+            });
+        }
+    }
+    out
+}
+
+// sorted marks, no empty runs, neighbours with the same formatting joined into one.
+// None when nothing is formatted at all, the block is plain text then
+pub fn normalize(spans: Vec<TextSpan>) -> Option<Vec<TextSpan>> {
+    let mut out: Vec<TextSpan> = Vec::new();
+    for mut span in spans.into_iter().filter(|s| !s.text.is_empty()) {
+        span.marks.sort();
+        span.marks.dedup();
+        match out.last_mut() {
+            Some(last) if last.same_format(&span) => last.text.push_str(&span.text),
+            _ => out.push(span),
+        }
+    }
+    let formatted = out.iter().any(|s| !s.marks.is_empty() || s.link.is_some());
+    formatted.then_some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_joins_runs_and_drops_plain() {
+        let bold = |text: &str| TextSpan {
+            marks: vec![Mark::Bold],
+            ..TextSpan::plain(text)
+        };
+        let spans = vec![
+            TextSpan::plain("a "),
+            bold("b"),
+            TextSpan {
+                marks: vec![Mark::Bold, Mark::Bold],
+                ..TextSpan::plain("c")
+            },
+            TextSpan::plain(""),
+        ];
+        assert_eq!(
+            normalize(spans),
+            Some(vec![TextSpan::plain("a "), bold("bc")])
+        );
+        assert_eq!(normalize(vec![TextSpan::plain("just text")]), None);
+        assert_eq!(plain_text(&[TextSpan::plain("a "), bold("bc")]), "a bc");
+    }
+
+    #[test]
+    fn retext_keeps_marks_around_the_edit() {
+        let bold = |text: &str| TextSpan {
+            marks: vec![Mark::Bold],
+            ..TextSpan::plain(text)
+        };
+        let spans = vec![
+            TextSpan::plain("say "),
+            bold("hello"),
+            TextSpan::plain(" now"),
+        ];
+
+        // typing inside (and at the end of) the bold run makes more bold text
+        assert_eq!(
+            retext(spans.clone(), "say helloo now"),
+            Some(vec![
+                TextSpan::plain("say "),
+                bold("helloo"),
+                TextSpan::plain(" now")
+            ])
+        );
+        // deleting from the plain text leaves the bold run alone
+        assert_eq!(
+            retext(spans.clone(), "say hello"),
+            Some(vec![TextSpan::plain("say "), bold("hello")])
+        );
+        // retyping all of the bold text takes the plain formatting before it
+        assert_eq!(retext(spans, "say ü now"), None);
+        assert_eq!(retext(vec![bold("ab")], ""), None);
+    }
+}
@@ -1,16 +1,67 @@
+use super::Block;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub enum SearchMode {
+    #[default]
+    Literal,
+    Regex,
+    Fuzzy,
+}
+
+// narrows a search down, can come from the frontend or from `type:todo in:"Project X"` in the query
+// after is inclusive and before is exclusive for all the dates
+#[derive(Debug, Serialize, Deserialize, Clone, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(default)]
+pub struct SearchFilters {
+    pub block_types: Vec<BlockTypeFilter>, // any of these, empty means every type
+    pub within: Option<String>,            // page id or title, subpages included
+    #[ts(type = "string | null")]
+    pub created_after: Option<DateTime<Utc>>,
+    #[ts(type = "string | null")]
+    pub created_before: Option<DateTime<Utc>>,
+    #[ts(type = "string | null")]
+    pub updated_after: Option<DateTime<Utc>>,
+    #[ts(type = "string | null")]
+    pub updated_before: Option<DateTime<Utc>>,
+    pub archived: ArchivedFilter,
+}
+
+// block_type is the BlockType tag ("Todo", "Code"...), the rest only narrow those types
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct BlockTypeFilter {
+    pub block_type: String,
+    pub checked: Option<bool>,
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub enum ArchivedFilter {
+    #[default]
+    Exclude,
+    Include,
+    Only,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, TS)]
 #[ts(export, export_to = "../../src/types/")]
 pub struct SearchMatch {
     pub block_id: String,
     pub content: String,
     pub block_type: String,
-    pub match_start: usize,
+    pub match_start: usize, // utf-16 offsets into content so js can slice with them directly
     pub match_end: usize,
     pub snippet: String,
+    pub snippet_match_start: usize, // same thing but inside snippet
+    pub snippet_match_end: usize,
     pub order: i32,
+    pub score: f64, // 1.0 for literal/regex hits, lower for fuzzy ones with typos
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, TS)]
@@ -27,4 +78,56 @@ pub struct SearchFileGroup {
 pub struct SearchResponse {
     pub groups: Vec<SearchFileGroup>,
     pub total_matches: usize,
-}
\ No newline at end of file
+}
+
+// one block that a replace touched (or would touch on a dry run)
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct ReplaceChange {
+    pub block_id: String,
+    pub page_id: String,
+    pub before: String,
+    pub after: String,
+    pub replacements: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct ReplaceResponse {
+    pub changes: Vec<ReplaceChange>,
+    pub blocks: Vec<Block>, // updated blocks, empty when dry_run
+    pub total_replacements: usize,
+    pub dry_run: bool,
+}
+
+// sent to the frontend as is so it can point at the bad part of a regex instead of showing a string
+#[derive(Debug, Serialize, Deserialize, Clone, TS)]
+#[ts(export, export_to = "../../src/types/")]
+#[serde(tag = "kind", content = "data")]
+pub enum SearchError {
+    InvalidRegex { message: String },
+    InvalidQuery { message: String },
+    UnsupportedMode { mode: SearchMode, message: String },
+    Database { message: String },
+}
+
+impl std::fmt::Display for SearchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchError::InvalidRegex { message } => write!(f, "invalid regex D: {}", message),
+            SearchError::InvalidQuery { message } => write!(f, "invalid query D: {}", message),
+            SearchError::UnsupportedMode { message, .. } => write!(f, "{}", message),
+            SearchError::Database { message } => write!(f, "database error D: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
+impl From<rusqlite::Error> for SearchError {
+    fn from(e: rusqlite::Error) -> Self {
+        SearchError::Database {
+            message: e.to_string(),
+        }
+    }
+}
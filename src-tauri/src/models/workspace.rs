@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+// a workspace is just a folder with its own bismuth.db and page_assets
+#[derive(Debug, Clone, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../../src/types/")]
+pub struct Workspace {
+    pub name: String,
+    pub path: String,
+    #[ts(type = "string")]
+    pub created_at: DateTime<Utc>,
+}
+
+impl Workspace {
+    pub fn new(name: String, path: String) -> Self {
+        Self {
+            name,
+            path,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_bindings() {
+        Workspace::export().unwrap();
+    }
+}
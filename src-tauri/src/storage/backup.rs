@@ -0,0 +1,492 @@
+use super::migrations::{latest_version, schema_version};
+use super::workspace::{folder_name, ASSETS_DIRNAME, DB_FILENAME};
+use super::Database;
+use crate::models::{BackupManifest, BackupSummary};
+use chrono::{DateTime, Datelike, Utc};
+use rusqlite::backup::Progress;
+use rusqlite::{Connection, DatabaseName};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+use zip::write::SimpleFileOptions;
+
+/*
+ * .bismuth archives are a zip of manifest.json, a snapshot of bismuth.db and all of page_assets.
+ * the snapshot goes through sqlite's online backup api, copying the file of an open db can
+ * catch it halfway through a write. restoring always goes into a brand new folder
+ */
+pub const ARCHIVE_EXTENSION: &str = "bismuth";
+pub const MANIFEST_FILENAME: &str = "manifest.json";
+
+// bump when the layout of the zip changes, not for schema changes (those are migrations)
+const FORMAT_VERSION: u32 = 1;
+
+impl Database {
+    // consistent copy of the live db into a new file at `path`
+    pub fn backup_to(&self, path: &Path) -> rusqlite::Result<()> {
+        self.get_connection().backup(DatabaseName::Main, path, None)
+    }
+
+    // replaces everything in the open db with the one at `path`, the connection stays valid
+    pub fn restore_from(&self, path: &Path) -> rusqlite::Result<()> {
+        self.get_connection()
+            .restore(DatabaseName::Main, path, None::<fn(Progress)>)
+    }
+}
+
+// "My Notes" -> "my-notes-2024-01-15-093000.bismuth"
+pub fn archive_name(workspace: &str) -> String {
+    let name = folder_name(workspace);
+    let name = if name.is_empty() { "bismuth" } else { &name };
+    format!(
+        "{}-{}.{}",
+        name,
+        Utc::now().format("%Y-%m-%d-%H%M%S"),
+        ARCHIVE_EXTENSION
+    )
+}
+
+pub fn write_archive(
+    db: &Database,
+    assets_dir: &Path,
+    workspace: &str,
+    destination: &Path,
+) -> Result<BackupManifest, String> {
+    let snapshot = std::env::temp_dir().join(format!("bismuth-backup-{}.db", Uuid::new_v4()));
+    let result = db
+        .backup_to(&snapshot)
+        .map_err(|e| format!("failed to snapshot database D: {}", e))
+        .and_then(|_| write_zip(&snapshot, assets_dir, workspace, destination));
+    if snapshot.exists() {
+        if let Err(e) = fs::remove_file(&snapshot) {
+            eprintln!("Failed to clean up {:?}: {}", snapshot, e);
+        }
+    }
+    result
+}
+
+fn write_zip(
+    snapshot: &Path,
+    assets_dir: &Path,
+    workspace: &str,
+    destination: &Path,
+) -> Result<BackupManifest, String> {
+    verify_database(snapshot)?;
+    let (schema_version, page_count) = snapshot_info(snapshot)
+        .map_err(|e| format!("failed to read database snapshot D: {}", e))?;
+    let mut assets = Vec::new();
+    asset_files(assets_dir, Path::new(ASSETS_DIRNAME), &mut assets)
+        .map_err(|e| format!("failed to read {:?} D: {}", assets_dir, e))?;
+
+    let manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        schema_version,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        workspace: workspace.to_string(),
+        created_at: Utc::now(),
+        page_count,
+        asset_count: assets.len(),
+    };
+
+    // written next to the destination first so a failed backup never leaves half a file behind
+    let file_name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let partial = destination.with_file_name(format!("{}.partial", file_name));
+    let result = zip_files(&partial, &manifest, snapshot, &assets)
+        .and_then(|_| fs::rename(&partial, destination));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("failed to write {:?} D: {}", destination, e));
+    }
+    Ok(manifest)
+}
+
+// a backup of a damaged db is worse than none, it would push out the good ones
+pub fn verify_database(path: &Path) -> Result<(), String> {
+    let result: rusqlite::Result<String> = Connection::open(path)
+        .and_then(|conn| conn.query_row("PRAGMA integrity_check", [], |row| row.get(0)));
+    match result {
+        Ok(status) if status == "ok" => Ok(()),
+        Ok(status) => Err(format!("database failed the integrity check D: {}", status)),
+        Err(e) => Err(format!("database failed the integrity check D: {}", e)),
+    }
+}
+
+fn snapshot_info(snapshot: &Path) -> rusqlite::Result<(u32, usize)> {
+    let conn = Connection::open(snapshot)?;
+    let version = schema_version(&conn)?;
+    let pages: i64 = conn.query_row("SELECT COUNT(*) FROM pages", [], |row| row.get(0))?;
+    Ok((version, pages as usize))
+}
+
+// (file on disk, name inside the zip) for everything under page_assets
+fn asset_files(dir: &Path, prefix: &Path, files: &mut Vec<(PathBuf, String)>) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = prefix.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            asset_files(&path, &name, files)?;
+        } else {
+            let name: Vec<String> = name
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect();
+            files.push((path, name.join("/")));
+        }
+    }
+    Ok(())
+}
+
+fn zip_files(
+    target: &Path,
+    manifest: &BackupManifest,
+    snapshot: &Path,
+    assets: &[(PathBuf, String)],
+) -> io::Result<()> {
+    let mut zip = zip::ZipWriter::new(fs::File::create(target)?);
+    let options = SimpleFileOptions::default().large_file(true);
+
+    zip.start_file(MANIFEST_FILENAME, options)?;
+    serde_json::to_writer_pretty(&mut zip, manifest)?;
+
+    let files = std::iter::once((snapshot, DB_FILENAME)).chain(
+        assets
+            .iter()
+            .map(|(path, name)| (path.as_path(), name.as_str())),
+    );
+    for (path, name) in files {
+        zip.start_file(name, options)?;
+        io::copy(&mut fs::File::open(path)?, &mut zip)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn open_archive(archive: &Path) -> Result<zip::ZipArchive<fs::File>, String> {
+    let file =
+        fs::File::open(archive).map_err(|e| format!("failed to open {:?} D: {}", archive, e))?;
+    zip::ZipArchive::new(file).map_err(|e| format!("not a bismuth backup {:?} D: {}", archive, e))
+}
+
+// checks the archive can be restored by this build before touching anything
+pub fn read_manifest(archive: &Path) -> Result<BackupManifest, String> {
+    let mut zip = open_archive(archive)?;
+    let entry = zip
+        .by_name(MANIFEST_FILENAME)
+        .map_err(|_| format!("not a bismuth backup, {} is missing D:", MANIFEST_FILENAME))?;
+    let manifest: BackupManifest = serde_json::from_reader(entry)
+        .map_err(|e| format!("failed to read {} D: {}", MANIFEST_FILENAME, e))?;
+
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "backup format {} is newer than this version of bismuth supports ({}), please update the app",
+            manifest.format_version, FORMAT_VERSION
+        ));
+    }
+    if manifest.schema_version > latest_version() {
+        return Err(format!(
+            "backup schema version {} is newer than this version of bismuth supports ({}), please update the app",
+            manifest.schema_version,
+            latest_version()
+        ));
+    }
+    if zip.index_for_name(DB_FILENAME).is_none() {
+        return Err(format!(
+            "not a bismuth backup, {} is missing D:",
+            DB_FILENAME
+        ));
+    }
+    Ok(manifest)
+}
+
+// unpacks into `folder` (expected to be empty) and migrates the db up to the current schema
+pub fn restore_archive(archive: &Path, folder: &Path) -> Result<BackupManifest, String> {
+    let manifest = read_manifest(archive)?;
+    let mut zip = open_archive(archive)?;
+
+    for i in 0..zip.len() {
+        let mut entry = zip
+            .by_index(i)
+            .map_err(|e| format!("failed to read {:?} D: {}", archive, e))?;
+        // enclosed_name drops anything that would land outside the folder
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let wanted = name == Path::new(DB_FILENAME) || name.starts_with(ASSETS_DIRNAME);
+        if entry.is_dir() || !wanted {
+            continue;
+        }
+
+        let target = folder.join(&name);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("failed to create folder D: {}", e))?;
+        }
+        let mut out = fs::File::create(&target)
+            .map_err(|e| format!("failed to write {:?} D: {}", target, e))?;
+        io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("failed to write {:?} D: {}", target, e))?;
+    }
+
+    fs::create_dir_all(folder.join(ASSETS_DIRNAME))
+        .map_err(|e| format!("failed to create assets folder D: {}", e))?;
+    verify_database(&folder.join(DB_FILENAME))?;
+    Database::new(folder.join(DB_FILENAME))
+        .map_err(|e| format!("failed to open restored database D: {}", e))?;
+    Ok(manifest)
+}
+
+// puts an archive back over the open workspace: db through the restore api, page_assets replaced
+// the assets are copied next to the live folder first and swapped in with a rename, so a failed
+// copy leaves the old ones alone
+pub fn restore_in_place(
+    db: &Database,
+    assets_dir: &Path,
+    archive: &Path,
+) -> Result<BackupManifest, String> {
+    let scratch = std::env::temp_dir().join(format!("bismuth-restore-{}", Uuid::new_v4()));
+    let staged = sibling(assets_dir, "restoring");
+    let result = restore_archive(archive, &scratch).and_then(|manifest| {
+        copy_dir(&scratch.join(ASSETS_DIRNAME), &staged)
+            .map_err(|e| format!("failed to copy {:?} D: {}", staged, e))?;
+        db.restore_from(&scratch.join(DB_FILENAME))
+            .map_err(|e| format!("failed to restore database D: {}", e))?;
+        swap_dir(&staged, assets_dir)
+            .map_err(|e| format!("failed to restore {:?} D: {}", assets_dir, e))?;
+        Ok(manifest)
+    });
+    for leftover in [&scratch, &staged] {
+        if leftover.exists() {
+            if let Err(e) = fs::remove_dir_all(leftover) {
+                eprintln!("Failed to clean up {:?}: {}", leftover, e);
+            }
+        }
+    }
+    result
+}
+
+// a hidden folder next to `dir`, same filesystem so renames between them dont copy
+fn sibling(dir: &Path, what: &str) -> PathBuf {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    dir.with_file_name(format!(".{}-{}-{}", name, what, Uuid::new_v4()))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+// moves `staged` into place, the old folder is only deleted once the new one is in
+fn swap_dir(staged: &Path, to: &Path) -> io::Result<()> {
+    let old = sibling(to, "old");
+    if to.exists() {
+        fs::rename(to, &old)?;
+    }
+    if let Err(e) = fs::rename(staged, to) {
+        if old.exists() {
+            fs::rename(&old, to)?;
+        }
+        return Err(e);
+    }
+    if old.exists() {
+        if let Err(e) = fs::remove_dir_all(&old) {
+            eprintln!("Failed to clean up {:?}: {}", old, e);
+        }
+    }
+    Ok(())
+}
+
+// every readable archive in `dir`, newest first
+pub fn list_archives(dir: &Path) -> Vec<BackupSummary> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<BackupSummary> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == ARCHIVE_EXTENSION))
+        .filter_map(|path| {
+            let manifest = read_manifest(&path).ok()?;
+            Some(BackupSummary {
+                size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                path: path.to_string_lossy().to_string(),
+                manifest,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|b| std::cmp::Reverse(b.manifest.created_at));
+    backups
+}
+
+// deletes the archives in `dir` that fall outside the daily/weekly windows, returns how many
+pub fn rotate(dir: &Path, keep_daily: u32, keep_weekly: u32) -> usize {
+    let backups = list_archives(dir);
+    let created: Vec<DateTime<Utc>> = backups.iter().map(|b| b.manifest.created_at).collect();
+    let keep = to_keep(&created, keep_daily as usize, keep_weekly as usize);
+
+    let mut removed = 0;
+    for (idx, backup) in backups.iter().enumerate() {
+        if keep.contains(&idx) {
+            continue;
+        }
+        match fs::remove_file(&backup.path) {
+            Ok(_) => removed += 1,
+            Err(e) => eprintln!("Failed to remove old backup {}: {}", backup.path, e),
+        }
+    }
+    removed
+}
+
+// `created` is newest first. the newest backup of each of the last `daily` days and of each of
+// the last `weekly` weeks survive, and the very newest one always does
+fn to_keep(created: &[DateTime<Utc>], daily: usize, weekly: usize) -> HashSet<usize> {
+    let mut keep = HashSet::new();
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    for (idx, time) in created.iter().enumerate() {
+        let day = time.date_naive();
+        if days.len() < daily && days.insert(day) {
+            keep.insert(idx);
+        }
+        let week = (time.iso_week().year(), time.iso_week().week());
+        if weeks.len() < weekly && weeks.insert(week) {
+            keep.insert(idx);
+        }
+    }
+    if !created.is_empty() {
+        keep.insert(0);
+    }
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Page;
+    use std::io::Write;
+
+    fn scratch() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bismuth-backup-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let dir = scratch();
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Kept".to_string());
+        db.insert_page(&page).unwrap();
+        let assets = dir.join(ASSETS_DIRNAME);
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(assets.join("cover.png"), "png").unwrap();
+
+        let archive = dir.join(archive_name("My Notes"));
+        let written = write_archive(&db, &assets, "My Notes", &archive).unwrap();
+        assert_eq!(written.schema_version, latest_version());
+        assert_eq!((written.page_count, written.asset_count), (1, 1));
+        assert!(archive.is_file());
+
+        let restored_dir = dir.join("restored");
+        let restored = restore_archive(&archive, &restored_dir).unwrap();
+        assert_eq!(restored.workspace, "My Notes");
+        assert_eq!(
+            fs::read_to_string(restored_dir.join(ASSETS_DIRNAME).join("cover.png")).unwrap(),
+            "png"
+        );
+        let restored_db = Database::new(restored_dir.join(DB_FILENAME)).unwrap();
+        assert_eq!(
+            restored_db.get_page(&page.id).unwrap().unwrap().title,
+            "Kept"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn newer_archives_are_rejected() {
+        let dir = scratch();
+        let archive = dir.join("future.bismuth");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let manifest = BackupManifest {
+            format_version: FORMAT_VERSION,
+            schema_version: latest_version() + 1,
+            app_version: "99.0.0".to_string(),
+            workspace: "future".to_string(),
+            created_at: Utc::now(),
+            page_count: 0,
+            asset_count: 0,
+        };
+        zip.start_file(MANIFEST_FILENAME, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(serde_json::to_string(&manifest).unwrap().as_bytes())
+            .unwrap();
+        zip.start_file(DB_FILENAME, SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let err = restore_archive(&archive, &dir.join("restored")).unwrap_err();
+        assert!(err.contains("newer than this version"), "{}", err);
+        assert!(!dir.join("restored").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotation_keeps_daily_and_weekly() {
+        let start = DateTime::parse_from_rfc3339("2024-03-31T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        // two a day for 30 days, newest first
+        let created: Vec<DateTime<Utc>> = (0..60)
+            .map(|i| start - chrono::Duration::hours(12 * i))
+            .collect();
+
+        let mut keep: Vec<usize> = to_keep(&created, 3, 2).into_iter().collect();
+        keep.sort();
+        // newest of mar 31, 30 and 29, plus the newest of the week before (sunday mar 24)
+        assert_eq!(keep, vec![0, 2, 4, 14]);
+        assert_eq!(to_keep(&created, 0, 0), HashSet::from([0]));
+    }
+
+    #[test]
+    fn restores_over_the_open_workspace() {
+        let dir = scratch();
+        let db = Database::new(":memory:").unwrap();
+        let kept = Page::new("Kept".to_string());
+        db.insert_page(&kept).unwrap();
+        let assets = dir.join(ASSETS_DIRNAME);
+        fs::create_dir_all(&assets).unwrap();
+        fs::write(assets.join("old.png"), "png").unwrap();
+
+        let archive = dir.join("snapshot.bismuth");
+        write_archive(&db, &assets, "notes", &archive).unwrap();
+        assert_eq!(list_archives(&dir).len(), 1);
+
+        let later = Page::new("Later".to_string());
+        db.insert_page(&later).unwrap();
+        fs::write(assets.join("new.png"), "png").unwrap();
+
+        restore_in_place(&db, &assets, &archive).unwrap();
+        assert!(db.get_page(&kept.id).unwrap().is_some());
+        assert!(db.get_page(&later.id).unwrap().is_none());
+        assert!(assets.join("old.png").is_file());
+        assert!(!assets.join("new.png").exists());
+        // nothing staged is left lying around next to page_assets
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
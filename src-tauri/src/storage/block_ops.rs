@@ -0,0 +1,444 @@
+use super::db::Database;
+use super::undo::{write_block, Change};
+use crate::models::{Block, BlockOp, BlockOpError, BlockType};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/*
+ * a batch of inserts/updates/deletes/moves in one transaction. rows are written as each op runs
+ * (so later ops see earlier ones) but sibling order is only tracked in memory, one list per
+ * parent, and written in a single pass at the end. the first op that fails rolls everything back
+ */
+impl Database {
+    // returns every block that changed in its final state, plus the changes for one undo step
+    pub fn apply_block_ops(
+        &self,
+        ops: &[BlockOp],
+    ) -> Result<(Vec<Block>, Vec<Change>), BlockOpError> {
+        let fail = |index: usize| move |message: String| BlockOpError { index, message };
+        let database = |index: usize| {
+            move |e: rusqlite::Error| BlockOpError {
+                index,
+                message: format!("database error D: {}", e),
+            }
+        };
+
+        let mut conn = self.get_connection();
+        let tx = conn.transaction().map_err(database(0))?;
+        let mut batch = Batch {
+            db: self,
+            conn: &tx,
+            groups: HashMap::new(),
+            touched: Vec::new(),
+        };
+        for (index, op) in ops.iter().enumerate() {
+            batch.apply(op).map_err(fail(index))?;
+        }
+        let (blocks, changes) = batch.finish().map_err(database(ops.len()))?;
+        tx.commit().map_err(database(ops.len()))?;
+        Ok((blocks, changes))
+    }
+}
+
+type Group = (Uuid, Option<Uuid>); // page, parent
+
+struct Batch<'a> {
+    db: &'a Database,
+    conn: &'a Connection,
+    groups: HashMap<Group, Vec<Uuid>>, // sibling ids in their new order
+    touched: Vec<(Uuid, Option<Block>)>, // every row the batch wrote, with how it was before
+}
+
+impl Batch<'_> {
+    fn apply(&mut self, op: &BlockOp) -> Result<(), String> {
+        match op {
+            BlockOp::Insert {
+                id,
+                page_id,
+                parent_id,
+                index,
+                block_type,
+                content,
+                spans,
+            } => {
+                let page_uuid = parse(page_id, "page_id")?;
+                let mut block = Block::new(page_uuid, block_type.clone(), content.clone());
+                if let Some(spans) = spans {
+                    block = block.with_spans(spans.clone());
+                }
+                if let Some(id) = id {
+                    block.id = parse(id, "id")?;
+                    if self.load(&block.id)?.is_some() {
+                        return Err(format!("block already exists D: {}", id));
+                    }
+                }
+                if let Some(parent_id) = parent_id {
+                    block.parent_id = Some(self.parent_on(parent_id, &page_uuid)?);
+                }
+                self.place(&block, None, *index)?;
+                self.touch(block.id, None);
+                self.write(&block)
+            }
+            BlockOp::Update {
+                block_id,
+                block_type,
+                content,
+                spans,
+            } => {
+                let before = self.existing(block_id)?;
+                let mut block = before.clone();
+                if let Some(block_type) = block_type {
+                    // turning a SubPage into something else (or back) would orphan or steal a page
+                    let subpage = |t: &BlockType| matches!(t, BlockType::SubPage { .. });
+                    if *block_type != before.block_type
+                        && (subpage(block_type) || subpage(&before.block_type))
+                    {
+                        return Err(format!(
+                            "cannot change the type of a subpage block D: {}",
+                            block_id
+                        ));
+                    }
+                    block.block_type = block_type.clone();
+                }
+                if let Some(content) = content {
+                    block = block.with_content(content.clone());
+                }
+                if let Some(spans) = spans {
+                    block = block.with_spans(spans.clone());
+                }
+                block.updated_at = Utc::now();
+                self.touch(block.id, Some(before));
+                self.write(&block)
+            }
+            BlockOp::Delete { block_id } => {
+                let block = self.existing(block_id)?;
+                let subtree = self.subtree(&block.id)?;
+                if subtree
+                    .iter()
+                    .any(|b| matches!(b.block_type, BlockType::SubPage { .. }))
+                {
+                    // that takes its page down too (nested ones as well), delete_block handles it
+                    return Err(format!(
+                        "subpage blocks go through delete_block D: {}",
+                        block_id
+                    ));
+                }
+                self.group(&(block.page_id, block.parent_id))?
+                    .retain(|id| *id != block.id);
+                for nested in subtree {
+                    self.groups.remove(&(nested.page_id, Some(nested.id)));
+                    self.touch(nested.id, Some(nested));
+                }
+                self.conn
+                    .execute("DELETE FROM blocks WHERE id = ?1", params![block_id])
+                    .map_err(|e| format!("failed to delete block D: {}", e))?;
+                Ok(())
+            }
+            BlockOp::Move {
+                block_id,
+                parent_id,
+                index,
+            } => {
+                let before = self.existing(block_id)?;
+                let mut block = before.clone();
+                block.parent_id = match parent_id {
+                    Some(parent_id) => {
+                        let parent = self.parent_on(parent_id, &block.page_id)?;
+                        if self.subtree(&block.id)?.iter().any(|b| b.id == parent) {
+                            return Err(format!(
+                                "cannot move a block inside itself D: {}",
+                                block_id
+                            ));
+                        }
+                        Some(parent)
+                    }
+                    None => None,
+                };
+                block.updated_at = Utc::now();
+                self.place(&block, before.parent_id, *index)?;
+                self.touch(block.id, Some(before));
+                self.write(&block)
+            }
+        }
+    }
+
+    // takes the block out of its old sibling list (if it had one) and into the new one
+    fn place(
+        &mut self,
+        block: &Block,
+        old_parent: Option<Uuid>,
+        index: Option<usize>,
+    ) -> Result<(), String> {
+        // both lists are read before anything is written, so the db still matches them
+        if self.load(&block.id)?.is_some() {
+            self.group(&(block.page_id, old_parent))?
+                .retain(|id| *id != block.id);
+        }
+        let siblings = self.group(&(block.page_id, block.parent_id))?;
+        siblings.retain(|id| *id != block.id);
+        let index = index.unwrap_or(siblings.len()).min(siblings.len());
+        siblings.insert(index, block.id);
+        Ok(())
+    }
+
+    fn group(&mut self, key: &Group) -> Result<&mut Vec<Uuid>, String> {
+        if !self.groups.contains_key(key) {
+            let parent = key.1.map(|p| p.to_string());
+            let ids = self
+                .db
+                .children(self.conn, &key.0.to_string(), parent.as_deref(), None)
+                .map_err(|e| format!("failed to get blocks D: {}", e))?
+                .into_iter()
+                .map(|b| b.id)
+                .collect();
+            self.groups.insert(*key, ids);
+        }
+        Ok(self.groups.get_mut(key).unwrap())
+    }
+
+    // the parent has to be on the same page, blocks never point across pages
+    fn parent_on(&self, parent_id: &str, page_id: &Uuid) -> Result<Uuid, String> {
+        let parent = self.existing(parent_id)?;
+        if parent.page_id != *page_id {
+            return Err(format!("parent block is on another page D: {}", parent_id));
+        }
+        Ok(parent.id)
+    }
+
+    fn existing(&self, block_id: &str) -> Result<Block, String> {
+        self.load(&parse(block_id, "block_id")?)?
+            .ok_or_else(|| format!("block not found D: {}", block_id))
+    }
+
+    fn load(&self, id: &Uuid) -> Result<Option<Block>, String> {
+        self.db
+            .block_in(self.conn, &id.to_string())
+            .map_err(|e| format!("database error D: {}", e))
+    }
+
+    fn subtree(&self, id: &Uuid) -> Result<Vec<Block>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "WITH RECURSIVE subtree(id) AS (
+                     SELECT ?1
+                     UNION
+                     SELECT c.id FROM blocks c JOIN subtree s ON c.parent_id = s.id
+                 )
+                 SELECT id FROM subtree",
+            )
+            .map_err(|e| format!("database error D: {}", e))?;
+        let ids = stmt
+            .query_map(params![id.to_string()], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| format!("database error D: {}", e))?;
+        let mut blocks = Vec::new();
+        for id in ids {
+            if let Some(block) = self.load(&parse(&id, "block_id")?)? {
+                blocks.push(block);
+            }
+        }
+        Ok(blocks)
+    }
+
+    fn write(&self, block: &Block) -> Result<(), String> {
+        write_block(self.conn, &block.id.to_string(), Some(block))
+            .map_err(|e| format!("failed to save block D: {}", e))
+    }
+
+    fn touch(&mut self, id: Uuid, before: Option<Block>) {
+        if !self.touched.iter().any(|(touched, _)| *touched == id) {
+            self.touched.push((id, before));
+        }
+    }
+
+    // the one ordering pass, then before/after for everything the batch touched
+    fn finish(mut self) -> rusqlite::Result<(Vec<Block>, Vec<Change>)> {
+        let groups = std::mem::take(&mut self.groups);
+        for ids in groups.values() {
+            for (position, id) in ids.iter().enumerate() {
+                let Some(block) = self.db.block_in(self.conn, &id.to_string())? else {
+                    continue;
+                };
+                if block.order == position as i32 {
+                    continue;
+                }
+                let mut after = block.clone();
+                after.order = position as i32;
+                write_block(self.conn, &id.to_string(), Some(&after))?;
+                self.touch(block.id, Some(block));
+            }
+        }
+
+        let mut blocks = Vec::new();
+        let mut changes = Vec::new();
+        for (id, before) in self.touched {
+            let after = self.db.block_in(self.conn, &id.to_string())?;
+            if before.is_none() && after.is_none() {
+                continue; // inserted and deleted again in the same batch
+            }
+            blocks.extend(after.clone());
+            changes.push(Change::Block { before, after });
+        }
+        Ok((blocks, changes))
+    }
+}
+
+fn parse(id: &str, field: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(id).map_err(|e| format!("invalid {} D: {}", field, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Page;
+
+    fn contents(db: &Database, page_id: &str) -> Vec<(String, i32)> {
+        db.get_page_blocks(page_id)
+            .unwrap()
+            .into_iter()
+            .filter(|b| b.parent_id.is_none())
+            .map(|b| (b.content, b.order))
+            .collect()
+    }
+
+    fn insert(page: &Page, content: &str, index: Option<usize>) -> BlockOp {
+        BlockOp::Insert {
+            id: None,
+            page_id: page.id.clone(),
+            parent_id: None,
+            index,
+            block_type: BlockType::Text,
+            content: content.to_string(),
+            spans: None,
+        }
+    }
+
+    #[test]
+    fn batch_applies_everything_with_one_ordering_pass() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let old = Block::new(page_id, BlockType::Text, "old".to_string());
+        let last = Block::new(page_id, BlockType::Text, "last".to_string());
+        db.place_blocks(&[old.clone(), last.clone()], None).unwrap();
+
+        let list = Uuid::new_v4();
+        let ops = vec![
+            insert(&page, "first", Some(0)),
+            BlockOp::Insert {
+                id: Some(list.to_string()),
+                page_id: page.id.clone(),
+                parent_id: None,
+                index: Some(1),
+                block_type: BlockType::BulletList,
+                content: "list".to_string(),
+                spans: None,
+            },
+            BlockOp::Insert {
+                id: None,
+                page_id: page.id.clone(),
+                parent_id: Some(list.to_string()),
+                index: None,
+                block_type: BlockType::Text,
+                content: "nested".to_string(),
+                spans: None,
+            },
+            BlockOp::Update {
+                block_id: old.id.to_string(),
+                block_type: None,
+                content: Some("edited".to_string()),
+                spans: None,
+            },
+            BlockOp::Move {
+                block_id: last.id.to_string(),
+                parent_id: None,
+                index: Some(0),
+            },
+            BlockOp::Delete {
+                block_id: old.id.to_string(),
+            },
+        ];
+        let (blocks, changes) = db.apply_block_ops(&ops).unwrap();
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![("last".into(), 0), ("first".into(), 1), ("list".into(), 2)]
+        );
+        let nested = blocks.iter().find(|b| b.content == "nested").unwrap();
+        assert_eq!((nested.parent_id, nested.order), (Some(list), 0));
+
+        db.apply_changes(&changes, true).unwrap();
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![("old".into(), 0), ("last".into(), 1)]
+        );
+    }
+
+    #[test]
+    fn failing_op_rolls_back_the_batch() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+
+        let ops = vec![
+            insert(&page, "kept?", None),
+            BlockOp::Delete {
+                block_id: Uuid::new_v4().to_string(),
+            },
+        ];
+        let error = db.apply_block_ops(&ops).unwrap_err();
+        assert_eq!(error.index, 1);
+        assert!(error.message.starts_with("block not found"));
+        assert!(contents(&db, &page.id).is_empty());
+    }
+
+    #[test]
+    fn subpage_blocks_cant_be_deleted_or_retyped_in_a_batch() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        let child = Page::new("Child".to_string()).with_parent(page.id.clone());
+        db.insert_page(&page).unwrap();
+        db.insert_page(&child).unwrap();
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let list = Block::new(page_id, BlockType::BulletList, "list".to_string());
+        let subpage = Block::new(
+            page_id,
+            BlockType::SubPage {
+                page_id: Uuid::parse_str(&child.id).unwrap(),
+            },
+            "Child".to_string(),
+        )
+        .with_parent(list.id);
+        let text = Block::new(page_id, BlockType::Text, "text".to_string());
+        db.insert_block(&list).unwrap();
+        db.insert_block(&subpage).unwrap();
+        db.insert_block(&text).unwrap();
+
+        let rejected = [
+            BlockOp::Delete {
+                block_id: list.id.to_string(),
+            },
+            BlockOp::Update {
+                block_id: subpage.id.to_string(),
+                block_type: Some(BlockType::Text),
+                content: None,
+                spans: None,
+            },
+            BlockOp::Update {
+                block_id: text.id.to_string(),
+                block_type: Some(subpage.block_type.clone()),
+                content: None,
+                spans: None,
+            },
+        ];
+        for op in rejected {
+            let error = db.apply_block_ops(&[op]).unwrap_err();
+            assert_eq!(error.index, 0);
+        }
+        assert!(db.get_page(&child.id).unwrap().is_some());
+        assert_eq!(db.get_page_blocks(&page.id).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,382 @@
+use super::db::Database;
+use super::undo::{write_block, write_page, Change};
+use crate::models::{Block, BlockType, Page};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+const BLOCK_COLUMNS: &str =
+    "id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans";
+
+/*
+ * block order is the position among siblings (same page, same parent_id), always 0..n with no
+ * duplicates. placing a block reads and renumbers its siblings inside one transaction while holding
+ * the connection, so two inserts at once cant land on the same position. only the siblings whose
+ * position actually changed get written
+ */
+impl Database {
+    // puts `block` at `index` among its siblings (the end when None), new or already stored.
+    // returns a change for every row it touched so the caller can record them as one undo step
+    pub fn place_block(&self, block: &Block, index: Option<usize>) -> Result<Vec<Change>> {
+        self.place_blocks(std::slice::from_ref(block), index)
+    }
+
+    // same thing for several blocks in a row, all in one transaction. a block that changes parent
+    // closes the gap it leaves, one that changes page takes its nested blocks along and a SubPage
+    // block takes its page with it in the page tree
+    pub fn place_blocks(&self, blocks: &[Block], index: Option<usize>) -> Result<Vec<Change>> {
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
+        let mut changes = Vec::new();
+        for (offset, block) in blocks.iter().enumerate() {
+            self.place(&tx, block, index.map(|i| i + offset), &mut changes)?;
+        }
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    // the blocks directly under parent_id (top level when None), in order
+    pub fn child_blocks(&self, page_id: &str, parent_id: Option<&str>) -> Result<Vec<Block>> {
+        let conn = self.get_connection();
+        self.children(&conn, page_id, parent_id, None)
+    }
+
+    fn place(
+        &self,
+        conn: &Connection,
+        block: &Block,
+        index: Option<usize>,
+        changes: &mut Vec<Change>,
+    ) -> Result<()> {
+        let id = block.id.to_string();
+        let page_id = block.page_id.to_string();
+        let parent_id = block.parent_id.map(|p| p.to_string());
+
+        let before = self.block_in(conn, &id)?;
+        if let Some(old) = &before {
+            if old.page_id != block.page_id || old.parent_id != block.parent_id {
+                let old_parent = old.parent_id.map(|p| p.to_string());
+                let left = self.children(
+                    conn,
+                    &old.page_id.to_string(),
+                    old_parent.as_deref(),
+                    Some(&id),
+                )?;
+                renumber(conn, left, changes)?;
+            }
+            if old.page_id != block.page_id {
+                self.move_subtree(conn, old, block, changes)?;
+            }
+        }
+
+        let mut siblings = self.children(conn, &page_id, parent_id.as_deref(), Some(&id))?;
+        let index = index.unwrap_or(siblings.len()).min(siblings.len());
+        let mut placed = block.clone();
+        placed.order = index as i32;
+        write_block(conn, &id, Some(&placed))?;
+        record(
+            changes,
+            Change::Block {
+                before,
+                after: Some(placed.clone()),
+            },
+        );
+
+        siblings.insert(index, placed);
+        renumber(conn, siblings, changes)
+    }
+
+    // nested blocks follow their parent to the new page, and pages behind SubPage blocks get
+    // the new page as their parent
+    fn move_subtree(
+        &self,
+        conn: &Connection,
+        old: &Block,
+        block: &Block,
+        changes: &mut Vec<Change>,
+    ) -> Result<()> {
+        let mut stmt = conn.prepare(&format!(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT id FROM blocks WHERE parent_id = ?1
+                 UNION
+                 SELECT c.id FROM blocks c JOIN subtree s ON c.parent_id = s.id
+             )
+             SELECT {} FROM blocks WHERE id IN subtree",
+            BLOCK_COLUMNS
+        ))?;
+        let nested = stmt
+            .query_map(params![block.id.to_string()], |row| self.row_to_block(row))?
+            .collect::<Result<Vec<_>>>()?;
+
+        for child in std::iter::once(old.clone()).chain(nested) {
+            if let BlockType::SubPage { page_id } = child.block_type {
+                self.reparent_page(conn, &page_id.to_string(), old, block, changes)?;
+            }
+            if child.id == block.id {
+                continue;
+            }
+            let mut moved = child.clone();
+            moved.page_id = block.page_id;
+            write_block(conn, &moved.id.to_string(), Some(&moved))?;
+            record(
+                changes,
+                Change::Block {
+                    before: Some(child),
+                    after: Some(moved),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn reparent_page(
+        &self,
+        conn: &Connection,
+        page_id: &str,
+        old: &Block,
+        block: &Block,
+        changes: &mut Vec<Change>,
+    ) -> Result<()> {
+        let page = conn
+            .query_row(
+                "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
+                 FROM pages WHERE id = ?1",
+                params![page_id],
+                |row| self.row_to_page(row),
+            )
+            .optional()?;
+        // only the page the block actually owns, a SubPage block copied from elsewhere stays a link
+        let Some(page) = page.filter(|p| p.parent_id == Some(old.page_id.to_string())) else {
+            return Ok(());
+        };
+        let mut moved: Page = page.clone();
+        moved.parent_id = Some(block.page_id.to_string());
+        moved.updated_at = Utc::now();
+        write_page(conn, page_id, Some(&moved))?;
+        record(
+            changes,
+            Change::Page {
+                before: Some(page),
+                after: Some(moved),
+            },
+        );
+        Ok(())
+    }
+
+    // reads through the connection the caller already holds, get_block_by_id would lock it again
+    pub(super) fn block_in(&self, conn: &Connection, id: &str) -> Result<Option<Block>> {
+        conn.query_row(
+            &format!("SELECT {} FROM blocks WHERE id = ?1", BLOCK_COLUMNS),
+            params![id],
+            |row| self.row_to_block(row),
+        )
+        .optional()
+    }
+
+    pub(super) fn children(
+        &self,
+        conn: &Connection,
+        page_id: &str,
+        parent_id: Option<&str>,
+        except: Option<&str>,
+    ) -> Result<Vec<Block>> {
+        // ties (from before order was kept unique) fall back to insertion order
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM blocks WHERE page_id = ?1 AND parent_id IS ?2 AND id IS NOT ?3
+             ORDER BY order_position ASC, rowid ASC",
+            BLOCK_COLUMNS
+        ))?;
+        let blocks = stmt
+            .query_map(params![page_id, parent_id, except], |row| {
+                self.row_to_block(row)
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(blocks)
+    }
+}
+
+// writes 0..n as the order of `blocks`, skipping the ones already in place
+fn renumber(conn: &Connection, blocks: Vec<Block>, changes: &mut Vec<Change>) -> Result<()> {
+    for (position, block) in blocks.into_iter().enumerate() {
+        if block.order == position as i32 {
+            continue;
+        }
+        let mut after = block.clone();
+        after.order = position as i32;
+        write_block(conn, &after.id.to_string(), Some(&after))?;
+        record(
+            changes,
+            Change::Block {
+                before: Some(block),
+                after: Some(after),
+            },
+        );
+    }
+    Ok(())
+}
+
+// one change per row, a row touched twice keeps its first before and its last after
+fn record(changes: &mut Vec<Change>, change: Change) {
+    let existing = changes.iter_mut().find(|c| match (c, &change) {
+        (Change::Block { after: Some(a), .. }, Change::Block { after: Some(b), .. }) => {
+            a.id == b.id
+        }
+        (Change::Page { after: Some(a), .. }, Change::Page { after: Some(b), .. }) => a.id == b.id,
+        _ => false,
+    });
+    match (existing, change) {
+        (Some(Change::Block { after, .. }), Change::Block { after: newer, .. }) => *after = newer,
+        (Some(Change::Page { after, .. }), Change::Page { after: newer, .. }) => *after = newer,
+        (_, change) => changes.push(change),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BlockType, Page};
+    use uuid::Uuid;
+
+    fn contents(db: &Database, page_id: &str) -> Vec<(String, i32)> {
+        db.get_page_blocks(page_id)
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.content, b.order))
+            .collect()
+    }
+
+    #[test]
+    fn insert_between_and_move_keep_order_unique() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let text = |content: &str| Block::new(page_id, BlockType::Text, content.to_string());
+
+        for content in ["a", "c"] {
+            db.place_block(&text(content), None).unwrap();
+        }
+        let b = text("b");
+        let changes = db.place_block(&b, Some(1)).unwrap();
+        // b itself and c, which moved down one. a stays untouched
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![("a".into(), 0), ("b".into(), 1), ("c".into(), 2)]
+        );
+
+        let changes = db.place_block(&b, Some(0)).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![("b".into(), 0), ("a".into(), 1), ("c".into(), 2)]
+        );
+
+        // undoing the move puts every touched sibling back
+        db.apply_changes(&changes, true).unwrap();
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![("a".into(), 0), ("b".into(), 1), ("c".into(), 2)]
+        );
+    }
+
+    #[test]
+    fn duplicate_positions_get_squashed() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        for content in ["x", "y"] {
+            db.insert_block(
+                &Block::new(page_id, BlockType::Text, content.to_string()).with_order(3),
+            )
+            .unwrap();
+        }
+
+        let z = Block::new(page_id, BlockType::Text, "z".to_string());
+        db.place_block(&z, None).unwrap();
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![("x".into(), 0), ("y".into(), 1), ("z".into(), 2)]
+        );
+    }
+
+    #[test]
+    fn page_blocks_come_in_tree_order() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let text = |content: &str| Block::new(page_id, BlockType::Text, content.to_string());
+
+        let a = text("a");
+        let b = text("b");
+        db.place_block(&a, None).unwrap();
+        db.place_block(&b, None).unwrap();
+        db.place_block(&text("b1").with_parent(b.id), None).unwrap();
+        db.place_block(&text("a1").with_parent(a.id), None).unwrap();
+
+        // a1 and b1 are both first under their parent, they still show up under them
+        assert_eq!(
+            contents(&db, &page.id),
+            vec![
+                ("a".into(), 0),
+                ("a1".into(), 0),
+                ("b".into(), 1),
+                ("b1".into(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn moving_to_another_page_carries_nested_blocks_and_subpages() {
+        let db = Database::new(":memory:").unwrap();
+        let source = Page::new("Source".to_string());
+        let target = Page::new("Target".to_string());
+        let child = Page::new("Child".to_string()).with_parent(source.id.clone());
+        for page in [&source, &target, &child] {
+            db.insert_page(page).unwrap();
+        }
+        let source_id = Uuid::parse_str(&source.id).unwrap();
+        let target_id = Uuid::parse_str(&target.id).unwrap();
+
+        let keep = Block::new(source_id, BlockType::Text, "keep".to_string());
+        let parent = Block::new(source_id, BlockType::BulletList, "parent".to_string());
+        let subpage = Block::new(
+            source_id,
+            BlockType::SubPage {
+                page_id: Uuid::parse_str(&child.id).unwrap(),
+            },
+            "Child".to_string(),
+        )
+        .with_parent(parent.id);
+        for block in [&parent, &keep, &subpage] {
+            db.place_block(block, None).unwrap();
+        }
+
+        let mut moved = parent.clone();
+        moved.page_id = target_id;
+        let changes = db.place_block(&moved, None).unwrap();
+
+        assert_eq!(contents(&db, &source.id), vec![("keep".into(), 0)]);
+        let nested = db
+            .get_block_by_id(&subpage.id.to_string())
+            .unwrap()
+            .unwrap();
+        assert_eq!(nested.page_id, target_id);
+        assert_eq!(nested.parent_id, Some(parent.id));
+        let child_page = db.get_page(&child.id).unwrap().unwrap();
+        assert_eq!(child_page.parent_id, Some(target.id.clone()));
+
+        db.apply_changes(&changes, true).unwrap();
+        assert_eq!(
+            contents(&db, &source.id),
+            vec![
+                ("parent".into(), 0),
+                ("Child".into(), 0),
+                ("keep".into(), 1)
+            ]
+        );
+        let child_page = db.get_page(&child.id).unwrap().unwrap();
+        assert_eq!(child_page.parent_id, Some(source.id.clone()));
+    }
+}
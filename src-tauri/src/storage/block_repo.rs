@@ -1,16 +1,19 @@
 use super::db::Database;
+use crate::models::rich_text::{self, TextSpan};
 use crate::models::{Block, BlockType};
 use rusqlite::{params, OptionalExtension, Result, Row};
 use uuid::Uuid;
 
 impl Database {
+    // commands go through place_block to keep the order unique, tests still insert rows as is
+    #[cfg(test)]
     pub fn insert_block(&self, block: &Block) -> Result<()> {
         let conn = self.get_connection();
         let block_type_json = serde_json::to_string(&block.block_type).unwrap();
 
         conn.execute(
-            "INSERT INTO blocks (id, page_id, block_type, content, parent_id, order_position, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO blocks (id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             params![
                 block.id.to_string(),
                 block.page_id.to_string(),
@@ -20,6 +23,7 @@ impl Database {
                 block.order,
                 block.created_at.to_rfc3339(),
                 block.updated_at.to_rfc3339(),
+                spans_json(block),
             ],
         )?;
         Ok(())
@@ -28,7 +32,7 @@ impl Database {
     pub fn get_block_by_id(&self, id: &str) -> Result<Option<Block>> {
         let conn = self.get_connection();
         let mut stmt = conn.prepare(
-            "SELECT id, page_id, block_type, content, parent_id, order_position, created_at, updated_at 
+            "SELECT id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans
              FROM blocks WHERE id = ?1"
         )?;
 
@@ -41,9 +45,21 @@ impl Database {
 
     pub fn get_page_blocks(&self, page_id: &str) -> Result<Vec<Block>> {
         let conn = self.get_connection();
+        // order is per parent, so sort by the path of orders from the top: every block comes
+        // right before its children, the way the page shows them
         let mut stmt = conn.prepare(
-            "SELECT id, page_id, block_type, content, parent_id, order_position, created_at, updated_at 
-             FROM blocks WHERE page_id = ?1 ORDER BY order_position ASC"
+            "WITH RECURSIVE tree(id, path) AS (
+                 SELECT id, printf('%010d', order_position) FROM blocks
+                 WHERE page_id = ?1
+                   AND (parent_id IS NULL OR parent_id NOT IN (SELECT id FROM blocks WHERE page_id = ?1))
+                 UNION ALL
+                 SELECT c.id, t.path || '/' || printf('%010d', c.order_position)
+                 FROM blocks c JOIN tree t ON c.parent_id = t.id
+                 WHERE c.page_id = ?1
+             )
+             SELECT b.id, b.page_id, b.block_type, b.content, b.parent_id, b.order_position,
+                    b.created_at, b.updated_at, b.spans
+             FROM blocks b JOIN tree t ON t.id = b.id ORDER BY t.path ASC",
         )?;
 
         let blocks = stmt
@@ -53,35 +69,55 @@ impl Database {
         Ok(blocks)
     }
 
+    // the editor saves plain text, formatting on the parts that didnt change stays
     pub fn update_block_content(&self, id: &str, content: &str) -> Result<()> {
-        let conn = self.get_connection();
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
         let now = chrono::Utc::now().to_rfc3339();
 
-        conn.execute(
-            "UPDATE blocks SET content = ?1, updated_at = ?2 WHERE id = ?3",
-            params![content, now, id],
+        let spans: Option<String> = tx
+            .query_row(
+                "SELECT spans FROM blocks WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        let spans = spans
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .and_then(|spans| rich_text::retext(spans, content))
+            .map(|spans| serde_json::to_string(&spans).unwrap());
+
+        tx.execute(
+            "UPDATE blocks SET content = ?1, spans = ?2, updated_at = ?3 WHERE id = ?4",
+            params![content, spans, now, id],
         )?;
-        Ok(())
+        tx.commit()
     }
 
-    pub fn update_block_order(&self, id: &str, order: i32) -> Result<()> {
+    // the formatted text, content gets its plain projection
+    pub fn update_block_spans(&self, id: &str, spans: Vec<TextSpan>) -> Result<()> {
         let conn = self.get_connection();
         let now = chrono::Utc::now().to_rfc3339();
+        let plain = rich_text::plain_text(&spans);
+        let spans = rich_text::normalize(spans).map(|s| serde_json::to_string(&s).unwrap());
 
         conn.execute(
-            "UPDATE blocks SET order_position = ?1, updated_at = ?2 WHERE id = ?3",
-            params![order, now, id],
+            "UPDATE blocks SET content = ?1, spans = ?2, updated_at = ?3 WHERE id = ?4",
+            params![plain, spans, now, id],
         )?;
         Ok(())
     }
 
+    // commands delete through apply_changes so undo gets the subtree, tests use this directly
+    #[cfg(test)]
     pub fn delete_block(&self, id: &str) -> Result<()> {
         let conn = self.get_connection();
         conn.execute("DELETE FROM blocks WHERE id = ?1", params![id])?;
         Ok(())
     }
 
-    fn row_to_block(&self, row: &Row) -> Result<Block> {
+    pub(super) fn row_to_block(&self, row: &Row) -> Result<Block> {
         let block_type_json: String = row.get(2)?;
         let block_type: BlockType = serde_json::from_str(&block_type_json).unwrap();
 
@@ -100,6 +136,46 @@ impl Database {
             updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
                 .unwrap()
                 .with_timezone(&chrono::Utc),
+            spans: row
+                .get::<_, Option<String>>(8)?
+                .map(|json| serde_json::from_str(&json).unwrap()),
         })
     }
 }
+
+pub(super) fn spans_json(block: &Block) -> Option<String> {
+    block
+        .spans
+        .as_ref()
+        .map(|spans| serde_json::to_string(spans).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Mark, Page};
+
+    #[test]
+    fn editing_content_keeps_the_formatting() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+        let bold = |text: &str| TextSpan {
+            marks: vec![Mark::Bold],
+            ..TextSpan::plain(text)
+        };
+        let block = Block::new(
+            Uuid::parse_str(&page.id).unwrap(),
+            BlockType::Text,
+            String::new(),
+        )
+        .with_spans(vec![TextSpan::plain("a "), bold("b")]);
+        db.insert_block(&block).unwrap();
+
+        db.update_block_content(&block.id.to_string(), "a bold")
+            .unwrap();
+        let saved = db.get_block_by_id(&block.id.to_string()).unwrap().unwrap();
+        assert_eq!(saved.content, "a bold");
+        assert_eq!(saved.spans, Some(vec![TextSpan::plain("a "), bold("bold")]));
+    }
+}
@@ -0,0 +1,158 @@
+use super::db::Database;
+use crate::models::{Block, BlockNode, BlockTreeOptions};
+use rusqlite::Result;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+impl Database {
+    // the blocks of a page as a tree, each level sorted by order. loads the page once and builds
+    // the tree in memory, pages are small enough that filtering by depth in sql wouldnt pay off
+    pub fn get_page_block_tree(
+        &self,
+        page_id: &str,
+        options: &BlockTreeOptions,
+    ) -> Result<Vec<BlockNode>> {
+        let mut children: HashMap<Option<Uuid>, Vec<Block>> = HashMap::new();
+        for block in self.get_page_blocks(page_id)? {
+            children.entry(block.parent_id).or_default().push(block);
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_by_key(|b| (b.order, b.created_at));
+        }
+
+        let root = options
+            .root_id
+            .as_deref()
+            .and_then(|id| Uuid::parse_str(id).ok());
+        let tree = Tree {
+            collapsed: options
+                .collapsed
+                .iter()
+                .filter_map(|id| Uuid::parse_str(id).ok())
+                .collect(),
+            children,
+        };
+        Ok(tree.build(root, options.depth))
+    }
+}
+
+struct Tree {
+    children: HashMap<Option<Uuid>, Vec<Block>>,
+    collapsed: HashSet<Uuid>,
+}
+
+impl Tree {
+    fn build(&self, parent: Option<Uuid>, depth: Option<u32>) -> Vec<BlockNode> {
+        if depth == Some(0) {
+            return Vec::new();
+        }
+        let Some(blocks) = self.children.get(&parent) else {
+            return Vec::new();
+        };
+        blocks
+            .iter()
+            .map(|block| {
+                let child_count = self.children.get(&Some(block.id)).map_or(0, |c| c.len());
+                let children = if self.collapsed.contains(&block.id) {
+                    Vec::new()
+                } else {
+                    self.build(Some(block.id), depth.map(|d| d - 1))
+                };
+                BlockNode {
+                    block: block.clone(),
+                    children,
+                    child_count,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BlockType, Page};
+
+    fn titles(nodes: &[BlockNode]) -> Vec<(String, Vec<String>, usize)> {
+        nodes
+            .iter()
+            .map(|n| {
+                let children = n.children.iter().map(|c| c.block.content.clone()).collect();
+                (n.block.content.clone(), children, n.child_count)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tree_is_ordered_per_parent_with_depth_and_collapsed() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Page".to_string());
+        db.insert_page(&page).unwrap();
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let block = |content: &str| Block::new(page_id, BlockType::Text, content.to_string());
+
+        let first = block("first");
+        let second = block("second");
+        let (a, b) = (block("a"), block("b"));
+        for node in [&first, &second] {
+            db.place_block(node, None).unwrap();
+        }
+        // b placed before a, and a grandchild under b
+        db.place_block(&b.clone().with_parent(first.id), None)
+            .unwrap();
+        db.place_block(&a.clone().with_parent(first.id), Some(0))
+            .unwrap();
+        db.place_block(&block("deep").with_parent(b.id), None)
+            .unwrap();
+
+        let tree = db
+            .get_page_block_tree(&page.id, &BlockTreeOptions::default())
+            .unwrap();
+        assert_eq!(
+            titles(&tree),
+            vec![
+                ("first".into(), vec!["a".into(), "b".into()], 2),
+                ("second".into(), vec![], 0)
+            ]
+        );
+        assert_eq!(
+            titles(&tree[0].children)[1],
+            ("b".into(), vec!["deep".into()], 1)
+        );
+
+        let shallow = db
+            .get_page_block_tree(
+                &page.id,
+                &BlockTreeOptions {
+                    depth: Some(1),
+                    ..BlockTreeOptions::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(shallow[0].children.len(), 0);
+        assert_eq!(shallow[0].child_count, 2);
+
+        let collapsed = db
+            .get_page_block_tree(
+                &page.id,
+                &BlockTreeOptions {
+                    collapsed: vec![b.id.to_string()],
+                    ..BlockTreeOptions::default()
+                },
+            )
+            .unwrap();
+        assert!(collapsed[0].children[1].children.is_empty());
+
+        // loading the collapsed block later
+        let lazy = db
+            .get_page_block_tree(
+                &page.id,
+                &BlockTreeOptions {
+                    root_id: Some(b.id.to_string()),
+                    ..BlockTreeOptions::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(titles(&lazy), vec![("deep".into(), vec![], 0)]);
+    }
+}
@@ -1,4 +1,6 @@
-use rusqlite::{Connection, Result};
+use super::migrations::{self, MigrationError};
+use rusqlite::Connection;
+use std::path::Path;
 use std::sync::Mutex;
 
 pub struct Database {
@@ -6,68 +8,27 @@ pub struct Database {
 }
 
 impl Database {
-    pub fn new(path: &str) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        let db = Database {
-            conn: Mutex::new(conn),
-        };
-        db.init_tables();
-        Ok(db)
+    // opens the db and brings the schema up to date, fails if the file is from a newer bismuth
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, MigrationError> {
+        Ok(Database {
+            conn: Mutex::new(open_connection(path)?),
+        })
     }
-    fn init_tables(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-
-        // pages table :D
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS pages (
-                id TEXT PRIMARY KEY,
-                title TEXT NOT NULL,
-                icon TEXT,
-                cover TEXT,
-                parent_id TEXT,
-                is_archived INTEGER NOT NULL DEFAULT 0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY (parent_id) REFERENCES pages(id) ON DELETE CASCADE           
-            )",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS blocks (
-                id TEXT PRIMARY KEY,
-                page_id TEXT NOT NULL,
-                block_type TEXT NOT NULL,
-                content TEXT NOT NULL,
-                parent_id TEXT,
-                order_position INTEGER NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE,
-                FOREIGN KEY (parent_id) REFERENCES blocks(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // indexes so it can be faster
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_blocks_page_id ON blocks(page_id)",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_blocks_parent_id ON blocks(parent_id)",
-            [],
-        )?;
-
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_pages_parent_id ON pages(parent_id)",
-            [],
-        )?;
 
+    // swaps the underlying connection when switching workspaces so State<Database> stays valid
+    pub fn reopen<P: AsRef<Path>>(&self, path: P) -> Result<(), MigrationError> {
+        let conn = open_connection(path)?;
+        *self.conn.lock().unwrap() = conn;
         Ok(())
     }
-    pub fn get_connection(&self) -> std::sync::MutexGuard<Connection> {
+
+    pub fn get_connection(&self) -> std::sync::MutexGuard<'_, Connection> {
         self.conn.lock().unwrap()
     }
 }
+
+fn open_connection<P: AsRef<Path>>(path: P) -> Result<Connection, MigrationError> {
+    let mut conn = Connection::open(path)?;
+    migrations::run_pending(&mut conn)?;
+    Ok(conn)
+}
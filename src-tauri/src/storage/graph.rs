@@ -0,0 +1,191 @@
+use super::db::Database;
+use crate::models::{EdgeKind, GraphEdge, GraphNode, GraphOptions, PageGraph};
+use rusqlite::{params, Result};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/*
+ * pages as nodes, the parent_id hierarchy and the links table as edges.
+ * everything is loaded in two queries and narrowed down in memory, workspaces are small enough
+ * that walking the graph here is cheaper than a recursive query per hop
+ */
+impl Database {
+    pub fn get_page_graph(&self, options: &GraphOptions) -> Result<PageGraph> {
+        let conn = self.get_connection();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, title, icon, parent_id, is_archived FROM pages
+             WHERE ?1 OR is_archived = 0
+             ORDER BY title COLLATE NOCASE, id",
+        )?;
+        let mut nodes = stmt
+            .query_map(params![options.include_archived], |row| {
+                Ok(GraphNode {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    icon: row.get(2)?,
+                    parent_id: row.get(3)?,
+                    is_archived: row.get::<_, i32>(4)? != 0,
+                    inbound_links: 0,
+                    outbound_links: 0,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        let ids: HashSet<String> = nodes.iter().map(|n| n.id.clone()).collect();
+
+        let mut edges: Vec<GraphEdge> = nodes
+            .iter()
+            .filter_map(|node| {
+                let parent = node.parent_id.as_ref().filter(|p| ids.contains(*p))?;
+                Some(GraphEdge {
+                    source: parent.clone(),
+                    target: node.id.clone(),
+                    kind: EdgeKind::Parent,
+                    weight: 1,
+                })
+            })
+            .collect();
+
+        // links to pages that are gone (or filtered out) just dont make an edge
+        let mut stmt = conn.prepare(
+            "SELECT source_page_id, target_page_id, kind, COUNT(*) FROM links
+             WHERE source_page_id != target_page_id
+             GROUP BY source_page_id, target_page_id, kind",
+        )?;
+        let links = stmt
+            .query_map([], |row| {
+                let kind = match row.get::<_, String>(2)?.as_str() {
+                    "SubPage" => EdgeKind::SubPage,
+                    _ => EdgeKind::PageLink,
+                };
+                Ok(GraphEdge {
+                    source: row.get(0)?,
+                    target: row.get(1)?,
+                    kind,
+                    weight: row.get::<_, i64>(3)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        edges.extend(
+            links
+                .into_iter()
+                .filter(|e| ids.contains(&e.source) && ids.contains(&e.target)),
+        );
+
+        if let Some(center) = &options.center {
+            let keep = neighbourhood(center, options.depth.unwrap_or(1), &edges);
+            nodes.retain(|n| keep.contains(&n.id));
+            edges.retain(|e| keep.contains(&e.source) && keep.contains(&e.target));
+        }
+
+        let mut inbound: HashMap<&str, usize> = HashMap::new();
+        let mut outbound: HashMap<&str, usize> = HashMap::new();
+        for edge in edges.iter().filter(|e| e.kind != EdgeKind::Parent) {
+            *outbound.entry(edge.source.as_str()).or_default() += edge.weight;
+            *inbound.entry(edge.target.as_str()).or_default() += edge.weight;
+        }
+        for node in &mut nodes {
+            node.inbound_links = inbound.get(node.id.as_str()).copied().unwrap_or(0);
+            node.outbound_links = outbound.get(node.id.as_str()).copied().unwrap_or(0);
+        }
+
+        edges.sort_by(|a, b| (&a.source, &a.target, a.kind).cmp(&(&b.source, &b.target, b.kind)));
+        Ok(PageGraph { nodes, edges })
+    }
+}
+
+// pages within `depth` hops of center, following edges both ways
+fn neighbourhood(center: &str, depth: u32, edges: &[GraphEdge]) -> HashSet<String> {
+    let mut adjacent: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        adjacent.entry(&edge.source).or_default().push(&edge.target);
+        adjacent.entry(&edge.target).or_default().push(&edge.source);
+    }
+
+    let mut seen = HashSet::from([center.to_string()]);
+    let mut queue = VecDeque::from([(center, 0)]);
+    while let Some((id, distance)) = queue.pop_front() {
+        if distance == depth {
+            continue;
+        }
+        for next in adjacent.get(id).into_iter().flatten() {
+            if seen.insert(next.to_string()) {
+                queue.push_back((next, distance + 1));
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, BlockType, Page};
+    use uuid::Uuid;
+
+    #[test]
+    fn whole_and_local_graphs() {
+        let db = Database::new(":memory:").unwrap();
+        let a = Page::new("A".to_string());
+        let b = Page::new("B".to_string()).with_parent(a.id.clone());
+        let c = Page::new("C".to_string());
+        let d = Page::new("D".to_string());
+        let mut trashed = Page::new("Trashed".to_string());
+        trashed.is_archived = true;
+        for page in [&a, &b, &c, &d, &trashed] {
+            db.insert_page(page).unwrap();
+        }
+        let uuid = |p: &Page| Uuid::parse_str(&p.id).unwrap();
+
+        // a -> c twice, c -> d, b -> trashed
+        for (from, to) in [(&a, &c), (&a, &c), (&c, &d), (&b, &trashed)] {
+            let block = Block::new(
+                uuid(from),
+                BlockType::PageLink { page_id: uuid(to) },
+                String::new(),
+            );
+            db.insert_block(&block).unwrap();
+        }
+
+        let graph = db.get_page_graph(&GraphOptions::default()).unwrap();
+        let titles: Vec<&str> = graph.nodes.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, vec!["A", "B", "C", "D"]);
+        assert_eq!(graph.edges.len(), 3);
+        assert!(graph.edges.contains(&GraphEdge {
+            source: a.id.clone(),
+            target: b.id.clone(),
+            kind: EdgeKind::Parent,
+            weight: 1,
+        }));
+        assert!(graph.edges.contains(&GraphEdge {
+            source: a.id.clone(),
+            target: c.id.clone(),
+            kind: EdgeKind::PageLink,
+            weight: 2,
+        }));
+        let node_c = graph.nodes.iter().find(|n| n.id == c.id).unwrap();
+        assert_eq!((node_c.inbound_links, node_c.outbound_links), (2, 1));
+
+        let with_archived = db
+            .get_page_graph(&GraphOptions {
+                include_archived: true,
+                ..GraphOptions::default()
+            })
+            .unwrap();
+        assert_eq!(with_archived.nodes.len(), 5);
+        assert_eq!(with_archived.edges.len(), 4);
+
+        // one hop from b is a (its parent), two hops reaches c through a
+        let local = |depth| {
+            let graph = db
+                .get_page_graph(&GraphOptions {
+                    center: Some(b.id.clone()),
+                    depth: Some(depth),
+                    include_archived: false,
+                })
+                .unwrap();
+            graph.nodes.into_iter().map(|n| n.title).collect::<Vec<_>>()
+        };
+        assert_eq!(local(1), vec!["A", "B"]);
+        assert_eq!(local(2), vec!["A", "B", "C"]);
+    }
+}
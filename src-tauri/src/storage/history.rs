@@ -0,0 +1,449 @@
+use super::db::Database;
+use super::undo::{write_block, write_page, Change};
+use crate::models::{PageVersion, PageVersionInfo, VersionKind};
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Error, OptionalExtension, Result, Row};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/*
+ * page history, a version is the page's title/icon/cover plus every block as json
+ * automatic versions are taken right *before* an edit lands and at most once every
+ * AUTO_SNAPSHOT_MINUTES, so each burst of editing gets a restore point from before it started
+ */
+pub const AUTO_SNAPSHOT_MINUTES: i64 = 5;
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+// (max age, keep the newest per bucket of this many seconds) for everything except save points
+// so: all of the last hour, hourly for a day, daily for a month, anything older is dropped
+const RETENTION: &[(i64, Option<i64>)] = &[(HOUR, None), (DAY, Some(HOUR)), (30 * DAY, Some(DAY))];
+
+const VERSION_COLUMNS: &str = "id, page_id, kind, label, title, block_count, created_at";
+
+impl Database {
+    pub fn snapshot_page(
+        &self,
+        page_id: &str,
+        kind: VersionKind,
+        label: Option<&str>,
+    ) -> Result<Option<PageVersionInfo>> {
+        let conn = self.get_connection();
+        self.snapshot_with(&conn, page_id, kind, label)
+    }
+
+    // the debounced one, editing commands call this before they change anything
+    pub fn auto_snapshot(&self, page_id: &str) -> Result<Option<PageVersionInfo>> {
+        let conn = self.get_connection();
+        self.auto_snapshot_with(&conn, page_id)
+    }
+
+    pub(super) fn auto_snapshot_with(
+        &self,
+        conn: &Connection,
+        page_id: &str,
+    ) -> Result<Option<PageVersionInfo>> {
+        let now = Utc::now();
+        let last: Option<String> = conn.query_row(
+            "SELECT MAX(created_at) FROM page_versions WHERE page_id = ?1",
+            params![page_id],
+            |row| row.get(0),
+        )?;
+        if let Some(last) = last.and_then(|d| DateTime::parse_from_rfc3339(&d).ok()) {
+            if now - last.with_timezone(&Utc) < Duration::minutes(AUTO_SNAPSHOT_MINUTES) {
+                return Ok(None);
+            }
+        }
+
+        let version = self.snapshot_with(conn, page_id, VersionKind::Auto, None)?;
+        if version.is_some() {
+            prune_versions(conn, page_id, now)?;
+        }
+        Ok(version)
+    }
+
+    // None when the page doesnt exist, or nothing changed since the last version (save points always go in)
+    pub(super) fn snapshot_with(
+        &self,
+        conn: &Connection,
+        page_id: &str,
+        kind: VersionKind,
+        label: Option<&str>,
+    ) -> Result<Option<PageVersionInfo>> {
+        let page = conn
+            .query_row(
+                "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
+                 FROM pages WHERE id = ?1",
+                params![page_id],
+                |row| self.row_to_page(row),
+            )
+            .optional()?;
+        let Some(page) = page else {
+            return Ok(None);
+        };
+
+        let blocks = {
+            let mut stmt = conn.prepare(
+                "SELECT id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans
+                 FROM blocks WHERE page_id = ?1 ORDER BY order_position ASC",
+            )?;
+            let rows = stmt.query_map(params![page_id], |row| self.row_to_block(row))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        let blocks_json = serde_json::to_string(&blocks).unwrap();
+
+        if kind != VersionKind::SavePoint {
+            let unchanged = conn
+                .query_row(
+                    "SELECT title IS ?2 AND icon IS ?3 AND cover IS ?4 AND blocks = ?5
+                     FROM page_versions WHERE page_id = ?1 ORDER BY created_at DESC LIMIT 1",
+                    params![page_id, page.title, page.icon, page.cover, blocks_json],
+                    |row| row.get::<_, bool>(0),
+                )
+                .optional()?
+                .unwrap_or(false);
+            if unchanged {
+                return Ok(None);
+            }
+        }
+
+        let info = PageVersionInfo {
+            id: Uuid::new_v4().to_string(),
+            page_id: page.id,
+            kind,
+            label: label.map(|l| l.to_string()),
+            title: page.title,
+            block_count: blocks.len() as u32,
+            created_at: Utc::now(),
+        };
+
+        conn.execute(
+            "INSERT INTO page_versions (id, page_id, kind, label, title, icon, cover, blocks, block_count, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                info.id,
+                info.page_id,
+                info.kind.as_str(),
+                info.label,
+                info.title,
+                page.icon,
+                page.cover,
+                blocks_json,
+                info.block_count,
+                info.created_at.to_rfc3339(),
+            ],
+        )?;
+
+        Ok(Some(info))
+    }
+
+    // newest first
+    pub fn list_page_versions(&self, page_id: &str) -> Result<Vec<PageVersionInfo>> {
+        let conn = self.get_connection();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM page_versions WHERE page_id = ?1 ORDER BY created_at DESC",
+            VERSION_COLUMNS
+        ))?;
+
+        let versions = stmt
+            .query_map(params![page_id], row_to_version_info)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(versions)
+    }
+
+    pub fn get_page_version(&self, id: &str) -> Result<Option<PageVersion>> {
+        let conn = self.get_connection();
+        conn.query_row(
+            &format!(
+                "SELECT {}, icon, cover, blocks FROM page_versions WHERE id = ?1",
+                VERSION_COLUMNS
+            ),
+            params![id],
+            |row| {
+                Ok(PageVersion {
+                    info: row_to_version_info(row)?,
+                    icon: row.get(7)?,
+                    cover: row.get(8)?,
+                    blocks: serde_json::from_str(&row.get::<_, String>(9)?)
+                        .map_err(|e| Error::FromSqlConversionFailure(9, Type::Text, Box::new(e)))?,
+                })
+            },
+        )
+        .optional()
+    }
+
+    // puts the page back the way it was in that version, returns the page id and what changed for undo
+    // the current state is saved first so a restore can also be undone by restoring again
+    pub fn restore_page_version(&self, id: &str) -> Result<Option<(String, Vec<Change>)>> {
+        let Some(version) = self.get_page_version(id)? else {
+            return Ok(None);
+        };
+        let page_id = version.info.page_id.as_str();
+
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
+
+        self.snapshot_with(&tx, page_id, VersionKind::BeforeRestore, None)?;
+
+        let page = tx
+            .query_row(
+                "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
+                 FROM pages WHERE id = ?1",
+                params![page_id],
+                |row| self.row_to_page(row),
+            )
+            .optional()?;
+        let Some(page) = page else {
+            return Ok(None);
+        };
+        let mut restored = page.clone();
+        restored.title = version.info.title.clone();
+        restored.icon = version.icon.clone();
+        restored.cover = version.cover.clone();
+        restored.updated_at = Utc::now();
+
+        // everything on the page now, plus blocks from the version that were moved to another
+        // page since, and whatever is nested under those (it gets cascaded away with them)
+        let version_ids: Vec<String> = version.blocks.iter().map(|b| b.id.to_string()).collect();
+        let current = {
+            let mut stmt = tx.prepare(
+                "WITH RECURSIVE doomed(id) AS (
+                     SELECT id FROM blocks WHERE page_id = ?1 OR id IN (SELECT value FROM json_each(?2))
+                     UNION
+                     SELECT c.id FROM blocks c JOIN doomed d ON c.parent_id = d.id
+                 )
+                 SELECT id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans
+                 FROM blocks WHERE id IN (SELECT id FROM doomed) ORDER BY order_position ASC",
+            )?;
+            let rows = stmt.query_map(
+                params![page_id, serde_json::to_string(&version_ids).unwrap()],
+                |row| self.row_to_block(row),
+            )?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        // deletes go first, a delete cascades and would take out blocks that were just written back
+        let mut wanted: HashMap<_, _> = version.blocks.iter().map(|b| (b.id, b)).collect();
+        let mut changes = vec![Change::Page {
+            before: Some(page),
+            after: Some(restored),
+        }];
+        let mut kept = Vec::new();
+        for block in current {
+            match wanted.remove(&block.id) {
+                Some(old) => kept.push(Change::Block {
+                    before: Some(block),
+                    after: Some(old.clone()),
+                }),
+                None => changes.push(Change::Block {
+                    before: Some(block),
+                    after: None,
+                }),
+            }
+        }
+        changes.extend(kept);
+        changes.extend(
+            version
+                .blocks
+                .iter()
+                .filter(|b| wanted.contains_key(&b.id))
+                .map(|b| Change::Block {
+                    before: None,
+                    after: Some(b.clone()),
+                }),
+        );
+
+        // the blocks are stored in order, not parents first, so check parent ids at commit instead
+        tx.pragma_update(None, "defer_foreign_keys", true)?;
+        for change in &changes {
+            match change {
+                Change::Block { before, after } => {
+                    let id = before.as_ref().or(after.as_ref()).unwrap().id.to_string();
+                    write_block(&tx, &id, after.as_ref())?;
+                }
+                Change::Page { after, .. } => write_page(&tx, page_id, after.as_ref())?,
+            }
+        }
+
+        tx.commit()?;
+        Ok(Some((page_id.to_string(), changes)))
+    }
+}
+
+fn row_to_version_info(row: &Row) -> Result<PageVersionInfo> {
+    Ok(PageVersionInfo {
+        id: row.get(0)?,
+        page_id: row.get(1)?,
+        kind: VersionKind::parse(&row.get::<_, String>(2)?),
+        label: row.get(3)?,
+        title: row.get(4)?,
+        block_count: row.get(5)?,
+        created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+            .unwrap()
+            .with_timezone(&Utc),
+    })
+}
+
+fn prune_versions(conn: &Connection, page_id: &str, now: DateTime<Utc>) -> Result<()> {
+    let versions = {
+        let mut stmt = conn.prepare(
+            "SELECT id, created_at FROM page_versions
+             WHERE page_id = ?1 AND kind != 'SavePoint' ORDER BY created_at DESC",
+        )?;
+        let rows = stmt.query_map(params![page_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .map(|(id, created_at)| {
+                let created_at = DateTime::parse_from_rfc3339(&created_at)
+                    .unwrap()
+                    .with_timezone(&Utc);
+                (id, created_at)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    for id in prunable(&versions, now) {
+        conn.execute("DELETE FROM page_versions WHERE id = ?1", params![id])?;
+    }
+    Ok(())
+}
+
+// versions come in newest first, the newest one in each bucket survives
+fn prunable(versions: &[(String, DateTime<Utc>)], now: DateTime<Utc>) -> Vec<String> {
+    let mut kept_buckets = HashSet::new();
+
+    versions
+        .iter()
+        .filter(|(_, created_at)| {
+            let age = (now - *created_at).num_seconds();
+            let Some(tier) = RETENTION.iter().position(|(max_age, _)| age < *max_age) else {
+                return true;
+            };
+            match RETENTION[tier].1 {
+                Some(bucket) => {
+                    !kept_buckets.insert((tier, created_at.timestamp().div_euclid(bucket)))
+                }
+                None => false,
+            }
+        })
+        .map(|(id, _)| id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, BlockType, Page};
+
+    #[test]
+    fn restores_an_old_version_and_keeps_the_current_one() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("draft".to_string());
+        db.insert_page(&page).unwrap();
+        let page_uuid = Uuid::parse_str(&page.id).unwrap();
+        let parent = Block::new(page_uuid, BlockType::BulletList, "parent".to_string());
+        let child = Block::new(page_uuid, BlockType::Text, "child".to_string())
+            .with_parent(parent.id)
+            .with_order(1);
+        db.insert_block(&parent).unwrap();
+        db.insert_block(&child).unwrap();
+
+        let first = db.auto_snapshot(&page.id).unwrap().unwrap();
+        assert_eq!(first.block_count, 2);
+        // debounced, and nothing changed anyway
+        assert!(db.auto_snapshot(&page.id).unwrap().is_none());
+
+        db.update_page_title(&page.id, "oops").unwrap();
+        db.delete_block(&parent.id.to_string()).unwrap();
+        assert!(db.get_page_blocks(&page.id).unwrap().is_empty());
+
+        db.restore_page_version(&first.id).unwrap().unwrap();
+        assert_eq!(db.get_page(&page.id).unwrap().unwrap().title, "draft");
+        let blocks = db.get_page_blocks(&page.id).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].parent_id, Some(parent.id));
+
+        let versions = db.list_page_versions(&page.id).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].kind, VersionKind::BeforeRestore);
+        assert_eq!(versions[0].title, "oops");
+        assert_eq!(versions[0].block_count, 0);
+    }
+
+    #[test]
+    fn restores_blocks_that_moved_to_another_page() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("draft".to_string());
+        let other = Page::new("other".to_string());
+        db.insert_page(&page).unwrap();
+        db.insert_page(&other).unwrap();
+        let page_uuid = Uuid::parse_str(&page.id).unwrap();
+        let parent = Block::new(page_uuid, BlockType::BulletList, "parent".to_string());
+        let child = Block::new(page_uuid, BlockType::Text, "child".to_string())
+            .with_parent(parent.id)
+            .with_order(1);
+        db.insert_block(&parent).unwrap();
+        db.insert_block(&child).unwrap();
+        let first = db.auto_snapshot(&page.id).unwrap().unwrap();
+
+        // the parent (and its child) move over, and get a new child there
+        let mut moved = parent.clone();
+        moved.page_id = Uuid::parse_str(&other.id).unwrap();
+        let mut moved_child = child.clone();
+        moved_child.page_id = moved.page_id;
+        let newcomer = Block::new(moved.page_id, BlockType::Text, "new".to_string())
+            .with_parent(parent.id)
+            .with_order(2);
+        db.apply_changes(
+            &[
+                Change::Block {
+                    before: Some(parent.clone()),
+                    after: Some(moved),
+                },
+                Change::Block {
+                    before: Some(child.clone()),
+                    after: Some(moved_child),
+                },
+                Change::Block {
+                    before: None,
+                    after: Some(newcomer),
+                },
+            ],
+            false,
+        )
+        .unwrap();
+
+        let (_, changes) = db.restore_page_version(&first.id).unwrap().unwrap();
+        assert_eq!(db.get_page_blocks(&page.id).unwrap().len(), 2);
+        assert!(db.get_page_blocks(&other.id).unwrap().is_empty());
+
+        db.apply_changes(&changes, true).unwrap();
+        assert!(db.get_page_blocks(&page.id).unwrap().is_empty());
+        assert_eq!(db.get_page_blocks(&other.id).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn pruning_thins_out_older_versions() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let now = at("2026-01-10T12:30:00Z");
+        let versions: Vec<(String, DateTime<Utc>)> = [
+            ("a", "2026-01-10T12:20:00Z"), // last hour, all kept
+            ("b", "2026-01-10T11:40:00Z"),
+            ("c", "2026-01-10T10:50:00Z"), // hourly
+            ("d", "2026-01-10T10:10:00Z"),
+            ("e", "2026-01-05T18:00:00Z"), // daily
+            ("f", "2026-01-05T09:00:00Z"),
+            ("g", "2025-11-01T09:00:00Z"), // too old
+        ]
+        .iter()
+        .map(|(id, created_at)| (id.to_string(), at(created_at)))
+        .collect();
+
+        assert_eq!(prunable(&versions, now), vec!["d", "f", "g"]);
+    }
+}
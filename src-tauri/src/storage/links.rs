@@ -0,0 +1,446 @@
+use super::db::Database;
+use crate::models::{BacklinkGroup, Backlinks, LinkKind, LinkMention, LinkReference, Page};
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension, Params, Result};
+
+/*
+ * the links table is filled by triggers on blocks (see migration 5), so every way a block gets
+ * written (editor, undo, history restore, imports) keeps it up to date without doing anything here
+ */
+const SNIPPET_CHARS: usize = 60;
+const CONTEXT_CHARS: usize = 120;
+
+const PAGE_COLUMNS: &str =
+    "p.id, p.title, p.icon, p.cover, p.parent_id, p.is_archived, p.created_at, p.updated_at, p.archived_at";
+
+impl Database {
+    // pages in the trash dont show up as sources
+    pub fn get_backlinks(&self, page_id: &str) -> Result<Backlinks> {
+        let conn = self.get_connection();
+        let Some(title) = conn
+            .query_row(
+                "SELECT title FROM pages WHERE id = ?1",
+                params![page_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?
+        else {
+            return Ok(Backlinks::default());
+        };
+
+        Ok(Backlinks {
+            linked: self.linked(&conn, page_id)?,
+            unlinked: self.unlinked(&conn, page_id, &title)?,
+        })
+    }
+
+    fn linked(&self, conn: &Connection, page_id: &str) -> Result<Vec<BacklinkGroup>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, b.id, l.kind, b.content, b.parent_id, b.order_position
+             FROM links l
+             JOIN blocks b ON b.id = l.block_id
+             JOIN pages p ON p.id = l.source_page_id
+             WHERE l.target_page_id = ?1 AND p.is_archived = 0
+             ORDER BY p.title COLLATE NOCASE, p.id, b.order_position",
+            PAGE_COLUMNS
+        ))?;
+        let rows = stmt
+            .query_map(params![page_id], |row| {
+                Ok((
+                    self.row_to_page(row)?,
+                    row.get::<_, String>(9)?,
+                    link_kind(&row.get::<_, String>(10)?),
+                    row.get::<_, String>(11)?,
+                    row.get::<_, Option<String>>(12)?,
+                    row.get::<_, i32>(13)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut groups = Vec::new();
+        for (page, block_id, kind, content, parent_id, order) in rows {
+            let context = link_context(conn, &page.id, parent_id.as_deref(), order)?;
+            let mention = LinkMention {
+                block_id,
+                kind,
+                snippet: content.trim().to_string(),
+                context,
+            };
+            push_mention(&mut groups, page, mention);
+        }
+        Ok(groups)
+    }
+
+    fn unlinked(
+        &self,
+        conn: &Connection,
+        page_id: &str,
+        title: &str,
+    ) -> Result<Vec<BacklinkGroup>> {
+        let title = title.trim();
+        // every new page starts out as "Untitled", matching that would list half the workspace
+        if title.is_empty() || title.eq_ignore_ascii_case("untitled") {
+            return Ok(Vec::new());
+        }
+        // matched here rather than with LIKE, which only folds case for ascii ("Über" vs "über")
+        // and can't tell "Rust" from "trustworthy" anyway
+        let Ok(word) = Regex::new(&format!(
+            r"(?i)(?:^|[^\w])({})(?:[^\w]|$)",
+            regex::escape(title)
+        )) else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, b.id, b.content
+             FROM blocks b
+             JOIN pages p ON p.id = b.page_id
+             WHERE b.page_id != ?1 AND p.is_archived = 0
+               AND NOT EXISTS (SELECT 1 FROM links l WHERE l.block_id = b.id AND l.target_page_id = ?1)
+             ORDER BY p.title COLLATE NOCASE, p.id, b.order_position",
+            PAGE_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![page_id], |row| {
+            Ok((
+                self.row_to_page(row)?,
+                row.get::<_, String>(9)?,
+                row.get::<_, String>(10)?,
+            ))
+        })?;
+
+        // every block is a candidate, rows are checked as they come instead of collected first
+        let mut groups = Vec::new();
+        for row in rows {
+            let (page, block_id, content) = row?;
+            let Some(found) = word.captures(&content).and_then(|c| c.get(1)) else {
+                continue;
+            };
+            let mention = LinkMention {
+                block_id,
+                kind: LinkKind::Mention,
+                snippet: snippet(&content, found.start(), found.end()),
+                context: None,
+            };
+            push_mention(&mut groups, page, mention);
+        }
+        Ok(groups)
+    }
+
+    // links from outside the page and its subpages into them, what deleting it would break
+    // the parent's own SubPage block for it isnt one, it gets deleted along with the page
+    pub fn inbound_links(&self, page_id: &str) -> Result<Vec<LinkReference>> {
+        let conn = self.get_connection();
+        let sql = format!(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT id FROM pages WHERE id = ?1
+                 UNION
+                 SELECT c.id FROM pages c JOIN subtree s ON c.parent_id = s.id
+             )
+             SELECT {}, b.id, l.target_page_id, l.kind, b.content
+             FROM links l
+             JOIN blocks b ON b.id = l.block_id
+             JOIN pages p ON p.id = l.source_page_id
+             WHERE l.target_page_id IN (SELECT id FROM subtree)
+               AND l.source_page_id NOT IN (SELECT id FROM subtree)
+               AND NOT (l.kind = 'SubPage' AND l.target_page_id = ?1
+                        AND l.source_page_id IS (SELECT parent_id FROM pages WHERE id = ?1))
+             ORDER BY p.title COLLATE NOCASE, p.id, b.order_position",
+            PAGE_COLUMNS
+        );
+        self.link_references(&conn, &sql, params![page_id])
+    }
+
+    // the SubPage blocks in the parent page that show this page
+    pub fn subpage_blocks(&self, page_id: &str) -> Result<Vec<String>> {
+        let conn = self.get_connection();
+        let mut stmt = conn.prepare(
+            "SELECT l.block_id FROM links l
+             WHERE l.kind = 'SubPage' AND l.target_page_id = ?1
+               AND l.source_page_id IS (SELECT parent_id FROM pages WHERE id = ?1)",
+        )?;
+        let ids = stmt
+            .query_map(params![page_id], |row| row.get(0))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(ids)
+    }
+
+    // every PageLink/SubPage whose page doesnt exist anymore, trashed sources included
+    pub fn find_broken_links(&self) -> Result<Vec<LinkReference>> {
+        let conn = self.get_connection();
+        let sql = format!(
+            "SELECT {}, b.id, l.target_page_id, l.kind, b.content
+             FROM links l
+             JOIN blocks b ON b.id = l.block_id
+             JOIN pages p ON p.id = l.source_page_id
+             WHERE NOT EXISTS (SELECT 1 FROM pages t WHERE t.id = l.target_page_id)
+             ORDER BY p.title COLLATE NOCASE, p.id, b.order_position",
+            PAGE_COLUMNS
+        );
+        self.link_references(&conn, &sql, [])
+    }
+
+    fn link_references(
+        &self,
+        conn: &Connection,
+        sql: &str,
+        params: impl Params,
+    ) -> Result<Vec<LinkReference>> {
+        let mut stmt = conn.prepare(sql)?;
+        let links = stmt
+            .query_map(params, |row| {
+                Ok(LinkReference {
+                    page: self.row_to_page(row)?,
+                    block_id: row.get(9)?,
+                    target_page_id: row.get(10)?,
+                    kind: link_kind(&row.get::<_, String>(11)?),
+                    content: row.get(12)?,
+                })
+            })?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(links)
+    }
+}
+
+// the kind column holds the BlockType tag
+fn link_kind(kind: &str) -> LinkKind {
+    match kind {
+        "SubPage" => LinkKind::SubPage,
+        _ => LinkKind::PageLink,
+    }
+}
+
+// rows come sorted by page so a new group starts whenever the page changes
+fn push_mention(groups: &mut Vec<BacklinkGroup>, page: Page, mention: LinkMention) {
+    match groups.last_mut() {
+        Some(group) if group.page.id == page.id => group.mentions.push(mention),
+        _ => groups.push(BacklinkGroup {
+            page,
+            mentions: vec![mention],
+        }),
+    }
+}
+
+// the block a link is nested under, or the one right before it
+fn link_context(
+    conn: &Connection,
+    page_id: &str,
+    parent_id: Option<&str>,
+    order: i32,
+) -> Result<Option<String>> {
+    let content = match parent_id {
+        Some(parent_id) => conn
+            .query_row(
+                "SELECT content FROM blocks WHERE id = ?1",
+                params![parent_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?,
+        None => conn
+            .query_row(
+                "SELECT content FROM blocks
+                 WHERE page_id = ?1 AND parent_id IS NULL AND order_position < ?2
+                 ORDER BY order_position DESC LIMIT 1",
+                params![page_id, order],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?,
+    };
+    Ok(content
+        .map(|c| one_line(&c))
+        .filter(|c| !c.is_empty())
+        .map(|c| truncate(&c, CONTEXT_CHARS)))
+}
+
+// "...text before the title and after..." on one line
+fn snippet(content: &str, start: usize, end: usize) -> String {
+    let before: Vec<char> = content[..start].chars().collect();
+    let after: Vec<char> = content[end..].chars().collect();
+    let cut_before = before.len() > SNIPPET_CHARS;
+    let cut_after = after.len() > SNIPPET_CHARS;
+
+    let mut out = String::new();
+    if cut_before {
+        out.push('…');
+    }
+    out.extend(&before[before.len().saturating_sub(SNIPPET_CHARS)..]);
+    out.push_str(&content[start..end]);
+    out.extend(&after[..after.len().min(SNIPPET_CHARS)]);
+    if cut_after {
+        out.push('…');
+    }
+    one_line(&out)
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    format!("{}…", cut.trim_end())
+}
+
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, BlockType};
+    use uuid::Uuid;
+
+    #[test]
+    fn unlinked_mentions_ignore_case_outside_ascii() {
+        let db = Database::new(":memory:").unwrap();
+        let target = Page::new("Über".to_string());
+        let other = Page::new("Other".to_string());
+        db.insert_page(&target).unwrap();
+        db.insert_page(&other).unwrap();
+        let other_id = Uuid::parse_str(&other.id).unwrap();
+        let mention = Block::new(other_id, BlockType::Text, "notes on über alles".to_string());
+        db.insert_block(&mention).unwrap();
+
+        let backlinks = db.get_backlinks(&target.id).unwrap();
+        assert_eq!(backlinks.unlinked.len(), 1);
+        assert_eq!(
+            backlinks.unlinked[0].mentions[0].block_id,
+            mention.id.to_string()
+        );
+    }
+
+    #[test]
+    fn linked_and_unlinked_backlinks() {
+        let db = Database::new(":memory:").unwrap();
+        let target = Page::new("Rust".to_string());
+        let notes = Page::new("Notes".to_string());
+        let other = Page::new("Other".to_string());
+        for page in [&target, &notes, &other] {
+            db.insert_page(page).unwrap();
+        }
+        let target_id = Uuid::parse_str(&target.id).unwrap();
+        let notes_id = Uuid::parse_str(&notes.id).unwrap();
+        let other_id = Uuid::parse_str(&other.id).unwrap();
+
+        let intro = Block::new(notes_id, BlockType::Text, "reading about".to_string());
+        let link = Block::new(
+            notes_id,
+            BlockType::PageLink { page_id: target_id },
+            "Rust".to_string(),
+        )
+        .with_order(1);
+        let mention = Block::new(
+            other_id,
+            BlockType::Text,
+            "we should write this in rust, it is trustworthy".to_string(),
+        );
+        let not_a_mention =
+            Block::new(other_id, BlockType::Text, "trustworthy".to_string()).with_order(1);
+        for block in [&intro, &link, &mention, &not_a_mention] {
+            db.insert_block(block).unwrap();
+        }
+
+        let backlinks = db.get_backlinks(&target.id).unwrap();
+        assert_eq!(backlinks.linked.len(), 1);
+        assert_eq!(backlinks.linked[0].page.id, notes.id);
+        assert_eq!(
+            backlinks.linked[0].mentions,
+            vec![LinkMention {
+                block_id: link.id.to_string(),
+                kind: LinkKind::PageLink,
+                snippet: "Rust".to_string(),
+                context: Some("reading about".to_string()),
+            }]
+        );
+        assert_eq!(backlinks.unlinked.len(), 1);
+        assert_eq!(backlinks.unlinked[0].page.id, other.id);
+        assert_eq!(
+            backlinks.unlinked[0].mentions[0].block_id,
+            mention.id.to_string()
+        );
+        assert_eq!(
+            backlinks.unlinked[0].mentions[0].snippet,
+            "we should write this in rust, it is trustworthy"
+        );
+
+        // the triggers follow type changes and deletes
+        {
+            let conn = db.get_connection();
+            conn.execute(
+                "UPDATE blocks SET block_type = ?1 WHERE id = ?2",
+                params![
+                    serde_json::to_string(&BlockType::SubPage { page_id: target_id }).unwrap(),
+                    link.id.to_string()
+                ],
+            )
+            .unwrap();
+        }
+        let backlinks = db.get_backlinks(&target.id).unwrap();
+        assert_eq!(backlinks.linked[0].mentions[0].kind, LinkKind::SubPage);
+
+        db.delete_block(&link.id.to_string()).unwrap();
+        assert!(db.get_backlinks(&target.id).unwrap().linked.is_empty());
+    }
+
+    #[test]
+    fn snippets_are_cut_around_the_title() {
+        let content = format!("{} Rust {}", "a".repeat(100), "b".repeat(100));
+        let start = content.find("Rust").unwrap();
+        let cut = snippet(&content, start, start + 4);
+        assert_eq!(cut, format!("…{} Rust {}…", "a".repeat(59), "b".repeat(59)));
+    }
+
+    #[test]
+    fn inbound_and_broken_links() {
+        let db = Database::new(":memory:").unwrap();
+        let home = Page::new("Home".to_string());
+        let doomed = Page::new("Doomed".to_string()).with_parent(home.id.clone());
+        let child = Page::new("Child".to_string()).with_parent(doomed.id.clone());
+        let source = Page::new("Source".to_string());
+        for page in [&home, &doomed, &child, &source] {
+            db.insert_page(page).unwrap();
+        }
+        let doomed_id = Uuid::parse_str(&doomed.id).unwrap();
+        let child_id = Uuid::parse_str(&child.id).unwrap();
+        let source_id = Uuid::parse_str(&source.id).unwrap();
+
+        // the subpage block inside doomed doesnt count, it goes away with it
+        let internal = Block::new(
+            doomed_id,
+            BlockType::SubPage { page_id: child_id },
+            "Child".to_string(),
+        );
+        let outside = Block::new(
+            source_id,
+            BlockType::PageLink { page_id: child_id },
+            "see child".to_string(),
+        );
+        // neither does the one showing doomed in its parent
+        let shown = Block::new(
+            Uuid::parse_str(&home.id).unwrap(),
+            BlockType::SubPage { page_id: doomed_id },
+            "Doomed".to_string(),
+        );
+        db.insert_block(&internal).unwrap();
+        db.insert_block(&outside).unwrap();
+        db.insert_block(&shown).unwrap();
+        assert_eq!(
+            db.subpage_blocks(&doomed.id).unwrap(),
+            vec![shown.id.to_string()]
+        );
+
+        let inbound = db.inbound_links(&doomed.id).unwrap();
+        assert_eq!(inbound.len(), 1);
+        assert_eq!(inbound[0].block_id, outside.id.to_string());
+        assert_eq!(inbound[0].page.id, source.id);
+        assert_eq!(inbound[0].target_page_id, child.id);
+        assert!(db.find_broken_links().unwrap().is_empty());
+
+        // the delete_page command takes the shown block with it
+        db.delete_block(&shown.id.to_string()).unwrap();
+        db.delete_page(&doomed.id).unwrap();
+        let broken = db.find_broken_links().unwrap();
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].block_id, outside.id.to_string());
+        assert_eq!(broken[0].kind, LinkKind::PageLink);
+        assert_eq!(broken[0].content, "see child");
+    }
+}
@@ -0,0 +1,448 @@
+use crate::models::{SearchError, SearchMode};
+use regex::{Regex, RegexBuilder};
+
+/*
+ * all the "does this text match" logic for search and replace lives here
+ * Literal is the original substring search, Regex uses the regex crate and
+ * Fuzzy compares words with a typo budget so half remembered queries still hit
+ */
+pub enum Matcher {
+    Literal {
+        query: String,
+        case_sensitive: bool,
+        whole_word: bool,
+    },
+    Regex(Regex),
+    Fuzzy {
+        terms: Vec<String>,
+        case_sensitive: bool,
+    },
+}
+
+// a single hit, byte offsets into the original content
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub start: usize,
+    pub end: usize,
+    pub score: f64,
+}
+
+impl Matcher {
+    pub fn new(
+        query: &str,
+        mode: SearchMode,
+        case_sensitive: bool,
+        whole_word: bool,
+    ) -> Result<Self, SearchError> {
+        match mode {
+            SearchMode::Literal => Ok(Matcher::Literal {
+                query: query.to_string(),
+                case_sensitive,
+                whole_word,
+            }),
+            SearchMode::Regex => {
+                let pattern = if whole_word {
+                    format!(r"\b(?:{})\b", query)
+                } else {
+                    query.to_string()
+                };
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(!case_sensitive)
+                    .build()
+                    .map(Matcher::Regex)
+                    .map_err(|e| SearchError::InvalidRegex {
+                        message: e.to_string(),
+                    })
+            }
+            SearchMode::Fuzzy => Ok(Matcher::Fuzzy {
+                terms: query
+                    .split_whitespace()
+                    .map(|t| fold(t, case_sensitive))
+                    .collect(),
+                case_sensitive,
+            }),
+        }
+    }
+
+    // fts5 MATCH expression for the trigram index, only literals of 3+ chars can use it
+    // the query is wrapped in a phrase so operators and quotes in it are treated as text
+    pub fn index_query(&self) -> Option<String> {
+        match self {
+            Matcher::Literal { query, .. } if query.chars().count() >= 3 => {
+                Some(format!("\"{}\"", query.replace('"', "\"\"")))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn find(&self, content: &str) -> Vec<Hit> {
+        match self {
+            Matcher::Literal {
+                query,
+                case_sensitive,
+                whole_word,
+            } => find_all_matches(content, query, *case_sensitive, *whole_word)
+                .into_iter()
+                .map(|(start, end)| Hit {
+                    start,
+                    end,
+                    score: 1.0,
+                })
+                .collect(),
+            Matcher::Regex(regex) => regex
+                .find_iter(content)
+                .filter(|m| !m.is_empty())
+                .map(|m| Hit {
+                    start: m.start(),
+                    end: m.end(),
+                    score: 1.0,
+                })
+                .collect(),
+            Matcher::Fuzzy {
+                terms,
+                case_sensitive,
+            } => find_fuzzy(content, terms, *case_sensitive),
+        }
+    }
+
+    // regex replacements can use $1 / ${name} to refer to capture groups
+    pub fn replace(
+        &self,
+        content: &str,
+        replacement: &str,
+    ) -> Result<(String, usize), SearchError> {
+        match self {
+            // empty matches ("x*" between letters) arent hits, so they arent replaced either
+            Matcher::Regex(regex) => {
+                let mut result = String::new();
+                let mut last = 0;
+                let mut count = 0;
+
+                for caps in regex.captures_iter(content) {
+                    let m = caps.get(0).unwrap();
+                    if m.is_empty() {
+                        continue;
+                    }
+                    result.push_str(&content[last..m.start()]);
+                    caps.expand(replacement, &mut result);
+                    last = m.end();
+                    count += 1;
+                }
+                result.push_str(&content[last..]);
+
+                Ok((result, count))
+            }
+            Matcher::Literal { .. } => {
+                let mut result = String::new();
+                let mut last = 0;
+                let mut count = 0;
+
+                // overlapping matches ("aa" in "aaa") only count once
+                for hit in self.find(content) {
+                    if hit.start < last {
+                        continue;
+                    }
+                    result.push_str(&content[last..hit.start]);
+                    result.push_str(replacement);
+                    last = hit.end;
+                    count += 1;
+                }
+                result.push_str(&content[last..]);
+
+                Ok((result, count))
+            }
+            Matcher::Fuzzy { .. } => Err(SearchError::UnsupportedMode {
+                mode: SearchMode::Fuzzy,
+                message: "fuzzy search cant be used to replace text".to_string(),
+            }),
+        }
+    }
+}
+
+pub fn find_all_matches(
+    content: &str,
+    query: &str,
+    case_sensitive: bool,
+    whole_word: bool,
+) -> Vec<(usize, usize)> {
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let folded = FoldedText::new(content, case_sensitive);
+    let search_query = fold(query, case_sensitive);
+
+    let mut matches: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+
+    while let Some(pos) = folded.text[start..].find(&search_query) {
+        let abs_pos = start + pos;
+        let (orig_start, orig_end) = folded.original_range(abs_pos, abs_pos + search_query.len());
+
+        let is_word = !whole_word || {
+            let before = content[..orig_start].chars().next_back();
+            let after = content[orig_end..].chars().next();
+            !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+        };
+
+        // a query can land inside a char that folded to several (İ -> i̇), thats still one match
+        if is_word && matches.last() != Some(&(orig_start, orig_end)) {
+            matches.push((orig_start, orig_end));
+        }
+
+        start = abs_pos
+            + folded.text[abs_pos..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8);
+    }
+
+    matches
+}
+
+/*
+ * lowercasing can change byte lengths (İ -> i̇, ß -> ss) so offsets found in the folded
+ * text cant be used on the original directly, this keeps a map back for every folded byte
+ */
+struct FoldedText {
+    text: String,
+    starts: Vec<usize>, // folded byte -> start of the original char it came from
+    ends: Vec<usize>,   // folded byte -> end of the original char it came from
+}
+
+impl FoldedText {
+    fn new(content: &str, case_sensitive: bool) -> Self {
+        let mut text = String::with_capacity(content.len());
+        let mut starts = Vec::with_capacity(content.len());
+        let mut ends = Vec::with_capacity(content.len());
+
+        for (idx, c) in content.char_indices() {
+            let before = text.len();
+            if case_sensitive {
+                text.push(c);
+            } else {
+                fold_char(c, &mut text);
+            }
+            let added = text.len() - before;
+            starts.extend(std::iter::repeat_n(idx, added));
+            ends.extend(std::iter::repeat_n(idx + c.len_utf8(), added));
+        }
+
+        FoldedText { text, starts, ends }
+    }
+
+    // folded byte range -> byte range in the original, widened to whole chars
+    fn original_range(&self, start: usize, end: usize) -> (usize, usize) {
+        (self.starts[start], self.ends[end - 1])
+    }
+}
+
+// same equivalences the fts5 trigram tokenizer uses, otherwise the index would hide matches
+// (so no ß == ss, that would need full case folding on the sqlite side too)
+fn fold_char(c: char, out: &mut String) {
+    match c {
+        'ς' => out.push('σ'),
+        _ => out.extend(c.to_lowercase()),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// every query term has to land somewhere in the content, otherwise the block is not a hit
+fn find_fuzzy(content: &str, terms: &[String], case_sensitive: bool) -> Vec<Hit> {
+    if terms.is_empty() {
+        return vec![];
+    }
+
+    let words = split_words(content);
+    let mut hits = Vec::new();
+
+    for term in terms {
+        let budget = typo_budget(term);
+        let mut found = false;
+
+        for &(start, end) in &words {
+            let word = fold(&content[start..end], case_sensitive);
+            if let Some(score) = fuzzy_score(term, &word, budget) {
+                found = true;
+                hits.push(Hit { start, end, score });
+            }
+        }
+
+        if !found {
+            return vec![];
+        }
+    }
+
+    hits.sort_by_key(|h| h.start);
+    hits.dedup_by(|b, a| {
+        // same word matched by two terms, keep the better score
+        if a.start == b.start {
+            a.score = a.score.max(b.score);
+            true
+        } else {
+            false
+        }
+    });
+    hits
+}
+
+// None when the word is too far away, otherwise 0..=1 with 1 being exact
+fn fuzzy_score(term: &str, word: &str, budget: usize) -> Option<f64> {
+    let term_len = term.chars().count();
+    let full = edit_distance(term, word);
+
+    // "meet" should still find "meeting", compare against the start of longer words
+    let prefix: String = word.chars().take(term_len).collect();
+    let partial = if word.chars().count() > term_len {
+        Some(edit_distance(term, &prefix))
+    } else {
+        None
+    };
+
+    let mut best: Option<f64> = None;
+    if full <= budget {
+        best = Some(1.0 - full as f64 / (term_len as f64 + 1.0));
+    }
+    if let Some(distance) = partial.filter(|&d| d <= budget && term_len >= 3) {
+        let score = 0.9 * (1.0 - distance as f64 / (term_len as f64 + 1.0));
+        best = Some(best.map_or(score, |b: f64| b.max(score)));
+    }
+    best
+}
+
+// short words must be exact, longer ones get more typos
+fn typo_budget(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=6 => 1,
+        _ => 2,
+    }
+}
+
+// optimal string alignment distance, so a swapped pair of letters counts as one typo
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+fn split_words(content: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (idx, c) in content.char_indices() {
+        match (is_word_char(c), start) {
+            (true, None) => start = Some(idx),
+            (false, Some(s)) => {
+                words.push((s, idx));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, content.len()));
+    }
+
+    words
+}
+
+fn fold(text: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        return text.to_string();
+    }
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        fold_char(c, &mut folded);
+    }
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_regex_is_an_error_not_a_panic() {
+        match Matcher::new("(unclosed", SearchMode::Regex, false, false) {
+            Err(SearchError::InvalidRegex { .. }) => {}
+            _ => panic!("expected InvalidRegex"),
+        }
+    }
+
+    #[test]
+    fn regex_replace_expands_captures() {
+        let matcher = Matcher::new(r"(\w+)@(\w+)", SearchMode::Regex, false, false).unwrap();
+        let (after, count) = matcher.replace("mail cid@home now", "$2 at $1").unwrap();
+        assert_eq!(after, "mail home at cid now");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn regex_replace_skips_empty_matches() {
+        let matcher = Matcher::new("x*", SearchMode::Regex, false, false).unwrap();
+        assert_eq!(matcher.replace("xab", "Y").unwrap(), ("Yab".to_string(), 1));
+        assert_eq!(matcher.replace("ab", "Y").unwrap(), ("ab".to_string(), 0));
+    }
+
+    #[test]
+    fn fuzzy_tolerates_typos_and_prefixes() {
+        let matcher = Matcher::new("recieve meet", SearchMode::Fuzzy, false, false).unwrap();
+        let hits = matcher.find("Receive notes from the meeting");
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|h| h.score > 0.5 && h.score < 1.0));
+
+        // every term has to be somewhere
+        assert!(matcher.find("receive notes").is_empty());
+    }
+
+    #[test]
+    fn offsets_stay_on_the_original_text() {
+        let content = "GROẞE Grüße aus İstanbul 🎉 café ΣΟΦΟΣ";
+        for (query, expected) in [
+            ("große", "GROẞE"),
+            ("GRÜSSE", ""),
+            ("İSTANBUL", "İstanbul"),
+            ("CAFÉ", "café"),
+            ("σοφος", "ΣΟΦΟΣ"),
+        ] {
+            if expected.is_empty() {
+                assert!(find_all_matches(content, query, false, true).is_empty());
+                continue;
+            }
+            let matches = find_all_matches(content, query, false, true);
+            assert_eq!(matches.len(), 1, "{}", query);
+            let (start, end) = matches[0];
+            assert_eq!(&content[start..end], expected);
+        }
+
+        // İ lowercases to two chars, a match on either half is the whole İ
+        assert_eq!(find_all_matches("İ", "i", false, false), vec![(0, 2)]);
+        // no ascii only word check anymore
+        assert!(find_all_matches("übung", "bung", false, true).is_empty());
+    }
+}
@@ -0,0 +1,370 @@
+use rusqlite::Connection;
+use std::fmt;
+
+/*
+ * schema changes live here now instead of init_tables
+ * the version is kept in sqlite's PRAGMA user_version so we dont need an extra table
+ * RULES: never edit a migration that already shipped, just add a new one at the end
+ */
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: &'static str,
+    pub down: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "pages and blocks tables",
+        // IF NOT EXISTS so databases created before migrations existed (user_version 0) still upgrade
+        up: "
+        CREATE TABLE IF NOT EXISTS pages (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            icon TEXT,
+            cover TEXT,
+            parent_id TEXT,
+            is_archived INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (parent_id) REFERENCES pages(id) ON DELETE CASCADE
+        );
+        CREATE TABLE IF NOT EXISTS blocks (
+            id TEXT PRIMARY KEY,
+            page_id TEXT NOT NULL,
+            block_type TEXT NOT NULL,
+            content TEXT NOT NULL,
+            parent_id TEXT,
+            order_position INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE,
+            FOREIGN KEY (parent_id) REFERENCES blocks(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_blocks_page_id ON blocks(page_id);
+        CREATE INDEX IF NOT EXISTS idx_blocks_parent_id ON blocks(parent_id);
+        CREATE INDEX IF NOT EXISTS idx_pages_parent_id ON pages(parent_id);
+        ",
+        down: "
+        DROP INDEX IF EXISTS idx_pages_parent_id;
+        DROP INDEX IF EXISTS idx_blocks_parent_id;
+        DROP INDEX IF EXISTS idx_blocks_page_id;
+        DROP TABLE IF EXISTS blocks;
+        DROP TABLE IF EXISTS pages;
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "fts5 search index over block content and page titles",
+        // external content tables keyed by rowid, the triggers keep them in sync with the real tables
+        // (VACUUM can renumber rowids so run a 'rebuild' after one)
+        // trigram tokenizer so MATCH still behaves like the old substring search
+        up: "
+        CREATE VIRTUAL TABLE blocks_fts USING fts5(
+            content,
+            content = 'blocks',
+            content_rowid = 'rowid',
+            tokenize = 'trigram'
+        );
+        CREATE VIRTUAL TABLE pages_fts USING fts5(
+            title,
+            content = 'pages',
+            content_rowid = 'rowid',
+            tokenize = 'trigram'
+        );
+        INSERT INTO blocks_fts(blocks_fts) VALUES ('rebuild');
+        INSERT INTO pages_fts(pages_fts) VALUES ('rebuild');
+
+        CREATE TRIGGER blocks_fts_insert AFTER INSERT ON blocks BEGIN
+            INSERT INTO blocks_fts(rowid, content) VALUES (new.rowid, new.content);
+        END;
+        CREATE TRIGGER blocks_fts_delete AFTER DELETE ON blocks BEGIN
+            INSERT INTO blocks_fts(blocks_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+        END;
+        CREATE TRIGGER blocks_fts_update AFTER UPDATE OF content ON blocks BEGIN
+            INSERT INTO blocks_fts(blocks_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+            INSERT INTO blocks_fts(rowid, content) VALUES (new.rowid, new.content);
+        END;
+
+        CREATE TRIGGER pages_fts_insert AFTER INSERT ON pages BEGIN
+            INSERT INTO pages_fts(rowid, title) VALUES (new.rowid, new.title);
+        END;
+        CREATE TRIGGER pages_fts_delete AFTER DELETE ON pages BEGIN
+            INSERT INTO pages_fts(pages_fts, rowid, title) VALUES ('delete', old.rowid, old.title);
+        END;
+        CREATE TRIGGER pages_fts_update AFTER UPDATE OF title ON pages BEGIN
+            INSERT INTO pages_fts(pages_fts, rowid, title) VALUES ('delete', old.rowid, old.title);
+            INSERT INTO pages_fts(rowid, title) VALUES (new.rowid, new.title);
+        END;
+        ",
+        down: "
+        DROP TRIGGER IF EXISTS pages_fts_update;
+        DROP TRIGGER IF EXISTS pages_fts_delete;
+        DROP TRIGGER IF EXISTS pages_fts_insert;
+        DROP TRIGGER IF EXISTS blocks_fts_update;
+        DROP TRIGGER IF EXISTS blocks_fts_delete;
+        DROP TRIGGER IF EXISTS blocks_fts_insert;
+        DROP TABLE IF EXISTS pages_fts;
+        DROP TABLE IF EXISTS blocks_fts;
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "trash timestamps and a settings table",
+        // pages that were already archived count as trashed when they were last updated
+        up: "
+        ALTER TABLE pages ADD COLUMN archived_at TEXT;
+        UPDATE pages SET archived_at = updated_at WHERE is_archived = 1;
+        CREATE INDEX idx_pages_archived ON pages(is_archived, archived_at);
+        CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+        ",
+        down: "
+        DROP TABLE IF EXISTS settings;
+        DROP INDEX IF EXISTS idx_pages_archived;
+        ALTER TABLE pages DROP COLUMN archived_at;
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "page version history",
+        // blocks is the whole block list of the page as json, parent ids included so the tree survives
+        up: "
+        CREATE TABLE page_versions (
+            id TEXT PRIMARY KEY,
+            page_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            label TEXT,
+            title TEXT NOT NULL,
+            icon TEXT,
+            cover TEXT,
+            blocks TEXT NOT NULL,
+            block_count INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
+        );
+        CREATE INDEX idx_page_versions_page_id ON page_versions(page_id, created_at);
+        ",
+        down: "
+        DROP INDEX IF EXISTS idx_page_versions_page_id;
+        DROP TABLE IF EXISTS page_versions;
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "links index for backlinks",
+        // one row per PageLink/SubPage block, pulled out of the block_type json by the triggers.
+        // no foreign key on target_page_id on purpose, links to deleted pages are how we find broken ones
+        up: "
+        CREATE TABLE links (
+            block_id TEXT PRIMARY KEY,
+            source_page_id TEXT NOT NULL,
+            target_page_id TEXT NOT NULL,
+            kind TEXT NOT NULL
+        );
+        CREATE INDEX idx_links_target ON links(target_page_id);
+        CREATE INDEX idx_links_source ON links(source_page_id);
+
+        INSERT INTO links (block_id, source_page_id, target_page_id, kind)
+            SELECT id, page_id, json_extract(block_type, '$.data.page_id'), json_extract(block_type, '$.type')
+            FROM blocks WHERE json_extract(block_type, '$.type') IN ('PageLink', 'SubPage');
+
+        CREATE TRIGGER links_insert AFTER INSERT ON blocks
+        WHEN json_extract(new.block_type, '$.type') IN ('PageLink', 'SubPage') BEGIN
+            INSERT INTO links (block_id, source_page_id, target_page_id, kind)
+            VALUES (new.id, new.page_id, json_extract(new.block_type, '$.data.page_id'), json_extract(new.block_type, '$.type'));
+        END;
+        CREATE TRIGGER links_delete AFTER DELETE ON blocks BEGIN
+            DELETE FROM links WHERE block_id = old.id;
+        END;
+        CREATE TRIGGER links_update AFTER UPDATE OF block_type, page_id ON blocks BEGIN
+            DELETE FROM links WHERE block_id = old.id;
+            INSERT INTO links (block_id, source_page_id, target_page_id, kind)
+            SELECT new.id, new.page_id, json_extract(new.block_type, '$.data.page_id'), json_extract(new.block_type, '$.type')
+            WHERE json_extract(new.block_type, '$.type') IN ('PageLink', 'SubPage');
+        END;
+        ",
+        down: "
+        DROP TRIGGER IF EXISTS links_update;
+        DROP TRIGGER IF EXISTS links_delete;
+        DROP TRIGGER IF EXISTS links_insert;
+        DROP INDEX IF EXISTS idx_links_source;
+        DROP INDEX IF EXISTS idx_links_target;
+        DROP TABLE IF EXISTS links;
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "unique block order among siblings",
+        // reorder_block used to just overwrite order_position, so squash duplicates and gaps to 0..n once.
+        // ties keep insertion order (rowid) so nothing jumps around on upgrade
+        up: "
+        UPDATE blocks SET order_position = r.position
+        FROM (
+            SELECT id, ROW_NUMBER() OVER (
+                PARTITION BY page_id, parent_id ORDER BY order_position, rowid
+            ) - 1 AS position
+            FROM blocks
+        ) AS r
+        WHERE r.id = blocks.id;
+        CREATE INDEX idx_blocks_siblings ON blocks(page_id, parent_id, order_position);
+        ",
+        down: "
+        DROP INDEX IF EXISTS idx_blocks_siblings;
+        ",
+    },
+    Migration {
+        version: 7,
+        description: "rich text spans on blocks",
+        // json list of TextSpan, NULL for plain blocks. content stays the plain text so fts doesnt change
+        up: "
+        ALTER TABLE blocks ADD COLUMN spans TEXT;
+        ",
+        down: "
+        ALTER TABLE blocks DROP COLUMN spans;
+        ",
+    },
+];
+
+// the schema version this binary knows how to work with
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Sqlite(rusqlite::Error),
+    // the db was written by a newer bismuth, opening it could corrupt it so we bail
+    DatabaseTooNew { found: u32, supported: u32 },
+    UnknownVersion(u32),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Sqlite(e) => write!(f, "{}", e),
+            MigrationError::DatabaseTooNew { found, supported } => write!(
+                f,
+                "database schema version {} is newer than this version of bismuth supports ({}), please update the app",
+                found, supported
+            ),
+            MigrationError::UnknownVersion(version) => {
+                write!(f, "unknown schema version {}", version)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+impl From<rusqlite::Error> for MigrationError {
+    fn from(e: rusqlite::Error) -> Self {
+        MigrationError::Sqlite(e)
+    }
+}
+
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+// runs every pending up migration, each one in its own transaction
+pub fn run_pending(conn: &mut Connection) -> Result<u32, MigrationError> {
+    migrate_to(conn, latest_version())
+}
+
+// moves the schema up or down to `target`, used by run_pending and for rolling back
+pub fn migrate_to(conn: &mut Connection, target: u32) -> Result<u32, MigrationError> {
+    let current = schema_version(conn)?;
+    let supported = latest_version();
+
+    if current > supported {
+        return Err(MigrationError::DatabaseTooNew {
+            found: current,
+            supported,
+        });
+    }
+    if target > supported || (target != 0 && !MIGRATIONS.iter().any(|m| m.version == target)) {
+        return Err(MigrationError::UnknownVersion(target));
+    }
+
+    if target >= current {
+        for migration in MIGRATIONS
+            .iter()
+            .filter(|m| m.version > current && m.version <= target)
+        {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration.up)?;
+            tx.pragma_update(None, "user_version", migration.version)?;
+            tx.commit()?;
+            eprintln!(
+                "applied migration {}: {}",
+                migration.version, migration.description
+            );
+        }
+    } else {
+        for (idx, migration) in MIGRATIONS.iter().enumerate().rev() {
+            if migration.version <= target || migration.version > current {
+                continue;
+            }
+            let previous = if idx == 0 {
+                0
+            } else {
+                MIGRATIONS[idx - 1].version
+            };
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration.down)?;
+            tx.pragma_update(None, "user_version", previous)?;
+            tx.commit()?;
+            eprintln!(
+                "reverted migration {}: {}",
+                migration.version, migration.description
+            );
+        }
+    }
+
+    Ok(schema_version(conn)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Database;
+
+    #[test]
+    fn versions_are_strictly_increasing() {
+        let versions: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|w| w[0] < w[1]));
+        assert!(versions.first().copied().unwrap_or(1) >= 1);
+    }
+
+    #[test]
+    fn fresh_database_reaches_latest_and_rolls_back() {
+        let db = Database::new(":memory:").unwrap();
+        let mut conn = db.get_connection();
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+
+        assert_eq!(migrate_to(&mut conn, 0).unwrap(), 0);
+        assert_eq!(
+            migrate_to(&mut conn, latest_version()).unwrap(),
+            latest_version()
+        );
+    }
+
+    #[test]
+    fn newer_database_is_rejected() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        match run_pending(&mut conn) {
+            Err(MigrationError::DatabaseTooNew { found, supported }) => {
+                assert_eq!(found, latest_version() + 1);
+                assert_eq!(supported, latest_version());
+            }
+            other => panic!("expected DatabaseTooNew, got {:?}", other),
+        }
+    }
+}
@@ -1,6 +1,22 @@
+pub mod backup;
+pub mod block_ops;
+pub mod block_order;
 pub mod block_repo;
+pub mod block_tree;
 pub mod db;
+pub mod graph;
+pub mod history;
+pub mod links;
+pub mod matcher;
+pub mod migrations;
 pub mod page_repo;
+pub mod search_filters;
 pub mod search_queries;
+pub mod settings;
+pub mod trash;
+pub mod undo;
+pub mod workspace;
 
 pub use db::Database;
+pub use undo::UndoManager;
+pub use workspace::WorkspaceManager;
@@ -6,8 +6,8 @@ impl Database {
     pub fn insert_page(&self, page: &Page) -> Result<()> {
         let conn = self.get_connection();
         conn.execute(
-            "INSERT INTO pages (id, title, icon, cover, parent_id, is_archived, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            "INSERT INTO pages (id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             params![
                 page.id,
                 page.title,
@@ -17,6 +17,7 @@ impl Database {
                 page.is_archived as i32,
                 page.created_at.to_rfc3339(),
                 page.updated_at.to_rfc3339(),
+                page.archived_at.map(|d| d.to_rfc3339()),
             ],
         )?;
         Ok(())
@@ -25,7 +26,7 @@ impl Database {
     pub fn get_page(&self, id: &str) -> Result<Option<Page>> {
         let conn = self.get_connection();
         let mut stmt = conn.prepare(
-            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at 
+            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
              FROM pages WHERE id = ?1",
         )?;
 
@@ -38,7 +39,7 @@ impl Database {
     pub fn list_pages(&self) -> Result<Vec<Page>> {
         let conn = self.get_connection();
         let mut stmt = conn.prepare(
-            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at 
+            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
              FROM pages WHERE is_archived = 0 ORDER BY created_at DESC",
         )?;
 
@@ -60,13 +61,15 @@ impl Database {
         Ok(())
     }
 
+    // commands delete through apply_changes so undo gets the subtree, tests use this directly
+    #[cfg(test)]
     pub fn delete_page(&self, id: &str) -> Result<()> {
         let conn = self.get_connection();
         conn.execute("DELETE FROM pages WHERE id = ?1", params![id])?;
         Ok(())
     }
 
-    fn row_to_page(&self, row: &Row) -> Result<Page> {
+    pub(super) fn row_to_page(&self, row: &Row) -> Result<Page> {
         Ok(Page {
             id: row.get(0)?,
             title: row.get(1)?,
@@ -80,6 +83,11 @@ impl Database {
             updated_at: chrono::DateTime::parse_from_rfc3339(&row.get::<_, String>(7)?)
                 .unwrap()
                 .with_timezone(&chrono::Utc),
+            archived_at: row.get::<_, Option<String>>(8)?.map(|d| {
+                chrono::DateTime::parse_from_rfc3339(&d)
+                    .unwrap()
+                    .with_timezone(&chrono::Utc)
+            }),
         })
     }
 
@@ -108,7 +116,7 @@ impl Database {
     pub fn get_child_pages(&self, parent_id: &str) -> Result<Vec<Page>> {
         let conn = self.get_connection();
         let mut stmt = conn.prepare(
-            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at 
+            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
              FROM pages WHERE parent_id = ?1 AND is_archived = 0 ORDER BY created_at ASC",
         )?;
 
@@ -122,7 +130,7 @@ impl Database {
     pub fn get_root_pages(&self) -> Result<Vec<Page>> {
         let conn = self.get_connection();
         let mut stmt = conn.prepare(
-            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at 
+            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
              FROM pages WHERE parent_id IS NULL AND is_archived = 0 ORDER BY created_at ASC",
         )?;
 
@@ -147,8 +155,10 @@ impl Database {
             "cover"
         };
 
+        // old versions in the history still point at their icon/cover, keep those files around
         let query = format!(
-            "SELECT COUNT(*) FROM pages WHERE {} = ?1 AND id != ?2",
+            "SELECT (SELECT COUNT(*) FROM pages WHERE {0} = ?1 AND id != ?2)
+                  + (SELECT COUNT(*) FROM page_versions WHERE {0} = ?1)",
             column
         );
 
@@ -0,0 +1,381 @@
+use crate::models::{ArchivedFilter, BlockTypeFilter, SearchError, SearchFilters};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rusqlite::types::Value;
+
+/*
+ * tiny query language for the search box, filters are `key:value` and everything else is text
+ *   type:todo type:code lang:rust checked:false
+ *   in:"Project X"            page title or id, subpages included
+ *   created:>2026-01-01 updated:<=2026-02-01 updated:2026-03-04
+ *   archived:include | archived:only
+ * unknown keys stay in the text so searching for "http://..." still works
+ * quoting the whole term ("type:foo") searches for it as text. regex queries dont go through here
+ */
+pub fn parse_query(query: &str) -> Result<(String, SearchFilters), SearchError> {
+    let mut filters = SearchFilters::default();
+    let mut text_parts: Vec<&str> = Vec::new();
+    let mut rewritten = false;
+    let mut checked: Option<bool> = None;
+    let mut language: Option<String> = None;
+
+    for token in tokenize(query) {
+        if let Some(escaped) = quoted(token).filter(|t| t.contains(':')) {
+            text_parts.push(escaped);
+            rewritten = true;
+            continue;
+        }
+        let Some((key, value)) = token.split_once(':') else {
+            text_parts.push(token);
+            continue;
+        };
+        let value = unquote(value);
+
+        match key.to_lowercase().as_str() {
+            "type" => filters.block_types.push(parse_block_type(&value)?),
+            "in" => filters.within = Some(value),
+            "lang" | "language" => language = Some(value),
+            "checked" => checked = Some(parse_bool(&value)?),
+            "created" => {
+                let (after, before) = parse_date_range(&value)?;
+                filters.created_after = after.or(filters.created_after);
+                filters.created_before = before.or(filters.created_before);
+            }
+            "updated" => {
+                let (after, before) = parse_date_range(&value)?;
+                filters.updated_after = after.or(filters.updated_after);
+                filters.updated_before = before.or(filters.updated_before);
+            }
+            "archived" => filters.archived = parse_archived(&value)?,
+            _ => {
+                text_parts.push(token);
+                continue;
+            }
+        }
+        rewritten = true;
+    }
+
+    // lang:rust means code blocks, checked:false means todos
+    if let Some(language) = language {
+        attach(&mut filters.block_types, "Code", |f| {
+            f.language = Some(language.clone())
+        });
+    }
+    if let Some(checked) = checked {
+        attach(&mut filters.block_types, "Todo", |f| {
+            f.checked = Some(checked)
+        });
+    }
+
+    // without filters or escapes the query goes through untouched, spacing and all
+    let text = if rewritten {
+        text_parts.join(" ")
+    } else {
+        query.to_string()
+    };
+
+    Ok((text, filters))
+}
+
+impl SearchFilters {
+    pub fn is_empty(&self) -> bool {
+        self.block_types.is_empty()
+            && self.within.is_none()
+            && self.created_after.is_none()
+            && self.created_before.is_none()
+            && self.updated_after.is_none()
+            && self.updated_before.is_none()
+            && self.archived == ArchivedFilter::Exclude
+    }
+
+    // filters from the query text win over the ones passed in, block types add up
+    pub fn merge(mut self, parsed: SearchFilters) -> SearchFilters {
+        self.block_types.extend(parsed.block_types);
+        self.within = parsed.within.or(self.within);
+        self.created_after = parsed.created_after.or(self.created_after);
+        self.created_before = parsed.created_before.or(self.created_before);
+        self.updated_after = parsed.updated_after.or(self.updated_after);
+        self.updated_before = parsed.updated_before.or(self.updated_before);
+        if parsed.archived != ArchivedFilter::Exclude {
+            self.archived = parsed.archived;
+        }
+        self
+    }
+
+    // extra AND conditions for a query with blocks joined as `b` and pages as `p`
+    pub fn block_conditions(&self, sql: &mut String, params: &mut Vec<Value>) {
+        if !self.block_types.is_empty() {
+            let alternatives: Vec<String> = self
+                .block_types
+                .iter()
+                .map(|filter| {
+                    let mut condition = "(json_extract(b.block_type, '$.type') = ?".to_string();
+                    params.push(Value::Text(filter.block_type.clone()));
+                    if let Some(checked) = filter.checked {
+                        condition.push_str(" AND json_extract(b.block_type, '$.data.checked') = ?");
+                        params.push(Value::Integer(checked as i64));
+                    }
+                    if let Some(language) = &filter.language {
+                        condition.push_str(
+                            " AND lower(json_extract(b.block_type, '$.data.language')) = lower(?)",
+                        );
+                        params.push(Value::Text(language.clone()));
+                    }
+                    condition.push(')');
+                    condition
+                })
+                .collect();
+            sql.push_str(&format!(" AND ({})", alternatives.join(" OR ")));
+        }
+
+        self.within_condition("b.page_id", sql, params);
+        date_condition("b.created_at", ">=", self.created_after, sql, params);
+        date_condition("b.created_at", "<", self.created_before, sql, params);
+        date_condition("b.updated_at", ">=", self.updated_after, sql, params);
+        date_condition("b.updated_at", "<", self.updated_before, sql, params);
+        self.archived_condition(sql);
+    }
+
+    // same idea for page title matches, block type filters mean titles are skipped entirely
+    pub fn page_conditions(&self, sql: &mut String, params: &mut Vec<Value>) {
+        if !self.block_types.is_empty() {
+            sql.push_str(" AND 0");
+        }
+
+        self.within_condition("p.id", sql, params);
+        date_condition("p.created_at", ">=", self.created_after, sql, params);
+        date_condition("p.created_at", "<", self.created_before, sql, params);
+        date_condition("p.updated_at", ">=", self.updated_after, sql, params);
+        date_condition("p.updated_at", "<", self.updated_before, sql, params);
+        self.archived_condition(sql);
+    }
+
+    fn within_condition(&self, column: &str, sql: &mut String, params: &mut Vec<Value>) {
+        let Some(within) = &self.within else {
+            return;
+        };
+        sql.push_str(&format!(
+            " AND {} IN (
+                WITH RECURSIVE subtree(id) AS (
+                    SELECT id FROM pages WHERE id = ? OR lower(title) = lower(?)
+                    UNION
+                    SELECT c.id FROM pages c JOIN subtree s ON c.parent_id = s.id
+                )
+                SELECT id FROM subtree
+            )",
+            column
+        ));
+        params.push(Value::Text(within.clone()));
+        params.push(Value::Text(within.clone()));
+    }
+
+    fn archived_condition(&self, sql: &mut String) {
+        match self.archived {
+            ArchivedFilter::Exclude => sql.push_str(" AND p.is_archived = 0"),
+            ArchivedFilter::Only => sql.push_str(" AND p.is_archived = 1"),
+            ArchivedFilter::Include => {}
+        }
+    }
+}
+
+fn date_condition(
+    column: &str,
+    op: &str,
+    date: Option<DateTime<Utc>>,
+    sql: &mut String,
+    params: &mut Vec<Value>,
+) {
+    if let Some(date) = date {
+        // timestamps are stored with to_rfc3339 so comparing the strings orders them correctly
+        sql.push_str(&format!(" AND {} {} ?", column, op));
+        params.push(Value::Text(date.to_rfc3339()));
+    }
+}
+
+// splits on whitespace but keeps in:"Project X" together
+fn tokenize(query: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start: Option<usize> = None;
+    let mut in_quotes = false;
+
+    for (idx, c) in query.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+        match (c.is_whitespace() && !in_quotes, start) {
+            (false, None) => start = Some(idx),
+            (true, Some(s)) => {
+                tokens.push(&query[s..idx]);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push(&query[s..]);
+    }
+
+    tokens
+}
+
+// the inside of a token that is quoted as a whole
+fn quoted(token: &str) -> Option<&str> {
+    token
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .filter(|_| token.len() >= 2)
+}
+
+fn unquote(value: &str) -> String {
+    value
+        .strip_prefix('"')
+        .map(|v| v.strip_suffix('"').unwrap_or(v))
+        .unwrap_or(value)
+        .to_string()
+}
+
+fn attach(
+    filters: &mut Vec<BlockTypeFilter>,
+    block_type: &str,
+    apply: impl Fn(&mut BlockTypeFilter),
+) {
+    let mut found = false;
+    for filter in filters.iter_mut().filter(|f| f.block_type == block_type) {
+        apply(filter);
+        found = true;
+    }
+    if !found {
+        let mut filter = block_type_filter(block_type);
+        apply(&mut filter);
+        filters.push(filter);
+    }
+}
+
+fn block_type_filter(block_type: &str) -> BlockTypeFilter {
+    BlockTypeFilter {
+        block_type: block_type.to_string(),
+        checked: None,
+        language: None,
+    }
+}
+
+fn parse_block_type(value: &str) -> Result<BlockTypeFilter, SearchError> {
+    let block_type = match value.to_lowercase().as_str() {
+        "text" | "paragraph" => "Text",
+        "h1" | "heading1" => "Heading1",
+        "h2" | "heading2" => "Heading2",
+        "h3" | "heading3" => "Heading3",
+        "bullet" | "bulletlist" => "BulletList",
+        "numbered" | "numberedlist" => "NumberedList",
+        "todo" => "Todo",
+        "code" => "Code",
+        "quote" => "Quote",
+        "divider" => "Divider",
+        "subpage" => "SubPage",
+        "link" | "pagelink" => "PageLink",
+        _ => {
+            return Err(SearchError::InvalidQuery {
+                message: format!("unknown block type '{}'", value),
+            })
+        }
+    };
+    Ok(block_type_filter(block_type))
+}
+
+fn parse_bool(value: &str) -> Result<bool, SearchError> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "done" => Ok(true),
+        "false" | "no" | "open" => Ok(false),
+        _ => Err(SearchError::InvalidQuery {
+            message: format!("expected true or false, got '{}'", value),
+        }),
+    }
+}
+
+fn parse_archived(value: &str) -> Result<ArchivedFilter, SearchError> {
+    match value.to_lowercase().as_str() {
+        "false" | "no" | "exclude" => Ok(ArchivedFilter::Exclude),
+        "include" | "any" => Ok(ArchivedFilter::Include),
+        "true" | "yes" | "only" => Ok(ArchivedFilter::Only),
+        _ => Err(SearchError::InvalidQuery {
+            message: format!(
+                "expected include, only or false for archived, got '{}'",
+                value
+            ),
+        }),
+    }
+}
+
+// ">2026-01-01" -> (after, before), a bare date means that whole day
+type DateRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+fn parse_date_range(value: &str) -> Result<DateRange, SearchError> {
+    let (op, date) = [">=", "<=", ">", "<", "="]
+        .iter()
+        .find_map(|op| value.strip_prefix(op).map(|rest| (*op, rest)))
+        .unwrap_or(("=", value));
+
+    let day =
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| SearchError::InvalidQuery {
+            message: format!("expected a date like 2026-01-31, got '{}'", date),
+        })?;
+    let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let next = start + Duration::days(1);
+
+    Ok(match op {
+        ">" => (Some(next), None),
+        ">=" => (Some(start), None),
+        "<" => (None, Some(start)),
+        "<=" => (None, Some(next)),
+        _ => (Some(start), Some(next)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_filters_and_keeps_the_text() {
+        let (text, filters) =
+            parse_query(r#"type:todo checked:false in:"Project X" updated:>2026-01-01 foo bar"#)
+                .unwrap();
+
+        assert_eq!(text, "foo bar");
+        assert_eq!(
+            filters.block_types,
+            vec![BlockTypeFilter {
+                block_type: "Todo".to_string(),
+                checked: Some(false),
+                language: None,
+            }]
+        );
+        assert_eq!(filters.within.as_deref(), Some("Project X"));
+        assert_eq!(
+            filters.updated_after.unwrap().to_rfc3339(),
+            "2026-01-02T00:00:00+00:00"
+        );
+        assert!(filters.updated_before.is_none());
+    }
+
+    #[test]
+    fn plain_queries_are_untouched() {
+        let (text, filters) = parse_query("see  https://example.com").unwrap();
+        assert_eq!(text, "see  https://example.com");
+        assert!(filters.is_empty());
+
+        assert!(parse_query("type:nope").is_err());
+        assert!(parse_query("created:yesterday").is_err());
+    }
+
+    #[test]
+    fn quoted_terms_are_searched_as_text() {
+        let (text, filters) = parse_query(r#""in:x" type:todo "type:foo bar""#).unwrap();
+        assert_eq!(text, "in:x type:foo bar");
+        assert_eq!(filters.block_types.len(), 1);
+        assert!(filters.within.is_none());
+
+        // quotes without a colon inside are left alone
+        let (text, _) = parse_query(r#""hello world""#).unwrap();
+        assert_eq!(text, r#""hello world""#);
+    }
+}
@@ -1,31 +1,67 @@
+use super::block_repo::spans_json;
 use super::db::Database;
-use crate::models::{SearchFileGroup, SearchMatch, SearchResponse};
-use rusqlite::{params, Result};
+use super::matcher::{Hit, Matcher};
+use crate::models::rich_text;
+use crate::models::{
+    Block, ReplaceChange, ReplaceResponse, SearchError, SearchFileGroup, SearchFilters,
+    SearchMatch, SearchMode, SearchResponse, TextSpan, VersionKind,
+};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Result};
+use std::collections::HashSet;
+use unicode_segmentation::UnicodeSegmentation;
 
 impl Database {
+    // an empty query with filters lists every block the filters let through
     pub fn search_blocks(
         &self,
         query: &str,
         case_sensitive: bool,
         whole_word: bool,
-    ) -> Result<SearchResponse> {
+        mode: SearchMode,
+        filters: &SearchFilters,
+    ) -> std::result::Result<SearchResponse, SearchError> {
+        let matcher = Matcher::new(query, mode, case_sensitive, whole_word)?;
+        let browse = query.trim().is_empty();
         let conn = self.get_connection();
 
-        let mut stmt = conn.prepare(
+        // the trigram index needs a literal of at least 3 chars, anything else scans like before
+        let index_query = matcher.index_query();
+        let use_index = index_query.is_some();
+
+        // candidates come back best bm25 rank first (lower is better)
+        // without the index every row is a candidate and the rank comes from the match score
+        let mut block_sql = if use_index {
             "SELECT b.id, b.page_id, b.block_type, b.content, b.order_position,
-                    p.title, p.icon
+                    p.title, p.icon, bm25(blocks_fts)
+             FROM blocks_fts
+             JOIN blocks b ON b.rowid = blocks_fts.rowid
+             JOIN pages p ON b.page_id = p.id
+             WHERE blocks_fts MATCH ?"
+        } else {
+            "SELECT b.id, b.page_id, b.block_type, b.content, b.order_position,
+                    p.title, p.icon, 0.0
              FROM blocks b
              JOIN pages p ON b.page_id = p.id
-             WHERE p.is_archived = 0
-             ORDER BY p.updated_at DESC, b.order_position ASC",
-        )?;
+             WHERE 1"
+        }
+        .to_string();
+        let mut block_params: Vec<Value> = index_query.iter().cloned().map(Value::Text).collect();
+        filters.block_conditions(&mut block_sql, &mut block_params);
+        block_sql.push_str(if use_index {
+            " ORDER BY bm25(blocks_fts) ASC, b.order_position ASC"
+        } else {
+            " ORDER BY p.updated_at DESC, b.order_position ASC"
+        });
+        let mut stmt = conn.prepare(&block_sql)?;
 
         let mut groups: Vec<SearchFileGroup> = Vec::new();
+        let mut group_rank: Vec<f64> = Vec::new();
         let mut group_map: std::collections::HashMap<String, usize> =
             std::collections::HashMap::new();
         let mut total_matches: usize = 0;
 
-        let rows = stmt.query_map([], |row| {
+        let rows = stmt.query_map(params_from_iter(&block_params), |row| {
             Ok((
                 row.get::<_, String>(0)?,         // block id
                 row.get::<_, String>(1)?,         // page id
@@ -34,28 +70,49 @@ impl Database {
                 row.get::<_, i32>(4)?,            // order
                 row.get::<_, String>(5)?,         // page title
                 row.get::<_, Option<String>>(6)?, // page icon
+                row.get::<_, f64>(7)?,            // rank
             ))
         })?;
 
         for row_result in rows {
-            let (block_id, page_id, block_type, content, order, page_title, page_icon) =
+            let (block_id, page_id, block_type, content, order, page_title, page_icon, rank) =
                 row_result?;
 
-            let matches = find_all_matches(&content, query, case_sensitive, whole_word);
+            // the index is case insensitive and ignores word boundaries, so confirm in rust
+            let matches = if browse {
+                vec![Hit {
+                    start: 0,
+                    end: 0,
+                    score: 1.0,
+                }]
+            } else {
+                matcher.find(&content)
+            };
             if matches.is_empty() {
                 continue;
             }
+            let rank = if use_index {
+                rank
+            } else {
+                -best_score(&matches)
+            };
 
             let search_matches: Vec<SearchMatch> = matches
                 .iter()
-                .map(|&(start, end)| SearchMatch {
-                    block_id: block_id.clone(),
-                    content: content.clone(),
-                    block_type: block_type.clone(),
-                    match_start: start,
-                    match_end: end,
-                    snippet: build_snippet(&content, start, end - start, 40),
-                    order,
+                .map(|hit| {
+                    let snippet = build_snippet(&content, hit.start, hit.end, 40);
+                    SearchMatch {
+                        block_id: block_id.clone(),
+                        content: content.clone(),
+                        block_type: block_type.clone(),
+                        match_start: utf16_offset(&content, hit.start),
+                        match_end: utf16_offset(&content, hit.end),
+                        snippet: snippet.text,
+                        snippet_match_start: snippet.match_start,
+                        snippet_match_end: snippet.match_end,
+                        order,
+                        score: hit.score,
+                    }
                 })
                 .collect();
 
@@ -63,9 +120,11 @@ impl Database {
 
             if let Some(&idx) = group_map.get(&page_id) {
                 groups[idx].matches.extend(search_matches);
+                group_rank[idx] = group_rank[idx].min(rank);
             } else {
                 let idx = groups.len();
                 group_map.insert(page_id.clone(), idx);
+                group_rank.push(rank);
                 groups.push(SearchFileGroup {
                     page_id,
                     page_title,
@@ -76,47 +135,77 @@ impl Database {
         }
 
         // also search page titles
-        let mut title_stmt =
-            conn.prepare("SELECT id, title, icon FROM pages WHERE is_archived = 0")?;
+        let mut title_sql = if use_index {
+            "SELECT p.id, p.title, p.icon, bm25(pages_fts)
+             FROM pages_fts
+             JOIN pages p ON p.rowid = pages_fts.rowid
+             WHERE pages_fts MATCH ?"
+        } else {
+            "SELECT p.id, p.title, p.icon, 0.0 FROM pages p WHERE 1"
+        }
+        .to_string();
+        let mut title_params: Vec<Value> = index_query.iter().cloned().map(Value::Text).collect();
+        filters.page_conditions(&mut title_sql, &mut title_params);
+        if browse {
+            title_sql.push_str(" AND 0");
+        }
+        if use_index {
+            title_sql.push_str(" ORDER BY bm25(pages_fts) ASC");
+        }
+        let mut title_stmt = conn.prepare(&title_sql)?;
 
-        let title_rows = title_stmt.query_map([], |row| {
+        let title_rows = title_stmt.query_map(params_from_iter(&title_params), |row| {
             Ok((
                 row.get::<_, String>(0)?,
                 row.get::<_, String>(1)?,
                 row.get::<_, Option<String>>(2)?,
+                row.get::<_, f64>(3)?,
             ))
         })?;
 
         for row_result in title_rows {
-            let (page_id, title, icon) = row_result?;
-            let title_matches = find_all_matches(&title, query, case_sensitive, whole_word);
+            let (page_id, title, icon, rank) = row_result?;
+            let title_matches = matcher.find(&title);
             if title_matches.is_empty() {
                 continue;
             }
+            let rank = if use_index {
+                rank
+            } else {
+                -best_score(&title_matches)
+            };
 
             let search_matches: Vec<SearchMatch> = title_matches
                 .iter()
-                .map(|&(start, end)| SearchMatch {
+                .map(|hit| SearchMatch {
                     block_id: format!("title-{}", page_id),
                     content: title.clone(),
                     block_type: "Title".to_string(),
-                    match_start: start,
-                    match_end: end,
+                    match_start: utf16_offset(&title, hit.start),
+                    match_end: utf16_offset(&title, hit.end),
                     snippet: title.clone(),
+                    snippet_match_start: utf16_offset(&title, hit.start),
+                    snippet_match_end: utf16_offset(&title, hit.end),
                     order: -1,
+                    score: hit.score,
                 })
                 .collect();
 
             total_matches += search_matches.len();
 
+            // a title hit counts double so those pages float to the top
+            let rank = rank * TITLE_WEIGHT;
+
             if let Some(&idx) = group_map.get(&page_id) {
                 // prepend title matches
                 let mut combined = search_matches;
                 combined.extend(groups[idx].matches.clone());
                 groups[idx].matches = combined;
+                group_rank[idx] = group_rank[idx].min(rank);
             } else {
                 let idx = groups.len();
                 group_map.insert(page_id.clone(), idx);
+                group_rank.push(rank);
                 groups.push(SearchFileGroup {
                     page_id,
                     page_title: title,
@@ -126,107 +215,390 @@ impl Database {
             }
         }
 
+        // best ranked page first, matches inside a page stay in block order
+        let mut ranked: Vec<(f64, SearchFileGroup)> = group_rank.into_iter().zip(groups).collect();
+        ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let groups = ranked
+            .into_iter()
+            .map(|(_, mut group)| {
+                group.matches.sort_by_key(|m| m.order);
+                group
+            })
+            .collect();
+
         Ok(SearchResponse {
             groups,
             total_matches,
         })
     }
 
-    pub fn replace_in_block(
+    // replaces every match inside `scope` in one transaction, a dry run rolls back and only reports
+    // also hands back the changed blocks as they were before, same order as response.blocks (undo)
+    pub fn replace_text(
         &self,
-        block_id: &str,
-        search: &str,
+        scope: ReplaceScope,
+        matcher: &Matcher,
         replacement: &str,
-        case_sensitive: bool,
-    ) -> Result<String> {
-        let conn = self.get_connection();
-        let now = chrono::Utc::now().to_rfc3339();
+        dry_run: bool,
+    ) -> std::result::Result<(ReplaceResponse, Vec<Block>), SearchError> {
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
+
+        let select =
+            "SELECT b.id, b.page_id, b.block_type, b.content, b.parent_id, b.order_position,
+                             b.created_at, b.updated_at, b.spans
+                      FROM blocks b
+                      JOIN pages p ON b.page_id = p.id";
+        let (sql, arg) = match (scope, matcher.index_query()) {
+            (ReplaceScope::Block(block_id), _) => (
+                format!("{} WHERE b.id = ?1", select),
+                Some(block_id.to_string()),
+            ),
+            (ReplaceScope::Page(page_id), _) => (
+                format!(
+                    "{} WHERE b.page_id = ?1 ORDER BY b.order_position ASC",
+                    select
+                ),
+                Some(page_id.to_string()),
+            ),
+            (ReplaceScope::All, Some(index_query)) => (
+                format!(
+                    "{} JOIN blocks_fts ON blocks_fts.rowid = b.rowid
+                     WHERE blocks_fts MATCH ?1 AND p.is_archived = 0
+                     ORDER BY p.updated_at DESC, b.order_position ASC",
+                    select
+                ),
+                Some(index_query),
+            ),
+            (ReplaceScope::All, None) => (
+                format!(
+                    "{} WHERE p.is_archived = 0
+                     ORDER BY p.updated_at DESC, b.order_position ASC",
+                    select
+                ),
+                None,
+            ),
+        };
+
+        let candidates = {
+            let mut stmt = tx.prepare(&sql)?;
+            let rows = stmt.query_map(params_from_iter(&arg), |row| self.row_to_block(row))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let now = chrono::Utc::now();
+        let mut changes = Vec::new();
+        let mut blocks = Vec::new();
+        let mut previous = Vec::new();
+        let mut total_replacements = 0;
+        let mut snapshotted = HashSet::new();
+
+        for mut block in candidates {
+            let (after, replacements) = matcher.replace(&block.content, replacement)?;
+            if replacements == 0 {
+                continue;
+            }
 
-        let mut stmt = conn.prepare("SELECT content FROM blocks WHERE id = ?1")?;
-        let content: String = stmt.query_row(params![block_id], |row| row.get(0))?;
+            total_replacements += replacements;
+            changes.push(ReplaceChange {
+                block_id: block.id.to_string(),
+                page_id: block.page_id.to_string(),
+                before: block.content.clone(),
+                after: after.clone(),
+                replacements,
+            });
 
-        let new_content = if case_sensitive {
-            content.replace(search, replacement)
+            if !dry_run {
+                // not debounced, one replace can rewrite a lot of a page at once
+                if snapshotted.insert(block.page_id) {
+                    self.snapshot_with(&tx, &block.page_id.to_string(), VersionKind::Auto, None)?;
+                }
+                previous.push(block.clone());
+                block.spans = match block.spans.take() {
+                    Some(spans) => replace_in_spans(spans, matcher, replacement, &after)?,
+                    None => None,
+                };
+                tx.execute(
+                    "UPDATE blocks SET content = ?1, spans = ?2, updated_at = ?3 WHERE id = ?4",
+                    params![
+                        after,
+                        spans_json(&block),
+                        now.to_rfc3339(),
+                        block.id.to_string()
+                    ],
+                )?;
+                block.content = after;
+                block.updated_at = now;
+                blocks.push(block);
+            }
+        }
+
+        if dry_run {
+            tx.rollback()?;
         } else {
-            case_insensitive_replace(&content, search, replacement)
+            tx.commit()?;
+        }
+
+        let response = ReplaceResponse {
+            changes,
+            blocks,
+            total_replacements,
+            dry_run,
         };
+        Ok((response, previous))
+    }
+}
 
-        conn.execute(
-            "UPDATE blocks SET content = ?1, updated_at = ?2 WHERE id = ?3",
-            params![new_content, now, block_id],
-        )?;
+pub enum ReplaceScope<'a> {
+    Block(&'a str),
+    Page(&'a str),
+    All,
+}
 
-        Ok(new_content)
+const TITLE_WEIGHT: f64 = 2.0;
+
+// runs the replace inside each span so the formatting stays. a match that crosses two spans
+// cant be split between them, then the block falls back to plain text
+fn replace_in_spans(
+    spans: Vec<TextSpan>,
+    matcher: &Matcher,
+    replacement: &str,
+    after: &str,
+) -> std::result::Result<Option<Vec<TextSpan>>, SearchError> {
+    let mut replaced = Vec::new();
+    for mut span in spans {
+        span.text = matcher.replace(&span.text, replacement)?.0;
+        replaced.push(span);
     }
+    Ok((rich_text::plain_text(&replaced) == after)
+        .then(|| rich_text::normalize(replaced))
+        .flatten())
+}
+
+// fuzzy hits rank by how close they were, the others just count as perfect
+fn best_score(hits: &[Hit]) -> f64 {
+    hits.iter().map(|h| h.score).fold(0.0, f64::max)
+}
+
+struct Snippet {
+    text: String,
+    match_start: usize, // utf-16 offsets of the match inside `text`
+    match_end: usize,
 }
 
-fn find_all_matches(
-    content: &str,
-    query: &str,
-    case_sensitive: bool,
-    whole_word: bool,
-) -> Vec<(usize, usize)> {
-    if query.is_empty() {
-        return vec![];
+// cuts `context` graphemes either side of the match so we never split a char, emoji or accent
+fn build_snippet(content: &str, match_start: usize, match_end: usize, context: usize) -> Snippet {
+    let boundaries: Vec<usize> = content
+        .grapheme_indices(true)
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(content.len()))
+        .collect();
+
+    // grapheme the match starts in and the first one after it ends
+    let first = boundaries
+        .partition_point(|&b| b <= match_start)
+        .saturating_sub(1);
+    let last = boundaries.partition_point(|&b| b < match_end);
+
+    let start = boundaries[first.saturating_sub(context)];
+    let end = boundaries[(last + context).min(boundaries.len() - 1)];
+
+    let body = &content[start..end];
+    let trimmed = body.trim();
+    let leading = body.len() - body.trim_start().len();
+
+    let mut text = String::new();
+    if start > 0 {
+        text.push_str("...");
+    }
+    let offset = text.encode_utf16().count();
+    text.push_str(trimmed);
+    let trimmed_len = trimmed.encode_utf16().count();
+    if end < content.len() {
+        text.push_str("...");
     }
 
-    let (search_content, search_query) = if case_sensitive {
-        (content.to_string(), query.to_string())
-    } else {
-        (content.to_lowercase(), query.to_lowercase())
+    // the match can start or end inside the whitespace trim() took off
+    let relative = |pos: usize| {
+        let pos = pos.clamp(start + leading, start + leading + trimmed.len());
+        offset + content[start + leading..pos].encode_utf16().count()
     };
 
-    let mut matches = Vec::new();
-    let mut start = 0;
+    Snippet {
+        match_start: relative(match_start).min(offset + trimmed_len),
+        match_end: relative(match_end).min(offset + trimmed_len),
+        text,
+    }
+}
 
-    while let Some(pos) = search_content[start..].find(&search_query) {
-        let abs_pos = start + pos;
-        let end_pos = abs_pos + query.len();
+// js strings index by utf-16 code units, so thats what the frontend gets
+fn utf16_offset(content: &str, byte_idx: usize) -> usize {
+    content[..byte_idx].encode_utf16().count()
+}
 
-        if whole_word {
-            let before_ok =
-                abs_pos == 0 || !content.as_bytes()[abs_pos - 1].is_ascii_alphanumeric();
-            let after_ok =
-                end_pos >= content.len() || !content.as_bytes()[end_pos].is_ascii_alphanumeric();
-            if before_ok && after_ok {
-                matches.push((abs_pos, end_pos));
-            }
-        } else {
-            matches.push((abs_pos, end_pos));
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, BlockType, Page};
+    use uuid::Uuid;
 
-        start = abs_pos + 1;
+    fn search(
+        db: &Database,
+        query: &str,
+        case_sensitive: bool,
+        whole_word: bool,
+    ) -> SearchResponse {
+        let filters = SearchFilters::default();
+        db.search_blocks(
+            query,
+            case_sensitive,
+            whole_word,
+            SearchMode::Literal,
+            &filters,
+        )
+        .unwrap()
     }
 
-    matches
-}
+    fn add_block(db: &Database, page: &Page, content: &str) -> Block {
+        let page_id = Uuid::parse_str(&page.id).unwrap();
+        let block = Block::new(page_id, BlockType::Text, content.to_string());
+        db.insert_block(&block).unwrap();
+        block
+    }
+
+    #[test]
+    fn index_follows_inserts_updates_and_deletes() {
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("Groceries".to_string());
+        db.insert_page(&page).unwrap();
+        let block = add_block(&db, &page, "buy oat milk");
+
+        assert_eq!(search(&db, "oat", false, false).total_matches, 1);
+
+        db.update_block_content(&block.id.to_string(), "buy bread")
+            .unwrap();
+        assert_eq!(search(&db, "oat", false, false).total_matches, 0);
+        assert_eq!(search(&db, "BREAD", false, false).total_matches, 1);
+
+        db.delete_block(&block.id.to_string()).unwrap();
+        assert_eq!(search(&db, "bread", false, false).total_matches, 0);
 
-fn case_insensitive_replace(content: &str, search: &str, replacement: &str) -> String {
-    let lower_content = content.to_lowercase();
-    let lower_search = search.to_lowercase();
-    let mut result = String::new();
-    let mut last = 0;
-
-    while let Some(pos) = lower_content[last..].find(&lower_search) {
-        let abs = last + pos;
-        result.push_str(&content[last..abs]);
-        result.push_str(replacement);
-        last = abs + search.len();
+        // titles are indexed too and short queries fall back to a scan
+        let response = search(&db, "cer", false, false);
+        assert_eq!(response.groups[0].matches[0].block_type, "Title");
+        assert_eq!(search(&db, "ro", false, false).total_matches, 1);
     }
-    result.push_str(&content[last..]);
-    result
-}
 
-fn build_snippet(content: &str, match_start: usize, match_len: usize, context: usize) -> String {
-    let start = match_start.saturating_sub(context);
-    let end = (match_start + match_len + context).min(content.len());
-    let mut s = String::new();
-    if start > 0 {
-        s.push_str("...");
+    #[test]
+    fn better_ranked_pages_come_first() {
+        let db = Database::new(":memory:").unwrap();
+        let weak = Page::new("weak".to_string());
+        let strong = Page::new("strong".to_string());
+        db.insert_page(&weak).unwrap();
+        db.insert_page(&strong).unwrap();
+        add_block(
+            &db,
+            &weak,
+            "a long paragraph that mentions rust exactly once among many other words",
+        );
+        add_block(&db, &strong, "rust rust");
+
+        let response = search(&db, "rust", false, true);
+        assert_eq!(response.groups[0].page_id, strong.id);
+        assert_eq!(response.total_matches, 3);
     }
-    s.push_str(content[start..end].trim());
-    if end < content.len() {
-        s.push_str("...");
+
+    #[test]
+    fn replace_dry_run_leaves_content_alone() {
+        let matcher = |case_sensitive, whole_word| {
+            Matcher::new("cat", SearchMode::Literal, case_sensitive, whole_word).unwrap()
+        };
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("notes".to_string());
+        db.insert_page(&page).unwrap();
+        let block = add_block(&db, &page, "cat catalog Cat");
+
+        let (preview, previous) = db
+            .replace_text(ReplaceScope::All, &matcher(false, true), "dog", true)
+            .unwrap();
+        assert!(previous.is_empty());
+        assert_eq!(preview.total_replacements, 2);
+        assert_eq!(preview.changes[0].after, "dog catalog dog");
+        assert!(preview.blocks.is_empty());
+        let stored = db.get_block_by_id(&block.id.to_string()).unwrap().unwrap();
+        assert_eq!(stored.content, "cat catalog Cat");
+
+        let (applied, previous) = db
+            .replace_text(
+                ReplaceScope::Page(&page.id),
+                &matcher(true, false),
+                "dog",
+                false,
+            )
+            .unwrap();
+        assert_eq!(applied.blocks[0].content, "dog dogalog Cat");
+        assert_eq!(previous[0].content, "cat catalog Cat");
+        let stored = db.get_block_by_id(&block.id.to_string()).unwrap().unwrap();
+        assert_eq!(stored.content, "dog dogalog Cat");
+    }
+
+    #[test]
+    fn snippets_and_offsets_handle_non_ascii() {
+        let content = format!("{}🎉 Grüße {}", "ä".repeat(60), "x".repeat(60));
+        let start = content.find("Grüße").unwrap();
+        let snippet = build_snippet(&content, start, start + "Grüße".len(), 5);
+
+        assert_eq!(snippet.text, "...äää🎉 Grüße xxxx...");
+        let utf16: Vec<u16> = snippet.text.encode_utf16().collect();
+        let highlighted =
+            String::from_utf16(&utf16[snippet.match_start..snippet.match_end]).unwrap();
+        assert_eq!(highlighted, "Grüße");
+
+        let db = Database::new(":memory:").unwrap();
+        let page = Page::new("emoji".to_string());
+        db.insert_page(&page).unwrap();
+        add_block(&db, &page, "🎉🎉 GRÜẞE");
+        let response = search(&db, "grüße", false, false);
+        let m = &response.groups[0].matches[0];
+        // each emoji is two utf-16 units
+        assert_eq!((m.match_start, m.match_end), (5, 10));
+    }
+
+    #[test]
+    fn filters_narrow_by_type_and_subtree() {
+        let db = Database::new(":memory:").unwrap();
+        let project = Page::new("Project X".to_string());
+        let sub = Page::new("meetings".to_string()).with_parent(project.id.clone());
+        let other = Page::new("other".to_string());
+        for page in [&project, &sub, &other] {
+            db.insert_page(page).unwrap();
+        }
+
+        for (page, block_type) in [
+            (&sub, BlockType::Todo { checked: false }),
+            (&sub, BlockType::Todo { checked: true }),
+            (&other, BlockType::Todo { checked: false }),
+            (&project, BlockType::Text),
+        ] {
+            let page_id = Uuid::parse_str(&page.id).unwrap();
+            db.insert_block(&Block::new(page_id, block_type, "ship it".to_string()))
+                .unwrap();
+        }
+
+        let (text, filters) =
+            super::super::search_filters::parse_query(r#"type:todo checked:false in:"project x""#)
+                .unwrap();
+        let response = db
+            .search_blocks(&text, false, false, SearchMode::Literal, &filters)
+            .unwrap();
+        assert_eq!(response.total_matches, 1);
+        assert_eq!(response.groups[0].page_id, sub.id);
+
+        let (text, filters) =
+            super::super::search_filters::parse_query("ship in:\"Project X\"").unwrap();
+        let response = db
+            .search_blocks(&text, false, false, SearchMode::Literal, &filters)
+            .unwrap();
+        assert_eq!(response.total_matches, 3);
     }
-    s
 }
@@ -0,0 +1,79 @@
+use super::db::Database;
+use crate::models::BackupSettings;
+use rusqlite::{params, OptionalExtension, Result};
+
+pub const TRASH_RETENTION_KEY: &str = "trash_retention_days";
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+pub const BACKUP_INTERVAL_KEY: &str = "backup_interval_hours";
+pub const BACKUP_KEEP_DAILY_KEY: &str = "backup_keep_daily";
+pub const BACKUP_KEEP_WEEKLY_KEY: &str = "backup_keep_weekly";
+pub const DEFAULT_BACKUP_INTERVAL_HOURS: u32 = 24;
+pub const DEFAULT_BACKUP_KEEP_DAILY: u32 = 7;
+pub const DEFAULT_BACKUP_KEEP_WEEKLY: u32 = 4;
+
+// per workspace settings, plain key/value strings so adding one doesnt need a migration
+impl Database {
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>> {
+        let conn = self.get_connection();
+        conn.query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let conn = self.get_connection();
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    // None means the trash is never emptied automatically
+    pub fn trash_retention_days(&self) -> Result<Option<u32>> {
+        Ok(match self.get_setting(TRASH_RETENTION_KEY)? {
+            Some(value) => value.parse().ok(),
+            None => Some(DEFAULT_TRASH_RETENTION_DAYS),
+        })
+    }
+
+    pub fn set_trash_retention_days(&self, days: Option<u32>) -> Result<()> {
+        let value = days
+            .map(|d| d.to_string())
+            .unwrap_or_else(|| "never".to_string());
+        self.set_setting(TRASH_RETENTION_KEY, &value)
+    }
+
+    pub fn backup_settings(&self) -> Result<BackupSettings> {
+        let number = |key: &str, default: u32| -> Result<u32> {
+            Ok(self
+                .get_setting(key)?
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default))
+        };
+        Ok(BackupSettings {
+            interval_hours: match self.get_setting(BACKUP_INTERVAL_KEY)? {
+                Some(value) => value.parse().ok().filter(|hours| *hours > 0),
+                None => Some(DEFAULT_BACKUP_INTERVAL_HOURS),
+            },
+            keep_daily: number(BACKUP_KEEP_DAILY_KEY, DEFAULT_BACKUP_KEEP_DAILY)?,
+            keep_weekly: number(BACKUP_KEEP_WEEKLY_KEY, DEFAULT_BACKUP_KEEP_WEEKLY)?,
+        })
+    }
+
+    pub fn set_backup_settings(&self, settings: &BackupSettings) -> Result<()> {
+        let interval = settings
+            .interval_hours
+            .filter(|hours| *hours > 0)
+            .map(|h| h.to_string())
+            .unwrap_or_else(|| "never".to_string());
+        self.set_setting(BACKUP_INTERVAL_KEY, &interval)?;
+        self.set_setting(BACKUP_KEEP_DAILY_KEY, &settings.keep_daily.to_string())?;
+        self.set_setting(BACKUP_KEEP_WEEKLY_KEY, &settings.keep_weekly.to_string())
+    }
+}
@@ -0,0 +1,216 @@
+use super::db::Database;
+use crate::models::Page;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, OptionalExtension, Result};
+
+/*
+ * archiving a page moves it and all of its subpages to the trash in one go, they share the same
+ * archived_at so restoring the page brings back exactly what was trashed with it
+ * (a subpage trashed on its own earlier stays in the trash as its own entry)
+ */
+pub struct PurgedTrash {
+    pub pages: usize,
+    // icon/cover files that no page points at anymore, safe to delete from page_assets
+    pub unused_assets: Vec<String>,
+}
+
+impl Database {
+    pub fn archive_page(&self, id: &str) -> Result<usize> {
+        let conn = self.get_connection();
+        let now = Utc::now().to_rfc3339();
+
+        conn.execute(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT id FROM pages WHERE id = ?1
+                 UNION
+                 SELECT c.id FROM pages c JOIN subtree s ON c.parent_id = s.id
+             )
+             UPDATE pages SET is_archived = 1, archived_at = ?2
+             WHERE id IN (SELECT id FROM subtree) AND is_archived = 0",
+            params![id, now],
+        )
+    }
+
+    pub fn restore_page(&self, id: &str) -> Result<usize> {
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
+
+        let archived_at: Option<Option<String>> = tx
+            .query_row(
+                "SELECT archived_at FROM pages WHERE id = ?1 AND is_archived = 1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(archived_at) = archived_at else {
+            return Ok(0);
+        };
+
+        let restored = tx.execute(
+            "WITH RECURSIVE subtree(id) AS (
+                 SELECT id FROM pages WHERE id = ?1
+                 UNION
+                 SELECT c.id FROM pages c JOIN subtree s ON c.parent_id = s.id
+                 WHERE c.is_archived = 1 AND c.archived_at IS ?2
+             )
+             UPDATE pages SET is_archived = 0, archived_at = NULL
+             WHERE id IN (SELECT id FROM subtree)",
+            params![id, archived_at],
+        )?;
+
+        // if the parent is still in the trash (or gone) the page comes back at the top level
+        tx.execute(
+            "UPDATE pages SET parent_id = NULL
+             WHERE id = ?1 AND parent_id IS NOT NULL
+               AND parent_id NOT IN (SELECT id FROM pages WHERE is_archived = 0)",
+            params![id],
+        )?;
+
+        tx.commit()?;
+        Ok(restored)
+    }
+
+    // only the pages that were trashed directly, their subpages come along when restoring
+    pub fn list_trash(&self) -> Result<Vec<Page>> {
+        let conn = self.get_connection();
+        let mut stmt = conn.prepare(
+            "SELECT p.id, p.title, p.icon, p.cover, p.parent_id, p.is_archived, p.created_at,
+                    p.updated_at, p.archived_at
+             FROM pages p LEFT JOIN pages parent ON parent.id = p.parent_id
+             WHERE p.is_archived = 1
+               AND (parent.is_archived IS NOT 1 OR parent.archived_at IS NOT p.archived_at)
+             ORDER BY p.archived_at DESC",
+        )?;
+
+        let pages = stmt
+            .query_map([], |row| self.row_to_page(row))?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(pages)
+    }
+
+    // deletes trashed pages for good, all of them or just the ones trashed before `archived_before`
+    pub fn purge_trash(&self, archived_before: Option<DateTime<Utc>>) -> Result<PurgedTrash> {
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
+        let cutoff = archived_before.map(|d| d.to_rfc3339());
+
+        let ids: Vec<String> = {
+            let mut stmt = tx.prepare(
+                "WITH RECURSIVE doomed(id) AS (
+                     SELECT id FROM pages
+                     WHERE is_archived = 1 AND (?1 IS NULL OR archived_at < ?1)
+                     UNION
+                     SELECT c.id FROM pages c JOIN doomed d ON c.parent_id = d.id
+                 )
+                 SELECT id FROM doomed",
+            )?;
+            let ids = stmt
+                .query_map(params![cutoff], |row| row.get(0))?
+                .collect::<Result<Vec<_>>>()?;
+            ids
+        };
+
+        let mut assets: Vec<String> = Vec::new();
+        for id in &ids {
+            let (icon, cover): (Option<String>, Option<String>) = tx.query_row(
+                "SELECT icon, cover FROM pages WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            assets.extend(icon);
+            assets.extend(cover);
+        }
+
+        // blocks and subpages go with their page through ON DELETE CASCADE
+        for id in &ids {
+            tx.execute("DELETE FROM pages WHERE id = ?1", params![id])?;
+        }
+
+        assets.sort();
+        assets.dedup();
+        let mut unused_assets = Vec::new();
+        for asset in assets {
+            let count: i64 = tx.query_row(
+                "SELECT (SELECT COUNT(*) FROM pages WHERE icon = ?1 OR cover = ?1)
+                      + (SELECT COUNT(*) FROM page_versions WHERE icon = ?1 OR cover = ?1)",
+                params![asset],
+                |row| row.get(0),
+            )?;
+            if count == 0 {
+                unused_assets.push(asset);
+            }
+        }
+
+        tx.commit()?;
+        Ok(PurgedTrash {
+            pages: ids.len(),
+            unused_assets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, BlockType};
+    use uuid::Uuid;
+
+    #[test]
+    fn archive_and_restore_whole_subtrees() {
+        let db = Database::new(":memory:").unwrap();
+        let parent = Page::new("parent".to_string());
+        let child = Page::new("child".to_string()).with_parent(parent.id.clone());
+        let grandchild = Page::new("grandchild".to_string()).with_parent(child.id.clone());
+        for page in [&parent, &child, &grandchild] {
+            db.insert_page(page).unwrap();
+        }
+
+        assert_eq!(db.archive_page(&parent.id).unwrap(), 3);
+        assert!(db.get_root_pages().unwrap().is_empty());
+        let trash = db.list_trash().unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, parent.id);
+
+        // restoring the child alone pulls it (and the grandchild) out to the top level
+        assert_eq!(db.restore_page(&child.id).unwrap(), 2);
+        let roots = db.get_root_pages().unwrap();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].id, child.id);
+        assert_eq!(db.get_child_pages(&child.id).unwrap().len(), 1);
+
+        assert_eq!(db.restore_page(&parent.id).unwrap(), 1);
+        assert_eq!(db.restore_page(&parent.id).unwrap(), 0);
+        assert!(db.list_trash().unwrap().is_empty());
+    }
+
+    #[test]
+    fn purge_removes_pages_blocks_and_reports_unused_assets() {
+        let db = Database::new(":memory:").unwrap();
+        let mut trashed = Page::new("old".to_string());
+        trashed.icon = Some("shared.png".to_string());
+        trashed.cover = Some("cover.png".to_string());
+        let mut kept = Page::new("kept".to_string());
+        kept.icon = Some("shared.png".to_string());
+        let child = Page::new("child".to_string()).with_parent(trashed.id.clone());
+        for page in [&trashed, &kept, &child] {
+            db.insert_page(page).unwrap();
+        }
+        let page_id = Uuid::parse_str(&child.id).unwrap();
+        db.insert_block(&Block::new(page_id, BlockType::Text, "bye".to_string()))
+            .unwrap();
+
+        db.archive_page(&trashed.id).unwrap();
+
+        // nothing is old enough yet
+        let cutoff = Utc::now() - chrono::Duration::days(30);
+        assert_eq!(db.purge_trash(Some(cutoff)).unwrap().pages, 0);
+
+        let purged = db.purge_trash(None).unwrap();
+        assert_eq!(purged.pages, 2);
+        assert_eq!(purged.unused_assets, vec!["cover.png".to_string()]);
+        assert!(db.get_page(&child.id).unwrap().is_none());
+        assert!(db.get_page_blocks(&child.id).unwrap().is_empty());
+        assert!(db.get_page(&kept.id).unwrap().is_some());
+    }
+}
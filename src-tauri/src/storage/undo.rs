@@ -0,0 +1,584 @@
+use super::block_repo::spans_json;
+use super::db::Database;
+use crate::models::{Block, Page, UndoResult, UndoState};
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/*
+ * undo/redo, every mutation records a Step with the before and after state of each row it touched
+ * undo writes the befores back (in reverse), redo writes the afters, so deleting a SubPage block
+ * is one step even though it also removes the child page, its subpages and all their blocks
+ * the stacks live in memory per window label, nothing survives a restart
+ */
+const MAX_STEPS: usize = 100;
+
+// saves to the same block this close together become one step, so undo doesnt go letter by letter
+const MERGE_WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub enum Change {
+    Block {
+        before: Option<Block>,
+        after: Option<Block>,
+    },
+    Page {
+        before: Option<Page>,
+        after: Option<Page>,
+    },
+}
+
+// a step is only applied while every row it touches still looks the way it left them, edits that
+// arent on this stack (another window, trash purges) make it stale and it gets dropped instead
+#[derive(Debug)]
+pub enum ApplyError {
+    Stale,
+    Database(rusqlite::Error),
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApplyError::Stale => write!(f, "it was changed since"),
+            ApplyError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ApplyError {
+    fn from(e: rusqlite::Error) -> Self {
+        ApplyError::Database(e)
+    }
+}
+
+#[derive(Debug)]
+pub struct Step {
+    label: String,
+    changes: Vec<Change>,
+    merge_key: Option<String>,
+    at: Instant,
+}
+
+impl Step {
+    pub fn new(label: &str, changes: Vec<Change>) -> Self {
+        Step {
+            label: label.to_string(),
+            changes,
+            merge_key: None,
+            at: Instant::now(),
+        }
+    }
+
+    pub fn merging(mut self, key: String) -> Self {
+        self.merge_key = Some(key);
+        self
+    }
+
+    fn page_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self
+            .changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Block { before, after } => before
+                    .as_ref()
+                    .or(after.as_ref())
+                    .map(|b| b.page_id.to_string()),
+                Change::Page { before, after } => {
+                    before.as_ref().or(after.as_ref()).map(|p| p.id.clone())
+                }
+            })
+            .collect();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+}
+
+#[derive(Default)]
+struct Stacks {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+}
+
+impl Stacks {
+    fn state(&self) -> UndoState {
+        UndoState {
+            undo_label: self.undo.last().map(|s| s.label.clone()),
+            redo_label: self.redo.last().map(|s| s.label.clone()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct UndoManager {
+    windows: Mutex<HashMap<String, Stacks>>,
+}
+
+impl UndoManager {
+    pub fn record(&self, window: &str, step: Step) {
+        if step.changes.is_empty() {
+            return;
+        }
+
+        let mut windows = self.windows.lock().unwrap();
+        let stacks = windows.entry(window.to_string()).or_default();
+        stacks.redo.clear();
+
+        if let Some(last) = stacks.undo.last_mut() {
+            if step.merge_key.is_some()
+                && last.merge_key == step.merge_key
+                && step.at.duration_since(last.at) < MERGE_WINDOW
+            {
+                // keep the oldest before, take the newest after
+                for (old, new) in last.changes.iter_mut().zip(step.changes) {
+                    match (old, new) {
+                        (Change::Block { after, .. }, Change::Block { after: newer, .. }) => {
+                            *after = newer
+                        }
+                        (Change::Page { after, .. }, Change::Page { after: newer, .. }) => {
+                            *after = newer
+                        }
+                        _ => {}
+                    }
+                }
+                last.at = step.at;
+                return;
+            }
+        }
+
+        stacks.undo.push(step);
+        if stacks.undo.len() > MAX_STEPS {
+            stacks.undo.remove(0);
+        }
+    }
+
+    pub fn undo(&self, window: &str, db: &Database) -> Result<Option<UndoResult>, ApplyError> {
+        self.pop_and_apply(window, db, true)
+    }
+
+    pub fn redo(&self, window: &str, db: &Database) -> Result<Option<UndoResult>, ApplyError> {
+        self.pop_and_apply(window, db, false)
+    }
+
+    pub fn state(&self, window: &str) -> UndoState {
+        self.windows
+            .lock()
+            .unwrap()
+            .get(window)
+            .map(|stacks| stacks.state())
+            .unwrap_or_default()
+    }
+
+    // the ids in the stacks only make sense for the database they came from
+    pub fn clear(&self) {
+        self.windows.lock().unwrap().clear();
+    }
+
+    fn pop_and_apply(
+        &self,
+        window: &str,
+        db: &Database,
+        undo: bool,
+    ) -> Result<Option<UndoResult>, ApplyError> {
+        let mut windows = self.windows.lock().unwrap();
+        let stacks = windows.entry(window.to_string()).or_default();
+
+        let (from, to) = if undo {
+            (&mut stacks.undo, &mut stacks.redo)
+        } else {
+            (&mut stacks.redo, &mut stacks.undo)
+        };
+        let Some(step) = from.pop() else {
+            return Ok(None);
+        };
+
+        // a step that cant be applied is dropped, kept on top it would block everything under it
+        db.apply_changes(&step.changes, undo)?;
+
+        let label = step.label.clone();
+        let page_ids = step.page_ids();
+        to.push(step);
+
+        Ok(Some(UndoResult {
+            label,
+            page_ids,
+            state: stacks.state(),
+        }))
+    }
+}
+
+impl Database {
+    // writes the before side of every change (undo) or the after side (redo) in one transaction
+    // every row has to be in the state on the other side first (or already at the target, rows
+    // that were cascaded away), otherwise nothing is written and it's ApplyError::Stale
+    pub fn apply_changes(&self, changes: &[Change], undo: bool) -> Result<(), ApplyError> {
+        let mut conn = self.get_connection();
+        let tx = conn.transaction()?;
+        // rows come back children first on undo, so check the parent ids at commit
+        tx.pragma_update(None, "defer_foreign_keys", true)?;
+
+        let ordered: Box<dyn Iterator<Item = &Change>> = if undo {
+            Box::new(changes.iter().rev())
+        } else {
+            Box::new(changes.iter())
+        };
+
+        for change in ordered {
+            match change {
+                Change::Block { before, after } => {
+                    let (id, target) = pick(before, after, undo, |b| b.id.to_string());
+                    let expected = if undo { after } else { before };
+                    let current = self.current_block(&tx, &id)?;
+                    if !same_block(current.as_ref(), expected.as_ref())
+                        && !same_block(current.as_ref(), target)
+                    {
+                        return Err(ApplyError::Stale);
+                    }
+                    write_block(&tx, &id, target)?;
+                }
+                Change::Page { before, after } => {
+                    let (id, target) = pick(before, after, undo, |p| p.id.clone());
+                    let expected = if undo { after } else { before };
+                    let current = self.current_page(&tx, &id)?;
+                    if !same_page(current.as_ref(), expected.as_ref())
+                        && !same_page(current.as_ref(), target)
+                    {
+                        return Err(ApplyError::Stale);
+                    }
+                    write_page(&tx, &id, target)?;
+                }
+            }
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    fn current_block(&self, conn: &Connection, id: &str) -> Result<Option<Block>> {
+        conn.query_row(
+            "SELECT id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans
+             FROM blocks WHERE id = ?1",
+            params![id],
+            |row| self.row_to_block(row),
+        )
+        .optional()
+    }
+
+    fn current_page(&self, conn: &Connection, id: &str) -> Result<Option<Page>> {
+        conn.query_row(
+            "SELECT id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at
+             FROM pages WHERE id = ?1",
+            params![id],
+            |row| self.row_to_page(row),
+        )
+        .optional()
+    }
+
+    // a page, its subpages and every block in them, parents first
+    pub fn page_subtree(&self, page_id: &str) -> Result<(Vec<Page>, Vec<Block>)> {
+        let conn = self.get_connection();
+        let subtree = "WITH RECURSIVE subtree(id, depth) AS (
+                           SELECT id, 0 FROM pages WHERE id = ?1
+                           UNION
+                           SELECT c.id, s.depth + 1 FROM pages c JOIN subtree s ON c.parent_id = s.id
+                       )";
+
+        let pages = {
+            let mut stmt = conn.prepare(&format!(
+                "{} SELECT p.id, p.title, p.icon, p.cover, p.parent_id, p.is_archived, p.created_at,
+                           p.updated_at, p.archived_at
+                    FROM pages p JOIN subtree s ON s.id = p.id ORDER BY s.depth ASC",
+                subtree
+            ))?;
+            let rows = stmt.query_map(params![page_id], |row| self.row_to_page(row))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let blocks = {
+            let mut stmt = conn.prepare(&format!(
+                "{} SELECT b.id, b.page_id, b.block_type, b.content, b.parent_id, b.order_position,
+                           b.created_at, b.updated_at, b.spans
+                    FROM blocks b JOIN subtree s ON s.id = b.page_id ORDER BY b.order_position ASC",
+                subtree
+            ))?;
+            let rows = stmt.query_map(params![page_id], |row| self.row_to_block(row))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        Ok((pages, blocks))
+    }
+
+    // a block and the nested blocks that get cascaded away with it, parents first
+    pub fn block_subtree(&self, block_id: &str) -> Result<Vec<Block>> {
+        let conn = self.get_connection();
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE subtree(id, depth) AS (
+                 SELECT id, 0 FROM blocks WHERE id = ?1
+                 UNION
+                 SELECT c.id, s.depth + 1 FROM blocks c JOIN subtree s ON c.parent_id = s.id
+             )
+             SELECT b.id, b.page_id, b.block_type, b.content, b.parent_id, b.order_position,
+                    b.created_at, b.updated_at, b.spans
+             FROM blocks b JOIN subtree s ON s.id = b.id
+             ORDER BY s.depth ASC, b.order_position ASC",
+        )?;
+
+        let blocks = stmt
+            .query_map(params![block_id], |row| self.row_to_block(row))?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(blocks)
+    }
+}
+
+fn pick<'a, T>(
+    before: &'a Option<T>,
+    after: &'a Option<T>,
+    undo: bool,
+    id: impl Fn(&T) -> String,
+) -> (String, Option<&'a T>) {
+    let target = if undo { before } else { after };
+    let id = before
+        .as_ref()
+        .or(after.as_ref())
+        .map(id)
+        .unwrap_or_default();
+    (id, target.as_ref())
+}
+
+// timestamps are left out, they move on every save without anything the user would notice
+fn same_block(a: Option<&Block>, b: Option<&Block>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.page_id == b.page_id
+                && a.block_type == b.block_type
+                && a.content == b.content
+                && a.spans == b.spans
+                && a.parent_id == b.parent_id
+                && a.order == b.order
+        }
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+fn same_page(a: Option<&Page>, b: Option<&Page>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            a.title == b.title
+                && a.icon == b.icon
+                && a.cover == b.cover
+                && a.parent_id == b.parent_id
+                && a.is_archived == b.is_archived
+                && a.archived_at.is_some() == b.archived_at.is_some()
+        }
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
+
+pub(super) fn write_block(conn: &Connection, id: &str, block: Option<&Block>) -> Result<()> {
+    let Some(block) = block else {
+        conn.execute("DELETE FROM blocks WHERE id = ?1", params![id])?;
+        return Ok(());
+    };
+
+    // upsert instead of INSERT OR REPLACE, a replace is a delete and would cascade to the children
+    conn.execute(
+        "INSERT INTO blocks (id, page_id, block_type, content, parent_id, order_position, created_at, updated_at, spans)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(id) DO UPDATE SET
+             page_id = excluded.page_id,
+             block_type = excluded.block_type,
+             content = excluded.content,
+             parent_id = excluded.parent_id,
+             order_position = excluded.order_position,
+             created_at = excluded.created_at,
+             updated_at = excluded.updated_at,
+             spans = excluded.spans",
+        params![
+            block.id.to_string(),
+            block.page_id.to_string(),
+            serde_json::to_string(&block.block_type).unwrap(),
+            block.content,
+            block.parent_id.map(|id| id.to_string()),
+            block.order,
+            block.created_at.to_rfc3339(),
+            block.updated_at.to_rfc3339(),
+            spans_json(block),
+        ],
+    )?;
+    Ok(())
+}
+
+pub(super) fn write_page(conn: &Connection, id: &str, page: Option<&Page>) -> Result<()> {
+    let Some(page) = page else {
+        conn.execute("DELETE FROM pages WHERE id = ?1", params![id])?;
+        return Ok(());
+    };
+
+    conn.execute(
+        "INSERT INTO pages (id, title, icon, cover, parent_id, is_archived, created_at, updated_at, archived_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(id) DO UPDATE SET
+             title = excluded.title,
+             icon = excluded.icon,
+             cover = excluded.cover,
+             parent_id = excluded.parent_id,
+             is_archived = excluded.is_archived,
+             created_at = excluded.created_at,
+             updated_at = excluded.updated_at,
+             archived_at = excluded.archived_at",
+        params![
+            page.id,
+            page.title,
+            page.icon,
+            page.cover,
+            page.parent_id,
+            page.is_archived as i32,
+            page.created_at.to_rfc3339(),
+            page.updated_at.to_rfc3339(),
+            page.archived_at.map(|d| d.to_rfc3339()),
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::BlockType;
+    use uuid::Uuid;
+
+    #[test]
+    fn undoing_a_subpage_delete_brings_everything_back() {
+        let db = Database::new(":memory:").unwrap();
+        let undo = UndoManager::default();
+        let parent = Page::new("parent".to_string());
+        let child = Page::new("child".to_string()).with_parent(parent.id.clone());
+        db.insert_page(&parent).unwrap();
+        db.insert_page(&child).unwrap();
+
+        let child_uuid = Uuid::parse_str(&child.id).unwrap();
+        let link = Block::new(
+            Uuid::parse_str(&parent.id).unwrap(),
+            BlockType::SubPage {
+                page_id: child_uuid,
+            },
+            "child".to_string(),
+        );
+        db.insert_block(&link).unwrap();
+        db.insert_block(&Block::new(
+            child_uuid,
+            BlockType::Text,
+            "inside".to_string(),
+        ))
+        .unwrap();
+
+        // what delete_block records for a SubPage block
+        let (pages, blocks) = db.page_subtree(&child.id).unwrap();
+        let mut changes: Vec<Change> = pages
+            .into_iter()
+            .map(|p| Change::Page {
+                before: Some(p),
+                after: None,
+            })
+            .collect();
+        changes.extend(
+            blocks
+                .into_iter()
+                .chain(db.block_subtree(&link.id.to_string()).unwrap())
+                .map(|b| Change::Block {
+                    before: Some(b),
+                    after: None,
+                }),
+        );
+        db.apply_changes(&changes, false).unwrap();
+        undo.record("main", Step::new("delete block", changes));
+        assert!(db.get_page(&child.id).unwrap().is_none());
+        assert!(db.get_page_blocks(&parent.id).unwrap().is_empty());
+
+        let result = undo.undo("main", &db).unwrap().unwrap();
+        assert_eq!(result.label, "delete block");
+        assert_eq!(result.state.redo_label.as_deref(), Some("delete block"));
+        assert!(db.get_page(&child.id).unwrap().is_some());
+        assert_eq!(db.get_page_blocks(&child.id).unwrap()[0].content, "inside");
+        assert_eq!(db.get_page_blocks(&parent.id).unwrap().len(), 1);
+
+        undo.redo("main", &db).unwrap().unwrap();
+        assert!(db.get_page(&child.id).unwrap().is_none());
+        assert!(undo.redo("main", &db).unwrap().is_none());
+        // other windows have their own stacks
+        assert!(undo.undo("other", &db).unwrap().is_none());
+    }
+
+    #[test]
+    fn steps_whose_rows_changed_since_are_dropped() {
+        let db = Database::new(":memory:").unwrap();
+        let undo = UndoManager::default();
+        let page = Page::new("old".to_string());
+        db.insert_page(&page).unwrap();
+
+        let before = db.get_page(&page.id).unwrap();
+        db.update_page_title(&page.id, "new").unwrap();
+        let after = db.get_page(&page.id).unwrap();
+        undo.record(
+            "main",
+            Step::new("rename page", vec![Change::Page { before, after }]),
+        );
+
+        // an edit that never went through this stack
+        db.update_page_icon(&page.id, "🔥").unwrap();
+        assert!(matches!(undo.undo("main", &db), Err(ApplyError::Stale)));
+        let current = db.get_page(&page.id).unwrap().unwrap();
+        assert_eq!(current.title, "new");
+        assert_eq!(current.icon.as_deref(), Some("🔥"));
+        assert!(undo.state("main").undo_label.is_none());
+
+        // a purged page isnt brought back by an old step either
+        let before = db.get_page(&page.id).unwrap();
+        db.update_page_title(&page.id, "newer").unwrap();
+        let after = db.get_page(&page.id).unwrap();
+        undo.record(
+            "main",
+            Step::new("rename page", vec![Change::Page { before, after }]),
+        );
+        db.delete_page(&page.id).unwrap();
+        assert!(matches!(undo.undo("main", &db), Err(ApplyError::Stale)));
+        assert!(db.get_page(&page.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn quick_edits_to_one_block_merge_into_one_step() {
+        let db = Database::new(":memory:").unwrap();
+        let undo = UndoManager::default();
+        let page = Page::new("page".to_string());
+        db.insert_page(&page).unwrap();
+        let block = Block::new(
+            Uuid::parse_str(&page.id).unwrap(),
+            BlockType::Text,
+            "a".to_string(),
+        );
+        db.insert_block(&block).unwrap();
+
+        let mut previous = block.clone();
+        for text in ["ab", "abc"] {
+            db.update_block_content(&block.id.to_string(), text)
+                .unwrap();
+            let current = db.get_block_by_id(&block.id.to_string()).unwrap().unwrap();
+            let step = Step::new(
+                "edit block",
+                vec![Change::Block {
+                    before: Some(previous),
+                    after: Some(current.clone()),
+                }],
+            );
+            undo.record("main", step.merging(block.id.to_string()));
+            previous = current;
+        }
+
+        undo.undo("main", &db).unwrap().unwrap();
+        let restored = db.get_block_by_id(&block.id.to_string()).unwrap().unwrap();
+        assert_eq!(restored.content, "a");
+        assert_eq!(undo.state("main").undo_label, None);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::models::Workspace;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+pub const DB_FILENAME: &str = "bismuth.db";
+pub const ASSETS_DIRNAME: &str = "page_assets";
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+const REGISTRY_FILENAME: &str = "workspaces.json";
+const WORKSPACES_DIRNAME: &str = "workspaces";
+const BACKUPS_DIRNAME: &str = "backups";
+
+/*
+ * keeps track of every workspace and which one is open, saved as workspaces.json in app data
+ * the default workspace lives right in the app data dir so the page_assets people already
+ * uploaded keep working, new ones go under app_data_dir/workspaces/<name>/
+ */
+#[derive(Debug, Serialize, Deserialize)]
+struct Registry {
+    current: String,
+    workspaces: Vec<Workspace>,
+}
+
+pub struct WorkspaceManager {
+    root: PathBuf,
+    registry: Mutex<Registry>,
+    switching: Mutex<()>,
+}
+
+impl WorkspaceManager {
+    pub fn load(root: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&root).map_err(|e| format!("failed to create app data dir D: {}", e))?;
+
+        let registry_path = root.join(REGISTRY_FILENAME);
+        let registry = match fs::read_to_string(&registry_path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("failed to read {} D: {}", REGISTRY_FILENAME, e))?,
+            Err(_) => {
+                let default = Workspace::new(
+                    DEFAULT_WORKSPACE.to_string(),
+                    root.to_string_lossy().to_string(),
+                );
+                adopt_legacy_database(&root);
+                Registry {
+                    current: default.name.clone(),
+                    workspaces: vec![default],
+                }
+            }
+        };
+
+        let manager = WorkspaceManager {
+            root,
+            registry: Mutex::new(registry),
+            switching: Mutex::new(()),
+        };
+        manager.save()?;
+        Ok(manager)
+    }
+
+    // held while the open workspace changes, so anything that needs the db and current() to
+    // belong to the same workspace (backups) takes it too
+    pub fn hold(&self) -> MutexGuard<'_, ()> {
+        self.switching.lock().unwrap()
+    }
+
+    pub fn list(&self) -> Vec<Workspace> {
+        self.registry.lock().unwrap().workspaces.clone()
+    }
+
+    pub fn current(&self) -> Workspace {
+        let registry = self.registry.lock().unwrap();
+        registry
+            .workspaces
+            .iter()
+            .find(|w| w.name == registry.current)
+            .or_else(|| registry.workspaces.first())
+            .cloned()
+            .expect("workspace registry is empty D:")
+    }
+
+    pub fn get(&self, name: &str) -> Option<Workspace> {
+        self.registry
+            .lock()
+            .unwrap()
+            .workspaces
+            .iter()
+            .find(|w| w.name == name)
+            .cloned()
+    }
+
+    // makes a fresh folder under app_data_dir/workspaces, does not switch to it
+    pub fn create(&self, name: &str) -> Result<Workspace, String> {
+        self.create_with(name, |path| {
+            fs::create_dir_all(path.join(ASSETS_DIRNAME))
+                .map_err(|e| format!("failed to create workspace folder D: {}", e))
+        })
+    }
+
+    // like create but `fill` sets up the new folder (restoring a backup), removed again if it fails
+    pub fn create_with(
+        &self,
+        name: &str,
+        fill: impl FnOnce(&Path) -> Result<(), String>,
+    ) -> Result<Workspace, String> {
+        let name = validate_name(name)?;
+        if self.get(&name).is_some() {
+            return Err(format!("workspace already exists D: {}", name));
+        }
+        let path = self.folder_for(&name);
+        if path.exists() {
+            return Err(format!("workspace folder already exists D: {:?}", path));
+        }
+
+        fs::create_dir_all(&path)
+            .map_err(|e| format!("failed to create workspace folder D: {}", e))?;
+        if let Err(e) = fill(&path) {
+            if let Err(cleanup) = fs::remove_dir_all(&path) {
+                eprintln!("Failed to clean up {:?}: {}", path, cleanup);
+            }
+            return Err(e);
+        }
+
+        self.register(name, path)
+    }
+
+    // "notes" -> "notes (2)" when the name or its folder is already taken
+    pub fn available_name(&self, wanted: &str) -> String {
+        let wanted = wanted.trim();
+        let mut candidate = wanted.to_string();
+        let mut n = 2;
+        while self.get(&candidate).is_some() || self.folder_for(&candidate).exists() {
+            candidate = format!("{} ({})", wanted, n);
+            n += 1;
+        }
+        candidate
+    }
+
+    fn folder_for(&self, name: &str) -> PathBuf {
+        self.root.join(WORKSPACES_DIRNAME).join(folder_name(name))
+    }
+
+    // registers a folder that already has a bismuth.db in it (synced folder, restored backup...)
+    pub fn add_existing(&self, name: &str, path: &Path) -> Result<Workspace, String> {
+        let name = validate_name(name)?;
+        if !path.join(DB_FILENAME).exists() {
+            return Err(format!("no {} found in {:?}", DB_FILENAME, path));
+        }
+
+        self.register(name, path.to_path_buf())
+    }
+
+    fn register(&self, name: String, path: PathBuf) -> Result<Workspace, String> {
+        let workspace = Workspace::new(name, path.to_string_lossy().to_string());
+        {
+            let mut registry = self.registry.lock().unwrap();
+            if registry.workspaces.iter().any(|w| w.name == workspace.name) {
+                return Err(format!("workspace already exists D: {}", workspace.name));
+            }
+            registry.workspaces.push(workspace.clone());
+        }
+        self.save()?;
+        Ok(workspace)
+    }
+
+    // takes a workspace back out of the list, the folder itself is left alone
+    pub fn unregister(&self, name: &str) -> Result<(), String> {
+        self.registry
+            .lock()
+            .unwrap()
+            .workspaces
+            .retain(|w| w.name != name);
+        self.save()
+    }
+
+    pub fn set_current(&self, name: &str) -> Result<Workspace, String> {
+        let workspace = self
+            .get(name)
+            .ok_or_else(|| format!("workspace not found D: {}", name))?;
+        self.registry.lock().unwrap().current = workspace.name.clone();
+        self.save()?;
+        Ok(workspace)
+    }
+
+    pub fn db_path(&self, workspace: &Workspace) -> PathBuf {
+        Path::new(&workspace.path).join(DB_FILENAME)
+    }
+
+    pub fn assets_dir(&self, workspace: &Workspace) -> PathBuf {
+        Path::new(&workspace.path).join(ASSETS_DIRNAME)
+    }
+
+    // automatic backups stay in app data, away from workspace folders that might be synced
+    pub fn backups_dir(&self, workspace: &Workspace) -> PathBuf {
+        self.root
+            .join(BACKUPS_DIRNAME)
+            .join(folder_name(&workspace.name))
+    }
+
+    pub fn current_db_path(&self) -> PathBuf {
+        self.db_path(&self.current())
+    }
+
+    pub fn current_assets_dir(&self) -> PathBuf {
+        self.assets_dir(&self.current())
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(&*self.registry.lock().unwrap())
+            .map_err(|e| e.to_string())?;
+        fs::write(self.root.join(REGISTRY_FILENAME), json)
+            .map_err(|e| format!("failed to save {} D: {}", REGISTRY_FILENAME, e))
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("workspace name cant be empty".to_string());
+    }
+    if folder_name(name).is_empty() {
+        return Err(format!("invalid workspace name D: {}", name));
+    }
+    Ok(name.to_string())
+}
+
+// "My Notes!" -> "my-notes" so it's safe to use as a folder on every os
+pub fn folder_name(name: &str) -> String {
+    name.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// older builds wrote bismuth.db into whatever folder the app was launched from,
+// pull it into the default workspace the first time so nobody's notes go missing
+fn adopt_legacy_database(root: &Path) {
+    let target = root.join(DB_FILENAME);
+    let legacy = Path::new(DB_FILENAME);
+    if target.exists() || !legacy.exists() {
+        return;
+    }
+
+    match fs::copy(legacy, &target) {
+        Ok(_) => eprintln!("Copied legacy database into {:?}", target),
+        Err(e) => eprintln!("Failed to copy legacy database: {}", e),
+    }
+}
@@ -96,7 +96,6 @@ export function SearchPanel({ onNavigate, externalQuery }: SearchPanelProps) {
           <FileGroup
             key={group.page_id}
             group={group}
-            query={effectiveQuery}
             isCollapsed={collapsedGroups.has(group.page_id)}
             onToggle={() => toggleGroup(group.page_id)}
             onNavigate={onNavigate}
@@ -113,13 +112,11 @@ export function SearchPanel({ onNavigate, externalQuery }: SearchPanelProps) {
 
 function FileGroup({
   group,
-  query,
   isCollapsed,
   onToggle,
   onNavigate,
 }: {
   group: SearchFileGroup;
-  query: string;
   isCollapsed: boolean;
   onToggle: () => void;
   onNavigate: (pageId: string, blockId?: string) => void;
@@ -155,7 +152,11 @@ function FileGroup({
               }}
             >
               <div className="search-match-content">
-                <HighlightedText text={match.snippet} query={query} />
+                <HighlightedText
+                  text={match.snippet}
+                  start={match.snippet_match_start}
+                  end={match.snippet_match_end}
+                />
               </div>
             </div>
           ))}
@@ -165,28 +166,25 @@ function FileGroup({
   );
 }
 
-function HighlightedText({ text, query }: { text: string; query: string }) {
-  if (!query.trim()) return <>{text}</>;
-
-  const lower = text.toLowerCase();
-  const lowerQ = query.toLowerCase();
-  const parts: React.ReactNode[] = [];
-  let last = 0;
-  let idx = lower.indexOf(lowerQ);
-
-  while (idx !== -1) {
-    if (idx > last) parts.push(text.slice(last, idx));
-    parts.push(
-      <span key={idx} className="search-highlight">
-        {text.slice(idx, idx + query.length)}
-      </span>
-    );
-    last = idx + query.length;
-    idx = lower.indexOf(lowerQ, last);
-  }
-
-  if (last < text.length) parts.push(text.slice(last));
-  return <>{parts}</>;
+// offsets come from the backend in utf-16 units so they line up with js string indexes
+function HighlightedText({
+  text,
+  start,
+  end,
+}: {
+  text: string;
+  start: number;
+  end: number;
+}) {
+  if (end <= start) return <>{text}</>;
+
+  return (
+    <>
+      {text.slice(0, start)}
+      <span className="search-highlight">{text.slice(start, end)}</span>
+      {text.slice(end)}
+    </>
+  );
 }
 
 function ToggleButton({
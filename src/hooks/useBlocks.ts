@@ -72,17 +72,25 @@ export function useBlocks() {
     const previous = blocks;
     setBlocks(reorderedBlocks);
 
-    const changed = reorderedBlocks.filter((block, newIndex) => {
-      const oldIndex = previous.findIndex((b) => b.id === block.id);
-      return oldIndex !== newIndex;
-    });
+    // a drag moves one block, the backend shifts the ones in between
+    const first = reorderedBlocks.findIndex((b, i) => previous[i]?.id !== b.id);
+    if (first === -1) return;
+    let last = reorderedBlocks.length - 1;
+    while (last > first && previous[last]?.id === reorderedBlocks[last].id) {
+      last--;
+    }
+    const movedUp = reorderedBlocks[first].id === previous[last]?.id;
+    const moved = reorderedBlocks[movedUp ? first : last];
+
+    // the backend orders blocks per parent, so send the position among the moved block's siblings
+    const siblingIndex = reorderedBlocks
+      .filter((b) => b.parent_id === moved.parent_id)
+      .findIndex((b) => b.id === moved.id);
 
     try {
-      await Promise.all(
-        changed.map((block) =>
-          blockService.reorderBlock(block.id, reorderedBlocks.indexOf(block)),
-        ),
-      );
+      await blockService.reorderBlock(moved.id, siblingIndex);
+      // nested blocks move along with their parent, reload to get the real tree order
+      setBlocks(await blockService.getPageBlocks(moved.page_id));
     } catch (error) {
       console.error("Failed to reorder blocks:", error);
       setBlocks(previous);
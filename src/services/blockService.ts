@@ -1,35 +1,98 @@
 import { invoke } from "@tauri-apps/api/core";
 import type { Block } from "../types/Block";
+import type { BlockNode } from "../types/BlockNode";
+import type { BlockOp } from "../types/BlockOp";
+import type { BlockTreeOptions } from "../types/BlockTreeOptions";
 import type { BlockType } from "../types/BlockType";
+import type { DeleteReport } from "../types/DeleteReport";
+import type { LinkRepair } from "../types/LinkRepair";
+import type { TextSpan } from "../types/TextSpan";
 
 export const blockService = {
   async getPageBlocks(pageId: string): Promise<Block[]> {
     return await invoke<Block[]>("get_page_blocks", { pageId });
   },
 
+  // nested and ordered per parent, pass rootId to load the children of a collapsed block
+  async getPageBlockTree(
+    pageId: string,
+    options?: BlockTreeOptions,
+  ): Promise<BlockNode[]> {
+    return await invoke<BlockNode[]>("get_page_block_tree", {
+      pageId,
+      options: options ?? null,
+    });
+  },
+
   async createBlock(
     pageId: string,
     blockType: BlockType,
     content: string,
     parentId: string | null,
+    index?: number,
   ): Promise<Block> {
     return await invoke<Block>("create_block", {
       pageId,
       blockType,
       content,
       parentId,
+      index: index ?? null,
     });
   },
 
-  async deleteBlock(blockId: string): Promise<void> {
-    return await invoke("delete_block", { blockId });
+  // deleting a SubPage block deletes its page, which reports or repairs links like deletePage
+  async deleteBlock(blockId: string, repair?: LinkRepair): Promise<DeleteReport> {
+    return await invoke<DeleteReport>("delete_block", {
+      blockId,
+      repair: repair ?? null,
+    });
   },
 
   async updateBlockContent(blockId: string, content: string): Promise<Block> {
     return await invoke<Block>("update_block_content", { blockId, content });
   },
 
+  async updateBlockRichText(blockId: string, spans: TextSpan[]): Promise<Block> {
+    return await invoke<Block>("update_block_rich_text", { blockId, spans });
+  },
+
   async reorderBlock(blockId: string, newOrder: number): Promise<Block> {
     return await invoke<Block>("reorder_block", { blockId, newOrder });
   },
+
+  async indentBlock(blockId: string): Promise<Block> {
+    return await invoke<Block>("indent_block", { blockId });
+  },
+
+  async outdentBlock(blockId: string): Promise<Block> {
+    return await invoke<Block>("outdent_block", { blockId });
+  },
+
+  // afterBlockId null puts it first under the new parent
+  async moveBlock(
+    blockId: string,
+    newParentId: string | null,
+    afterBlockId: string | null,
+  ): Promise<Block> {
+    return await invoke<Block>("move_block", {
+      blockId,
+      newParentId,
+      afterBlockId,
+    });
+  },
+
+  async moveBlocksToPage(
+    blockIds: string[],
+    targetPageId: string,
+  ): Promise<Block[]> {
+    return await invoke<Block[]>("move_blocks_to_page", {
+      blockIds,
+      targetPageId,
+    });
+  },
+
+  // all or nothing, rejects with a BlockOpError saying which op failed
+  async applyBlockOps(ops: BlockOp[]): Promise<Block[]> {
+    return await invoke<Block[]>("apply_block_ops", { ops });
+  },
 };
@@ -0,0 +1,27 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { ExportSummary } from "../types/ExportSummary";
+
+export const exportService = {
+  // writes the page and its subpages as .md files (plus an assets folder) into destination
+  async exportPageMarkdown(
+    pageId: string,
+    destination: string,
+  ): Promise<ExportSummary> {
+    return await invoke<ExportSummary>("export_page_markdown", {
+      pageId,
+      destination,
+    });
+  },
+
+  // html files with a sidebar, style.css and index.html, ready for any static web server.
+  // the whole workspace when no pageId is given
+  async exportSite(
+    destination: string,
+    pageId?: string,
+  ): Promise<ExportSummary> {
+    return await invoke<ExportSummary>("export_site", {
+      pageId: pageId ?? null,
+      destination,
+    });
+  },
+};
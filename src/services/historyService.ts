@@ -0,0 +1,26 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { Page } from "../types/Page";
+import type { PageVersion } from "../types/PageVersion";
+import type { PageVersionInfo } from "../types/PageVersionInfo";
+
+export const historyService = {
+  // newest first
+  async listPageVersions(pageId: string): Promise<PageVersionInfo[]> {
+    return await invoke<PageVersionInfo[]>("list_page_versions", { pageId });
+  },
+
+  async getPageVersion(versionId: string): Promise<PageVersion> {
+    return await invoke<PageVersion>("get_page_version", { versionId });
+  },
+
+  async createSavePoint(
+    pageId: string,
+    label: string | null = null,
+  ): Promise<PageVersionInfo> {
+    return await invoke<PageVersionInfo>("create_save_point", { pageId, label });
+  },
+
+  async restorePageVersion(versionId: string): Promise<Page> {
+    return await invoke<Page>("restore_page_version", { versionId });
+  },
+};
@@ -0,0 +1,31 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { ImportSummary } from "../types/ImportSummary";
+
+export const importService = {
+  // a .md file or a folder of them, becomes a new page (under parentId when given)
+  async importMarkdown(
+    path: string,
+    parentId?: string,
+  ): Promise<ImportSummary> {
+    return await invoke<ImportSummary>("import_markdown", {
+      path,
+      parentId: parentId ?? null,
+    });
+  },
+
+  // a notion export zip (markdown & csv or html), or the folder it was unzipped into
+  async importNotion(path: string, parentId?: string): Promise<ImportSummary> {
+    return await invoke<ImportSummary>("import_notion", {
+      path,
+      parentId: parentId ?? null,
+    });
+  },
+
+  // an obsidian or logseq vault folder, [[wikilinks]] become page links
+  async importVault(path: string, parentId?: string): Promise<ImportSummary> {
+    return await invoke<ImportSummary>("import_vault", {
+      path,
+      parentId: parentId ?? null,
+    });
+  },
+};
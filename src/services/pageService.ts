@@ -1,5 +1,11 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { Backlinks } from "../types/Backlinks";
+import type { DeleteReport } from "../types/DeleteReport";
+import type { GraphOptions } from "../types/GraphOptions";
+import type { LinkReference } from "../types/LinkReference";
+import type { LinkRepair } from "../types/LinkRepair";
 import type { Page } from "../types/Page";
+import type { PageGraph } from "../types/PageGraph";
 
 export interface PageWithChildren extends Page {
   children?: PageWithChildren[];
@@ -27,6 +33,32 @@ export const pageService = {
     return await invoke<Page>("create_nested_page", { title, parentId });
   },
 
+  // moves the page and its subpages to the trash
+  async archivePage(pageId: string): Promise<void> {
+    return await invoke<void>("archive_page", { pageId });
+  },
+
+  async restorePage(pageId: string): Promise<Page> {
+    return await invoke<Page>("restore_page", { pageId });
+  },
+
+  async listTrash(): Promise<Page[]> {
+    return await invoke<Page[]>("list_trash");
+  },
+
+  async emptyTrash(): Promise<number> {
+    return await invoke<number>("empty_trash");
+  },
+
+  // null means trashed pages are kept forever
+  async getTrashRetention(): Promise<number | null> {
+    return await invoke<number | null>("get_trash_retention");
+  },
+
+  async setTrashRetention(days: number | null): Promise<void> {
+    return await invoke<void>("set_trash_retention", { days });
+  },
+
   async uploadPageAsset(
     pageId: string,
     sourcePath: string,
@@ -38,4 +70,37 @@ export const pageService = {
       assetType,
     });
   },
+
+  // pages linking here, and blocks that mention the title without linking it
+  async getBacklinks(pageId: string): Promise<Backlinks> {
+    return await invoke<Backlinks>("get_backlinks", { pageId });
+  },
+
+  // deletes for good, links from other pages are either reported as broken or repaired
+  async deletePage(pageId: string, repair?: LinkRepair): Promise<DeleteReport> {
+    return await invoke<DeleteReport>("delete_page", {
+      pageId,
+      repair: repair ?? null,
+    });
+  },
+
+  // what deletePage would break, to ask before deleting
+  async getInboundLinks(pageId: string): Promise<LinkReference[]> {
+    return await invoke<LinkReference[]>("get_inbound_links", { pageId });
+  },
+
+  async findBrokenLinks(): Promise<LinkReference[]> {
+    return await invoke<LinkReference[]>("find_broken_links");
+  },
+
+  async repairLinks(blockIds: string[], repair: LinkRepair): Promise<number> {
+    return await invoke<number>("repair_links", { blockIds, repair });
+  },
+
+  // nodes and typed edges for the graph view, local around options.center when set
+  async getPageGraph(options?: GraphOptions): Promise<PageGraph> {
+    return await invoke<PageGraph>("get_page_graph", {
+      options: options ?? null,
+    });
+  },
 };
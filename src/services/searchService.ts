@@ -1,4 +1,7 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { ReplaceResponse } from "../types/ReplaceResponse";
+import type { SearchFilters } from "../types/SearchFilters";
+import type { SearchMode } from "../types/SearchMode";
 import type { SearchResponse } from "../types/SearchResponse";
 
 export const searchService = {
@@ -6,11 +9,73 @@ export const searchService = {
     query: string,
     caseSensitive: boolean,
     wholeWord: boolean,
+    mode: SearchMode = "Literal",
+    filters: SearchFilters | null = null,
   ): Promise<SearchResponse> {
     return await invoke<SearchResponse>("search_blocks", {
       query,
       caseSensitive,
       wholeWord,
+      mode,
+      filters,
+    });
+  },
+
+  async replaceInBlock(
+    blockId: string,
+    search: string,
+    replacement: string,
+    caseSensitive: boolean,
+    wholeWord: boolean,
+    dryRun: boolean,
+    mode: SearchMode = "Literal",
+  ): Promise<ReplaceResponse> {
+    return await invoke<ReplaceResponse>("replace_in_block", {
+      blockId,
+      search,
+      replacement,
+      caseSensitive,
+      wholeWord,
+      mode,
+      dryRun,
+    });
+  },
+
+  async replaceInPage(
+    pageId: string,
+    search: string,
+    replacement: string,
+    caseSensitive: boolean,
+    wholeWord: boolean,
+    dryRun: boolean,
+    mode: SearchMode = "Literal",
+  ): Promise<ReplaceResponse> {
+    return await invoke<ReplaceResponse>("replace_in_page", {
+      pageId,
+      search,
+      replacement,
+      caseSensitive,
+      wholeWord,
+      mode,
+      dryRun,
+    });
+  },
+
+  async replaceAll(
+    search: string,
+    replacement: string,
+    caseSensitive: boolean,
+    wholeWord: boolean,
+    dryRun: boolean,
+    mode: SearchMode = "Literal",
+  ): Promise<ReplaceResponse> {
+    return await invoke<ReplaceResponse>("replace_all", {
+      search,
+      replacement,
+      caseSensitive,
+      wholeWord,
+      mode,
+      dryRun,
     });
   },
 };
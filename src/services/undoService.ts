@@ -0,0 +1,18 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { UndoResult } from "../types/UndoResult";
+import type { UndoState } from "../types/UndoState";
+
+// the stacks are per window on the backend, null means there was nothing to undo/redo
+export const undoService = {
+  async undo(): Promise<UndoResult | null> {
+    return await invoke<UndoResult | null>("undo");
+  },
+
+  async redo(): Promise<UndoResult | null> {
+    return await invoke<UndoResult | null>("redo");
+  },
+
+  async getUndoState(): Promise<UndoState> {
+    return await invoke<UndoState>("get_undo_state");
+  },
+};
@@ -0,0 +1,60 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { BackupManifest } from "../types/BackupManifest";
+import type { BackupSettings } from "../types/BackupSettings";
+import type { BackupSummary } from "../types/BackupSummary";
+import type { Workspace } from "../types/Workspace";
+
+export const workspaceService = {
+  async listWorkspaces(): Promise<Workspace[]> {
+    return await invoke<Workspace[]>("list_workspaces");
+  },
+
+  async getCurrentWorkspace(): Promise<Workspace> {
+    return await invoke<Workspace>("get_current_workspace");
+  },
+
+  async createWorkspace(name: string): Promise<Workspace> {
+    return await invoke<Workspace>("create_workspace", { name });
+  },
+
+  async openWorkspace(path: string, name: string | null): Promise<Workspace> {
+    return await invoke<Workspace>("open_workspace", { path, name });
+  },
+
+  async switchWorkspace(name: string): Promise<Workspace> {
+    return await invoke<Workspace>("switch_workspace", { name });
+  },
+
+  // one .bismuth file with the database and page_assets, destination can be a folder
+  async exportWorkspace(destination: string): Promise<BackupSummary> {
+    return await invoke<BackupSummary>("export_workspace", { destination });
+  },
+
+  // restores into a new workspace, the current one is left alone
+  async importWorkspace(path: string, name: string | null): Promise<Workspace> {
+    return await invoke<Workspace>("import_workspace", { path, name });
+  },
+
+  // automatic backups of the current workspace, newest first
+  async listBackups(): Promise<BackupSummary[]> {
+    return await invoke<BackupSummary[]>("list_backups");
+  },
+
+  async backupNow(): Promise<BackupSummary> {
+    return await invoke<BackupSummary>("backup_now");
+  },
+
+  // path comes from listBackups, the current state is backed up before it's replaced
+  // force restores even when that backup fails (a corrupt db usually cant be backed up)
+  async restoreBackup(path: string, force = false): Promise<BackupManifest> {
+    return await invoke<BackupManifest>("restore_backup", { path, force });
+  },
+
+  async getBackupSettings(): Promise<BackupSettings> {
+    return await invoke<BackupSettings>("get_backup_settings");
+  },
+
+  async setBackupSettings(settings: BackupSettings): Promise<void> {
+    await invoke("set_backup_settings", { settings });
+  },
+};
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinkMention } from "./LinkMention";
+import type { Page } from "./Page";
+
+export type BacklinkGroup = { page: Page, mentions: Array<LinkMention>, };
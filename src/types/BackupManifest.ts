@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type BackupManifest = { format_version: number, schema_version: number, app_version: string, workspace: string, created_at: string, page_count: number, asset_count: number, };
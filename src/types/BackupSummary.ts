@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BackupManifest } from "./BackupManifest";
+
+export type BackupSummary = { path: string, size: number, manifest: BackupManifest, };
@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { BlockType } from "./BlockType";
+import type { TextSpan } from "./TextSpan";
 
-export type Block = { id: string, page_id: string, block_type: BlockType, content: string, parent_id: string | null, order: number, created_at: string, updated_at: string, };
+export type Block = { id: string, page_id: string, block_type: BlockType, content: string, spans: Array<TextSpan> | null, parent_id: string | null, order: number, created_at: string, updated_at: string, };
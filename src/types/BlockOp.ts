@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { BlockType } from "./BlockType";
+import type { TextSpan } from "./TextSpan";
+
+export type BlockOp = { "type": "Insert", "data": { id: string | null, page_id: string, parent_id: string | null, index: number | null, block_type: BlockType, content: string, spans: Array<TextSpan> | null, } } | { "type": "Update", "data": { block_id: string, block_type: BlockType | null, content: string | null, spans: Array<TextSpan> | null, } } | { "type": "Delete", "data": { block_id: string, } } | { "type": "Move", "data": { block_id: string, parent_id: string | null, index: number | null, } };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type GraphNode = { id: string, title: string, icon: string | null, parent_id: string | null, is_archived: boolean, inbound_links: number, outbound_links: number, };
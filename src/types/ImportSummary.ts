@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Page } from "./Page";
+
+export type ImportSummary = { root_page: Page, page_count: number, block_count: number, assets: Array<string>, warnings: Array<string>, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinkKind } from "./LinkKind";
+
+export type LinkMention = { block_id: string, kind: LinkKind, snippet: string, context: string | null, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LinkKind } from "./LinkKind";
+import type { Page } from "./Page";
+
+export type LinkReference = { block_id: string, page: Page, target_page_id: string, kind: LinkKind, content: string, };
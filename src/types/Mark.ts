@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type Mark = { "type": "Bold" } | { "type": "Italic" } | { "type": "Strikethrough" } | { "type": "Code" } | { "type": "Color", "data": string } | { "type": "Highlight", "data": string };
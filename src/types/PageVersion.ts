@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Block } from "./Block";
+import type { PageVersionInfo } from "./PageVersionInfo";
+
+export type PageVersion = { info: PageVersionInfo, icon: string | null, cover: string | null, blocks: Array<Block>, };
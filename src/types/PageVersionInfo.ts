@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { VersionKind } from "./VersionKind";
+
+export type PageVersionInfo = { id: string, page_id: string, kind: VersionKind, label: string | null, title: string, block_count: number, created_at: string, };
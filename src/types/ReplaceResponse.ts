@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Block } from "./Block";
+import type { ReplaceChange } from "./ReplaceChange";
+
+export type ReplaceResponse = { changes: Array<ReplaceChange>, blocks: Array<Block>, total_replacements: number, dry_run: boolean, };
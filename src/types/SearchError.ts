@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SearchMode } from "./SearchMode";
+
+export type SearchError = { "kind": "InvalidRegex", "data": { message: string, } } | { "kind": "InvalidQuery", "data": { message: string, } } | { "kind": "UnsupportedMode", "data": { mode: SearchMode, message: string, } } | { "kind": "Database", "data": { message: string, } };
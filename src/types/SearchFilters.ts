@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ArchivedFilter } from "./ArchivedFilter";
+import type { BlockTypeFilter } from "./BlockTypeFilter";
+
+export type SearchFilters = { block_types: Array<BlockTypeFilter>, within: string | null, created_after: string | null, created_before: string | null, updated_after: string | null, updated_before: string | null, archived: ArchivedFilter, };
@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type SearchMatch = { block_id: string, content: string, block_type: string, match_start: number, match_end: number, snippet: string, order: number, };
+export type SearchMatch = { block_id: string, content: string, block_type: string, match_start: number, match_end: number, snippet: string, snippet_match_start: number, snippet_match_end: number, order: number, score: number, };
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Mark } from "./Mark";
+
+export type TextSpan = { text: string, marks: Array<Mark>, link: string | null, };